@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use arrow::datatypes::{Field, Schema};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{Column, DataPacket};
+
+impl DataPacket {
+    /// Converts the block into an Arrow record batch with a field per
+    /// column, see [`clickhouse_datatypes::Column::into_arrow`] for how the
+    /// types map. Errors name the column that could not be converted.
+    pub fn into_record_batch(self) -> Result<RecordBatch> {
+        self.into_record_batch_with(false)
+    }
+
+    /// Like [`DataPacket::into_record_batch`], but converts `String` columns
+    /// to `Utf8`, failing on values that are not UTF-8.
+    pub fn into_record_batch_utf8(self) -> Result<RecordBatch> {
+        self.into_record_batch_with(true)
+    }
+
+    fn into_record_batch_with(self, utf8: bool) -> Result<RecordBatch> {
+        let (fields, arrays): (Vec<_>, Vec<_>) = self
+            .columns
+            .into_iter()
+            .map(|column| {
+                let nullable = column.column_type.is_nullable();
+                let array = if utf8 {
+                    column.data.into_arrow_utf8()
+                } else {
+                    column.data.into_arrow()
+                };
+                let array = array.map_err(|e| {
+                    ClickHouseClientError::EncodeError(format!(
+                        "column `{}`: {e}",
+                        column.name
+                    ))
+                })?;
+                let field = Field::new(
+                    column.name,
+                    array.data_type().clone(),
+                    nullable,
+                );
+                Ok((field, array))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let options = RecordBatchOptions::new()
+            .with_row_count(Some(self.rows_count as usize));
+        RecordBatch::try_new_with_options(
+            Arc::new(Schema::new(fields)),
+            arrays,
+            &options,
+        )
+        .map_err(|e| ClickHouseClientError::EncodeError(e.to_string()))
+    }
+
+    /// Builds a block shaped like `header` from the batch columns of the
+    /// same names, converting them to the header's types.
+    pub fn from_record_batch(
+        batch: &RecordBatch,
+        header: &DataPacket,
+    ) -> Result<DataPacket> {
+        let columns = header
+            .columns
+            .iter()
+            .map(|column| {
+                let error = |message: String| {
+                    ClickHouseClientError::EncodeError(format!(
+                        "column `{}`: {message}",
+                        column.name
+                    ))
+                };
+                let array =
+                    batch.column_by_name(&column.name).ok_or_else(|| {
+                        error("not in the record batch".to_owned())
+                    })?;
+                let data = clickhouse_datatypes::Column::from_arrow(
+                    array,
+                    &column.column_type,
+                )
+                .map_err(|e| error(e.to_string()))?;
+                Ok(Column {
+                    name: column.name.clone(),
+                    column_type: column.column_type.clone(),
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DataPacket {
+            columns_count: columns.len() as u64,
+            rows_count: batch.num_rows() as u64,
+            columns,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use arrow::array::{Array, AsArray, Int64Array, RecordBatch, StringArray};
+    use arrow::datatypes::{DataType as ArrowType, UInt64Type};
+
+    use crate::client::test::{block, data, expected_insert, response, serve};
+    use crate::protocol::client::QueryPacket;
+    use crate::protocol::server::ServerPacketCode;
+    use crate::{ClickHouseClientError, Client, ClientOptions};
+
+    #[tokio::test]
+    async fn test_fetch_and_insert() -> Result<()> {
+        let header = block(vec![], vec![])?;
+        let rows = block(vec![1, 2], vec![Some("a"), None])?;
+        let mut responses = response(&[header.clone(), rows.clone()]).await?;
+        responses.extend(response(&[header]).await?);
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let batches = client.query("SELECT id, name").fetch_arrow().await?;
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(
+            batch.column(0).as_primitive::<UInt64Type>().values(),
+            &[1, 2]
+        );
+        assert!(matches!(
+            batch.column(1).data_type(),
+            ArrowType::Dictionary(_, values) if **values == ArrowType::Binary
+        ));
+        assert!(batch.schema().field(1).is_nullable());
+        let nulls = batch.column(1).logical_nulls();
+        assert!(nulls.is_some_and(|nulls| nulls.is_null(1)));
+
+        // the batch columns are cast to the types of the table
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(vec![1, 2])) as _),
+            (
+                "name",
+                Arc::new(StringArray::from(vec![Some("a"), None])) as _,
+            ),
+        ])?;
+        client
+            .insert_arrow("events")
+            .execute_batches([batch])
+            .await?;
+        drop(client);
+
+        let query = QueryPacket::new("INSERT INTO events (id, name) VALUES");
+        let expected = expected_insert(&query, &[rows]).await?;
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_string_batches() -> Result<()> {
+        // only the last block holds a value that is not UTF-8
+        let mut result = Vec::new();
+        for values in [vec![], vec![b"a".to_vec()], vec![vec![0xff]]] {
+            let column = clickhouse_datatypes::Column::String(
+                values.into_iter().map(Into::into).collect(),
+            );
+            let block = vec![("name", column)];
+            result.extend(data(ServerPacketCode::Data, block).await?);
+        }
+        result.push(ServerPacketCode::EndOfStream as u8);
+        let (addr, _) = serve(result.repeat(2)).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let batches = client.query("SELECT name").fetch_arrow().await?;
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].schema(), batches[1].schema());
+        assert_eq!(batches[0].column(0).data_type(), &ArrowType::Binary);
+        assert_eq!(batches[1].column(0).as_binary::<i32>().value(0), [0xff]);
+
+        let error = client.query("SELECT name").fetch_arrow_utf8().await;
+        assert!(matches!(error, Err(ClickHouseClientError::EncodeError(_))));
+        Ok(())
+    }
+}
@@ -10,6 +10,10 @@ pub trait ClickHouseEncoder {
 
     async fn encode_i32(&mut self, x: i32) -> Result<usize>;
 
+    async fn encode_i64(&mut self, x: i64) -> Result<usize>;
+
+    async fn encode_u64(&mut self, x: u64) -> Result<usize>;
+
     async fn encode_var_uint(&mut self, x: u64) -> Result<usize>;
 
     async fn encode_string(
@@ -42,6 +46,16 @@ where
         Ok(4)
     }
 
+    async fn encode_i64(&mut self, x: i64) -> Result<usize> {
+        self.write_i64_le(x).await?;
+        Ok(8)
+    }
+
+    async fn encode_u64(&mut self, x: u64) -> Result<usize> {
+        self.write_u64_le(x).await?;
+        Ok(8)
+    }
+
     async fn encode_var_uint(&mut self, x: u64) -> Result<usize> {
         let mut i = 0;
         let mut x = x;
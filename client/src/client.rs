@@ -0,0 +1,795 @@
+use std::future::Future;
+use std::io;
+use std::time::{Duration, Instant};
+
+use clickhouse_datatypes::Row;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::timeout;
+
+use crate::connection::Connection;
+use crate::error::{ClickHouseClientError, Result};
+use crate::external::ExternalTable;
+use crate::insert::Insert;
+use crate::inserter::Inserter;
+use crate::protocol::client::{
+    ClickHouseWriteCancelPacket, ClickHouseWriteDataPacket,
+    ClickHouseWriteHelloPacket, ClickHouseWritePingPacket,
+    ClickHouseWriteQueryPacket, ClientPacketCode, DataPacket, HelloPacket,
+    QueryPacket,
+};
+use crate::protocol::server::{
+    self, ClickHouseRead, ExceptionPacket, ProgressPacket, ServerPacketCode,
+    TableColumnsPacket,
+};
+use crate::protocol::{
+    CLICKHOUSE_DEFAULT_DATABASE, CLICKHOUSE_DEFAULT_PASSWORD,
+    CLICKHOUSE_DEFAULT_USERNAME, CLICKHOUSE_PROTOCOL_VERSION,
+};
+use crate::query::Query;
+
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub addr: String,
+    pub database: String,
+    pub username: String,
+    pub password: String,
+    /// The longest a connection, its TLS handshake included, may take.
+    pub connect_timeout: Option<Duration>,
+    /// The longest to wait for each packet from the server.
+    pub read_timeout: Option<Duration>,
+    /// The longest a write to the server may take.
+    pub write_timeout: Option<Duration>,
+    /// The longest a query may take, from sending it until the end of its
+    /// response, or until an insert ends.
+    pub query_timeout: Option<Duration>,
+    /// How long the connection idles before TCP keepalive probes are sent,
+    /// or `None` to send none.
+    pub tcp_keepalive: Option<Duration>,
+    pub tcp_nodelay: bool,
+    #[cfg(feature = "tls")]
+    pub tls: Option<crate::TlsOptions>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:9000".to_owned(),
+            database: CLICKHOUSE_DEFAULT_DATABASE.to_owned(),
+            username: CLICKHOUSE_DEFAULT_USERNAME.to_owned(),
+            password: CLICKHOUSE_DEFAULT_PASSWORD.to_owned(),
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: None,
+            write_timeout: None,
+            query_timeout: None,
+            tcp_keepalive: None,
+            tcp_nodelay: true,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl ClientOptions {
+    pub fn addr(mut self, addr: impl Into<String>) -> ClientOptions {
+        self.addr = addr.into();
+        self
+    }
+
+    pub fn database(mut self, database: impl Into<String>) -> ClientOptions {
+        self.database = database.into();
+        self
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> ClientOptions {
+        self.username = username.into();
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> ClientOptions {
+        self.password = password.into();
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientOptions {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientOptions {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn write_timeout(mut self, timeout: Duration) -> ClientOptions {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    pub fn query_timeout(mut self, timeout: Duration) -> ClientOptions {
+        self.query_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, idle: Duration) -> ClientOptions {
+        self.tcp_keepalive = Some(idle);
+        self
+    }
+
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> ClientOptions {
+        self.tcp_nodelay = nodelay;
+        self
+    }
+
+    /// Connects over TLS, as the secure native port requires.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: crate::TlsOptions) -> ClientOptions {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+/// A connection to a server, which runs one query at a time.
+pub struct Client {
+    stream: BufStream<Connection>,
+    server: server::HelloPacket,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    query_timeout: Option<Duration>,
+    /// When the query being run has to have ended by.
+    deadline: Option<Instant>,
+    /// Set from sending a query until the end of its response has been
+    /// read, so that a response left behind by a dropped stream is
+    /// cancelled before the next query.
+    pending: bool,
+    /// Set once the pending query has been cancelled, by a stream that was
+    /// dropped, so that only the rest of its response is left to skip.
+    cancelled: bool,
+    /// Set while a packet is read or written. A future dropped halfway
+    /// through one, or a timeout, leaves it set, and the connection
+    /// unusable.
+    broken: bool,
+}
+
+impl Client {
+    /// Connects and says hello, failing if the server rejects the
+    /// credentials.
+    pub async fn connect(options: ClientOptions) -> Result<Client> {
+        let stream = within(
+            options.connect_timeout,
+            ClickHouseClientError::ConnectTimeout,
+            dial(&options),
+        )
+        .await?;
+        let mut stream = BufStream::new(stream);
+
+        let hello = HelloPacket::default()
+            .database(options.database)
+            .username(options.username)
+            .password(options.password);
+        within(
+            options.write_timeout,
+            ClickHouseClientError::WriteTimeout,
+            async {
+                stream.write_hello_packet(hello).await?;
+                Ok(stream.flush().await?)
+            },
+        )
+        .await?;
+
+        let server = within(
+            options.read_timeout,
+            ClickHouseClientError::ReadTimeout,
+            async {
+                match stream.read_packet_code().await? {
+                    ServerPacketCode::Hello => stream.read_hello_packet().await,
+                    ServerPacketCode::Exception => {
+                        Err(exception(stream.read_exception_packet().await?))
+                    }
+                    code => Err(unexpected(code)),
+                }
+            },
+        )
+        .await?;
+        within(
+            options.write_timeout,
+            ClickHouseClientError::WriteTimeout,
+            async {
+                stream.write_hello_addendum(server.revision).await?;
+                Ok(stream.flush().await?)
+            },
+        )
+        .await?;
+        Ok(Client {
+            stream,
+            server,
+            read_timeout: options.read_timeout,
+            write_timeout: options.write_timeout,
+            query_timeout: options.query_timeout,
+            deadline: None,
+            pending: false,
+            cancelled: false,
+            broken: false,
+        })
+    }
+
+    /// The hello the server answered with.
+    pub fn server(&self) -> &server::HelloPacket {
+        &self.server
+    }
+
+    /// The protocol revision spoken on this connection, the older one of
+    /// the client and the server.
+    pub fn revision(&self) -> u64 {
+        self.server.revision.min(CLICKHOUSE_PROTOCOL_VERSION)
+    }
+
+    pub async fn ping(&mut self) -> Result<()> {
+        self.finish().await?;
+        self.broken = true;
+        let stream = &mut self.stream;
+        within(
+            self.write_timeout,
+            ClickHouseClientError::WriteTimeout,
+            async {
+                stream.write_ping_packet().await?;
+                Ok(stream.flush().await?)
+            },
+        )
+        .await?;
+        let result = within(
+            self.read_timeout,
+            ClickHouseClientError::ReadTimeout,
+            async {
+                match stream.read_packet_code().await? {
+                    ServerPacketCode::Pong => Ok(Ok(())),
+                    ServerPacketCode::Exception => Ok(Err(exception(
+                        stream.read_exception_packet().await?,
+                    ))),
+                    code => Err(unexpected(code)),
+                }
+            },
+        )
+        .await?;
+        self.broken = false;
+        result
+    }
+
+    /// Starts building a query, which runs once its result is asked for.
+    pub fn query(&mut self, query: impl Into<String>) -> Query<'_> {
+        Query::new(self, query)
+    }
+
+    /// Starts building an insert of `T` rows into `table`, whose columns
+    /// are checked against the fields of `T` before any row is sent.
+    pub fn insert<T: Row>(
+        &mut self,
+        table: impl Into<String>,
+    ) -> Insert<'_, T> {
+        Insert::new(self, table)
+    }
+
+    /// Starts building an inserter of `T` rows into `table`, which batches
+    /// the rows written to it into blocks and inserts.
+    pub fn inserter<T: Row>(
+        &mut self,
+        table: impl Into<String>,
+    ) -> Inserter<'_, T> {
+        Inserter::new(self, table)
+    }
+
+    /// Starts building an insert of Arrow record batches into `table`,
+    /// into the columns named by the schema of the first batch.
+    #[cfg(feature = "arrow")]
+    pub fn insert_arrow(
+        &mut self,
+        table: impl Into<String>,
+    ) -> Insert<'_, arrow::array::RecordBatch> {
+        Insert::new(self, table)
+    }
+
+    /// Starts building an insert of a Polars data frame into `table`, into
+    /// the columns of the frame.
+    #[cfg(feature = "polars")]
+    pub fn insert_data_frame(
+        &mut self,
+        table: impl Into<String>,
+    ) -> Insert<'_, polars::frame::DataFrame> {
+        Insert::new(self, table)
+    }
+
+    /// Sends a query followed by its external tables, starting its
+    /// deadline.
+    pub(crate) async fn send_query(
+        &mut self,
+        packet: &QueryPacket,
+        external: &[ExternalTable],
+    ) -> Result<()> {
+        self.finish().await?;
+        self.deadline =
+            self.query_timeout.map(|timeout| Instant::now() + timeout);
+        let revision = self.revision();
+        let limit = self.limit(self.write_timeout);
+        self.broken = true;
+        let stream = &mut self.stream;
+        within(limit, ClickHouseClientError::WriteTimeout, async {
+            stream.write_query_packet(packet, revision).await?;
+            for table in external {
+                stream.write_data_packet(table.block(), revision).await?;
+            }
+            // the empty block ends the external tables
+            stream
+                .write_data_packet(&DataPacket::default(), revision)
+                .await?;
+            Ok(stream.flush().await?)
+        })
+        .await?;
+        self.broken = false;
+        self.pending = true;
+        Ok(())
+    }
+
+    /// Writes a block of an insert, returning the bytes written. The block
+    /// is buffered until [`Client::flush`].
+    pub(crate) async fn write_block(
+        &mut self,
+        block: &DataPacket,
+    ) -> Result<usize> {
+        let revision = self.revision();
+        self.broken = true;
+        let len = within(
+            self.limit(self.write_timeout),
+            ClickHouseClientError::WriteTimeout,
+            self.stream.write_data_packet(block, revision),
+        )
+        .await?;
+        self.broken = false;
+        Ok(len)
+    }
+
+    pub(crate) async fn flush(&mut self) -> Result<()> {
+        self.broken = true;
+        within(
+            self.limit(self.write_timeout),
+            ClickHouseClientError::WriteTimeout,
+            async { Ok(self.stream.flush().await?) },
+        )
+        .await?;
+        self.broken = false;
+        Ok(())
+    }
+
+    /// Readies the connection for the next query. The response of a query
+    /// that was not read to its end, because its stream was dropped, is
+    /// cancelled and whatever the server still sends of it is skipped.
+    async fn finish(&mut self) -> Result<()> {
+        if self.broken {
+            return Err(ClickHouseClientError::ConnectionBroken);
+        }
+        if !self.pending {
+            return Ok(());
+        }
+        // the response is drained even if the query ran out of time
+        self.deadline = None;
+        if !self.cancelled {
+            self.broken = true;
+            let stream = &mut self.stream;
+            within(
+                self.write_timeout,
+                ClickHouseClientError::WriteTimeout,
+                async {
+                    stream.write_cancel_packet().await?;
+                    Ok(stream.flush().await?)
+                },
+            )
+            .await?;
+            self.broken = false;
+        }
+        loop {
+            match self.receive(&Sinks::default()).await {
+                Ok(Response::EndOfStream)
+                | Err(ClickHouseClientError::ServerException { .. }) => {
+                    return Ok(())
+                }
+                Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Cancels the pending query if the socket takes the packet without
+    /// waiting, so that a dropped stream does not leave the query running
+    /// until the client is next used. Otherwise [`Client::finish`] does.
+    pub(crate) fn try_cancel(&mut self) {
+        if self.broken || !self.pending || self.cancelled {
+            return;
+        }
+        // the write buffer is empty, as every write is flushed before a
+        // response is read
+        let code = [ClientPacketCode::Cancel as u8];
+        self.cancelled =
+            matches!(self.stream.get_ref().try_write(&code), Ok(1));
+    }
+
+    /// Reads the next packet of a query's response that a caller acts on.
+    /// Progress, logs and profile events go to `sinks`, or are skipped if
+    /// it has no sender for them. An exception is returned as the error.
+    ///
+    /// Waiting for a packet can be given up, as by dropping a stream, and
+    /// the query is then cancelled by [`Client::finish`]. Giving up once a
+    /// packet has begun to arrive breaks the connection.
+    pub(crate) async fn receive(&mut self, sinks: &Sinks) -> Result<Response> {
+        let response = loop {
+            let limit = self.limit(self.read_timeout);
+            let packet = within(
+                limit,
+                ClickHouseClientError::ReadTimeout,
+                self.read_packet(sinks),
+            );
+            match packet.await {
+                Ok(Some(response)) => break Ok(response),
+                Ok(None) => self.broken = false,
+                Err(e) => break Err(e),
+            }
+        };
+        match &response {
+            Ok(Response::EndOfStream)
+            | Err(ClickHouseClientError::ServerException { .. }) => {
+                self.broken = false;
+                self.pending = false;
+                self.cancelled = false;
+                self.deadline = None;
+            }
+            Ok(_) => self.broken = false,
+            // the packet may have been cut short
+            Err(_) => self.broken = true,
+        }
+        response
+    }
+
+    /// Reads one packet, returning `None` for one that went to `sinks`
+    /// or was skipped.
+    async fn read_packet(&mut self, sinks: &Sinks) -> Result<Option<Response>> {
+        // filling the buffer takes nothing out of it, so it can be given up
+        if self.stream.fill_buf().await?.is_empty() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.broken = true;
+        let revision = self.revision();
+        let stream = &mut self.stream;
+        let response = match stream.read_packet_code().await? {
+            ServerPacketCode::Data => {
+                Response::Data(stream.read_data_packet(revision).await?)
+            }
+            ServerPacketCode::TableColumns => Response::TableColumns(
+                stream.read_table_columns_packet().await?,
+            ),
+            ServerPacketCode::EndOfStream => Response::EndOfStream,
+            ServerPacketCode::Progress => {
+                let progress = stream.read_progress_packet(revision).await?;
+                if let Some(sender) = &sinks.progress {
+                    // a receiver that went away is not an error
+                    let _ = sender.send(progress);
+                }
+                return Ok(None);
+            }
+            ServerPacketCode::ProfileInfo => {
+                stream.read_profile_info_packet().await?;
+                return Ok(None);
+            }
+            ServerPacketCode::Log => {
+                let block = stream.read_data_packet(revision).await?;
+                if let Some(sender) = &sinks.logs {
+                    let _ = sender.send(block);
+                }
+                return Ok(None);
+            }
+            ServerPacketCode::ProfileEvents => {
+                let block = stream.read_data_packet(revision).await?;
+                if let Some(sender) = &sinks.profile_events {
+                    let _ = sender.send(block);
+                }
+                return Ok(None);
+            }
+            ServerPacketCode::Totals | ServerPacketCode::Extremes => {
+                stream.read_data_packet(revision).await?;
+                return Ok(None);
+            }
+            ServerPacketCode::Exception => {
+                return Err(exception(stream.read_exception_packet().await?))
+            }
+            code => return Err(unexpected(code)),
+        };
+        Ok(Some(response))
+    }
+
+    /// `timeout`, cut short to the deadline of the query being run.
+    fn limit(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let left = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        match (timeout, left) {
+            (Some(timeout), Some(left)) => Some(timeout.min(left)),
+            (timeout, left) => timeout.or(left),
+        }
+    }
+}
+
+/// Opens the socket, and performs the TLS handshake if asked to.
+async fn dial(options: &ClientOptions) -> Result<Connection> {
+    let stream = TcpStream::connect(&options.addr).await?;
+    stream.set_nodelay(options.tcp_nodelay)?;
+    if let Some(idle) = options.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(idle);
+        SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+    }
+    #[cfg(feature = "tls")]
+    if let Some(tls) = &options.tls {
+        let stream = tls.connect(&options.addr, stream).await?;
+        return Ok(Connection::Tls(Box::new(stream)));
+    }
+    Ok(Connection::Plain(stream))
+}
+
+/// Runs `io`, failing with `error` if it takes longer than `limit`.
+///
+/// The connection is left broken, as `io` may have been cut short in the
+/// middle of a packet.
+async fn within<T>(
+    limit: Option<Duration>,
+    error: ClickHouseClientError,
+    io: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match limit {
+        Some(limit) => timeout(limit, io).await.map_err(|_| error)?,
+        None => io.await,
+    }
+}
+
+/// Where [`Client::receive`] sends the packets of a response that are
+/// not part of its result.
+#[derive(Default)]
+pub(crate) struct Sinks {
+    pub(crate) progress: Option<UnboundedSender<ProgressPacket>>,
+    pub(crate) logs: Option<UnboundedSender<DataPacket>>,
+    pub(crate) profile_events: Option<UnboundedSender<DataPacket>>,
+}
+
+/// A packet of a query's response, see [`Client::receive`].
+pub(crate) enum Response {
+    Data(DataPacket),
+    TableColumns(TableColumnsPacket),
+    EndOfStream,
+}
+
+/// The outermost of the exceptions the server sent.
+pub(crate) fn exception(
+    mut exceptions: Vec<ExceptionPacket>,
+) -> ClickHouseClientError {
+    exceptions.swap_remove(0).into()
+}
+
+pub(crate) fn unexpected(code: ServerPacketCode) -> ClickHouseClientError {
+    ClickHouseClientError::DecodeError(format!("unexpected packet {code:?}"))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::time::{Duration, Instant};
+
+    use anyhow::Result;
+    use clickhouse_datatypes::Row;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use crate::binary::ClickHouseEncoder;
+    use crate::protocol::client::{
+        ClickHouseWriteDataPacket, ClickHouseWriteQueryPacket, Column,
+        DataPacket, QueryPacket,
+    };
+    use crate::protocol::server::ServerPacketCode;
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+    use crate::{ClickHouseClientError, Client, ClientOptions};
+
+    /// Accepts one connection, answers its hello and then replays
+    /// `responses` without looking at what the client sends. The task
+    /// returns everything the client sent, its hello included.
+    pub(crate) async fn serve(
+        responses: Vec<u8>,
+    ) -> Result<(String, JoinHandle<Result<Vec<u8>>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        let task = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            replay(socket, &responses).await
+        });
+        Ok((addr, task))
+    }
+
+    /// Answers the hello of a client on `socket` and then replays
+    /// `responses`, returning everything the client sent.
+    pub(crate) async fn replay(
+        mut socket: impl AsyncRead + AsyncWrite + Unpin,
+        responses: &[u8],
+    ) -> Result<Vec<u8>> {
+        socket.write_all(&hello().await?).await?;
+        socket.write_all(responses).await?;
+        let mut sent = Vec::new();
+        socket.read_to_end(&mut sent).await?;
+        Ok(sent)
+    }
+
+    /// Encodes the hello of a server.
+    pub(crate) async fn hello() -> Result<Vec<u8>> {
+        let mut hello: Vec<u8> = Vec::new();
+        hello.encode_u8(ServerPacketCode::Hello as u8).await?;
+        hello.encode_utf8_string("ClickHouse").await?;
+        hello.encode_var_uint(24).await?;
+        hello.encode_var_uint(8).await?;
+        hello.encode_var_uint(CLICKHOUSE_PROTOCOL_VERSION).await?;
+        hello.encode_utf8_string("UTC").await?;
+        hello.encode_utf8_string("test").await?;
+        hello.encode_var_uint(1).await?;
+        Ok(hello)
+    }
+
+    /// Encodes a block of `columns` sent as a packet with `code`.
+    pub(crate) async fn data(
+        code: ServerPacketCode,
+        columns: Vec<(&str, clickhouse_datatypes::Column)>,
+    ) -> Result<Vec<u8>> {
+        let rows_count = columns.first().map_or(0, |(_, data)| data.len());
+        let packet = DataPacket {
+            columns_count: columns.len() as u64,
+            rows_count: rows_count as u64,
+            columns: columns
+                .into_iter()
+                .map(|(name, data)| Column {
+                    name: name.to_owned(),
+                    column_type: data.data_type(),
+                    data,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_data_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        buf[0] = code as u8;
+        Ok(buf)
+    }
+
+    /// A block of an `id UInt64` and a
+    /// `name LowCardinality(Nullable(String))` column.
+    pub(crate) fn block(
+        ids: Vec<u64>,
+        names: Vec<Option<&str>>,
+    ) -> Result<DataPacket> {
+        let mut column = clickhouse_datatypes::Column::new(
+            &"LowCardinality(Nullable(String))".parse()?,
+        );
+        for name in names {
+            column.push(name)?;
+        }
+        Ok(DataPacket {
+            columns_count: 2,
+            rows_count: ids.len() as u64,
+            columns: vec![
+                Column {
+                    name: "id".into(),
+                    column_type: clickhouse_datatypes::DataType::UInt64,
+                    data: clickhouse_datatypes::Column::UInt64(ids),
+                },
+                Column {
+                    name: "name".into(),
+                    column_type: column.data_type(),
+                    data: column,
+                },
+            ],
+            ..Default::default()
+        })
+    }
+
+    /// Encodes a response of `blocks`, the first of which is usually the
+    /// header, followed by the end of the stream.
+    pub(crate) async fn response(blocks: &[DataPacket]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        for block in blocks {
+            let columns = block
+                .columns
+                .iter()
+                .map(|c| (c.name.as_str(), c.data.clone()))
+                .collect();
+            buf.extend(data(ServerPacketCode::Data, columns).await?);
+        }
+        buf.encode_u8(ServerPacketCode::EndOfStream as u8).await?;
+        Ok(buf)
+    }
+
+    /// What a client sends for an insert of `blocks`: the query, the empty
+    /// block ending its external tables, the blocks and the empty block
+    /// ending the insert.
+    pub(crate) async fn expected_insert(
+        query: &QueryPacket,
+        blocks: &[DataPacket],
+    ) -> Result<Vec<u8>> {
+        let end = DataPacket::default();
+        let mut buf = Vec::new();
+        buf.write_query_packet(query, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        for block in [&end].into_iter().chain(blocks).chain([&end]) {
+            buf.write_data_packet(block, CLICKHOUSE_PROTOCOL_VERSION)
+                .await?;
+        }
+        Ok(buf)
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() -> Result<()> {
+        // the server never answers the query
+        let (addr, _) = serve(Vec::new()).await?;
+        let options = ClientOptions::default()
+            .addr(addr)
+            .read_timeout(Duration::from_millis(50))
+            .tcp_keepalive(Duration::from_secs(60));
+        let mut client = Client::connect(options).await?;
+        let error = client.query("SELECT 1").execute().await;
+        assert!(matches!(error, Err(ClickHouseClientError::ReadTimeout)));
+        let error = client.query("SELECT 1").execute().await;
+        assert!(matches!(
+            error,
+            Err(ClickHouseClientError::ConnectionBroken)
+        ));
+
+        let (addr, _) = serve(Vec::new()).await?;
+        let options = ClientOptions::default()
+            .addr(addr)
+            .query_timeout(Duration::from_millis(50));
+        let mut client = Client::connect(options).await?;
+        let started = Instant::now();
+        let error = client.query("SELECT 1").execute().await;
+        assert!(matches!(error, Err(ClickHouseClientError::ReadTimeout)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_timeout() -> Result<()> {
+        #[derive(Row)]
+        struct Blob {
+            data: String,
+        }
+
+        // the server takes the insert but reads none of its rows
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        let header =
+            vec![("data", clickhouse_datatypes::Column::String(vec![]))];
+        let header = data(ServerPacketCode::Data, header).await?;
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            socket.write_all(&hello().await?).await?;
+            socket.write_all(&header).await?;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            anyhow::Ok(socket)
+        });
+
+        let options = ClientOptions::default()
+            .addr(addr)
+            .write_timeout(Duration::from_millis(100));
+        let mut client = Client::connect(options).await?;
+        let blobs = (0..16).map(|_| Blob {
+            data: "x".repeat(4 << 20),
+        });
+        let error = client.insert::<Blob>("blobs").execute(blobs).await;
+        assert!(matches!(error, Err(ClickHouseClientError::WriteTimeout)));
+        assert!(matches!(
+            client.ping().await,
+            Err(ClickHouseClientError::ConnectionBroken)
+        ));
+        Ok(())
+    }
+}
@@ -17,9 +17,43 @@ pub enum ClickHouseClientError {
         stack_trace: String,
     },
 
+    #[error("deserialize error: {0}")]
+    DeserializeError(String),
+
+    #[error("cannot deserialize column `{column}`: {message}")]
+    ColumnDeserializeError { column: String, message: String },
+
+    #[error("cannot write {expected} to column `{column}` of type {data_type}")]
+    ColumnTypeMismatch {
+        column: String,
+        data_type: clickhouse_datatypes::DataType,
+        expected: clickhouse_datatypes::DataType,
+    },
+
+    #[error("bind error: {0}")]
+    BindError(String),
+
+    #[error("invalid setting `{key}`: {message}")]
+    InvalidSetting { key: String, message: String },
+
+    #[error("tls error: {0}")]
+    TlsError(String),
+
+    #[error("connection is broken, a packet was cut short")]
+    ConnectionBroken,
+
     #[error("timeout when reading from remote")]
     ReadTimeout,
 
+    #[error("timeout when writing to remote")]
+    WriteTimeout,
+
+    #[error("timeout when connecting to remote")]
+    ConnectTimeout,
+
+    #[error("{0}")]
+    DataTypeError(#[from] clickhouse_datatypes::DataTypeError),
+
     #[error("{0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 
@@ -28,3 +62,9 @@ pub enum ClickHouseClientError {
 }
 
 pub type Result<T, E = ClickHouseClientError> = std::result::Result<T, E>;
+
+impl serde::de::Error for ClickHouseClientError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        ClickHouseClientError::DeserializeError(msg.to_string())
+    }
+}
@@ -0,0 +1,136 @@
+use clickhouse_datatypes::Row;
+
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{Column, DataPacket};
+
+/// A table of data sent along with a query, which reads it by name like a
+/// temporary table, as in `SELECT ... WHERE id IN allowed`.
+///
+/// This keeps long lists out of the query text, which the server parses
+/// more slowly and limits with `max_query_size`.
+#[derive(Debug, Clone)]
+pub struct ExternalTable {
+    block: DataPacket,
+}
+
+impl ExternalTable {
+    /// A table without columns, which are added with
+    /// [`ExternalTable::column`].
+    pub fn new(name: impl Into<String>) -> Self {
+        ExternalTable {
+            block: DataPacket {
+                table_name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// A table with a column per field of `T`, holding `rows`.
+    pub fn from_rows<T: Row>(
+        name: impl Into<String>,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<Self> {
+        let types = T::data_types();
+        let mut columns = types
+            .iter()
+            .map(clickhouse_datatypes::Column::new)
+            .collect::<Vec<_>>();
+        for row in rows {
+            row.append_to(&mut columns)?;
+        }
+        let mut table = ExternalTable::new(name);
+        for ((name, column_type), data) in
+            T::COLUMN_NAMES.iter().zip(types).zip(columns)
+        {
+            table.push(*name, column_type, data)?;
+        }
+        Ok(table)
+    }
+
+    /// Adds a column of the type of `data`. Every column must have as many
+    /// rows as the first.
+    pub fn column(
+        mut self,
+        name: impl Into<String>,
+        data: clickhouse_datatypes::Column,
+    ) -> Result<Self> {
+        self.push(name, data.data_type(), data)?;
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.block.table_name
+    }
+
+    pub(crate) fn block(&self) -> &DataPacket {
+        &self.block
+    }
+
+    fn push(
+        &mut self,
+        name: impl Into<String>,
+        column_type: clickhouse_datatypes::DataType,
+        data: clickhouse_datatypes::Column,
+    ) -> Result<()> {
+        let name = name.into();
+        let rows = data.len() as u64;
+        if !self.block.columns.is_empty() && rows != self.block.rows_count {
+            return Err(ClickHouseClientError::EncodeError(format!(
+                "column `{name}` of external table `{}` has {rows} rows, \
+                 expected {}",
+                self.block.table_name, self.block.rows_count
+            )));
+        }
+        self.block.rows_count = rows;
+        self.block.columns.push(Column {
+            name,
+            column_type,
+            data,
+        });
+        self.block.columns_count = self.block.columns.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use clickhouse_datatypes::{DataType, Row};
+
+    use crate::external::ExternalTable;
+
+    #[derive(Row)]
+    struct Grant {
+        user_id: u64,
+        role: String,
+    }
+
+    #[test]
+    fn test_from_rows() -> Result<()> {
+        let grants =
+            [(1, "admin"), (2, "viewer")].map(|(user_id, role)| Grant {
+                user_id,
+                role: role.into(),
+            });
+        let table = ExternalTable::from_rows("grants", grants)?;
+        assert_eq!(table.name(), "grants");
+        let block = table.block();
+        assert_eq!((block.columns_count, block.rows_count), (2, 2));
+        let columns = block
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.column_type.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            [("user_id", DataType::UInt64), ("role", DataType::String)]
+        );
+
+        let empty = ExternalTable::from_rows::<Grant>("grants", [])?;
+        assert_eq!(
+            (empty.block().columns_count, empty.block().rows_count),
+            (2, 0)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,622 @@
+use std::marker::PhantomData;
+
+use clickhouse_datatypes::{quote_identifier, DataType, Row};
+use uuid::Uuid;
+
+use crate::client::{Client, Response, Sinks};
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{Column, DataPacket, QueryPacket, Settings};
+use crate::settings::QuerySettings;
+
+/// An insert built by [`Client::insert`]. Nothing is sent until it is run.
+#[must_use = "inserts do nothing unless they are run"]
+pub struct Insert<'a, T> {
+    client: &'a mut Client,
+    table: String,
+    packet: QueryPacket,
+    _row: PhantomData<fn(T)>,
+}
+
+/// How the server acknowledged an insert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertAck {
+    /// The rows were written to the table.
+    Written,
+    /// The rows were buffered by an asynchronous insert, and written to
+    /// the table when the server flushed its buffer.
+    Flushed { query_id: String },
+    /// The rows were buffered by an asynchronous insert that did not wait
+    /// for the flush, which may still fail. How it went is logged in
+    /// `system.asynchronous_insert_log` under the query id.
+    Queued { query_id: String },
+}
+
+impl InsertAck {
+    /// The id of an asynchronous insert, which is the `query_id` of its
+    /// entries in `system.asynchronous_insert_log`.
+    pub fn query_id(&self) -> Option<&str> {
+        match self {
+            InsertAck::Written => None,
+            InsertAck::Flushed { query_id }
+            | InsertAck::Queued { query_id } => Some(query_id),
+        }
+    }
+}
+
+impl<'a, T> Insert<'a, T> {
+    pub(crate) fn new(
+        client: &'a mut Client,
+        table: impl Into<String>,
+    ) -> Self {
+        Insert {
+            client,
+            table: table.into(),
+            packet: QueryPacket::new(String::new()),
+            _row: PhantomData,
+        }
+    }
+
+    pub fn query_id(mut self, query_id: impl Into<String>) -> Self {
+        self.packet.query_id = query_id.into();
+        self
+    }
+
+    /// Sets a setting for this insert only.
+    pub fn setting(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.packet.settings.push(Settings::new(key, value));
+        self
+    }
+
+    /// Adds the typed settings for this insert only.
+    pub fn settings(mut self, settings: QuerySettings) -> Self {
+        self.packet.settings.extend(settings.into_vec());
+        self
+    }
+
+    /// Makes this an asynchronous insert, whose rows the server buffers
+    /// with those of other inserts into the table and writes in one go.
+    ///
+    /// With `wait_for_flush` the insert is acknowledged once the buffer was
+    /// written, otherwise once the rows are buffered. A query id is made up
+    /// unless one is set, so that the insert can be found in
+    /// `system.asynchronous_insert_log`.
+    ///
+    /// The settings are important, so a server that does not know them
+    /// fails the insert rather than run it synchronously. They replace any
+    /// set before, and settings set after override them, as the
+    /// acknowledgement goes by the settings sent.
+    pub fn async_insert(mut self, wait_for_flush: bool) -> Self {
+        let wait = if wait_for_flush { "1" } else { "0" };
+        for (key, value) in
+            [("async_insert", "1"), ("wait_for_async_insert", wait)]
+        {
+            self.packet.settings.retain(|setting| setting.key != key);
+            self.packet.settings.push(Settings {
+                important: true,
+                ..Settings::new(key, value)
+            });
+        }
+        self
+    }
+
+    /// Whether the insert is asynchronous and then whether it waits for the
+    /// flush, going by the last value sent for each setting.
+    fn wait_for_flush(&self) -> Option<bool> {
+        let enabled = |key: &str| {
+            let setting = self.packet.settings.iter().rfind(|s| s.key == key);
+            setting
+                .map(|s| s.value == "1" || s.value.eq_ignore_ascii_case("true"))
+        };
+        // the server waits for the flush unless told not to
+        enabled("async_insert")?
+            .then(|| enabled("wait_for_async_insert").unwrap_or(true))
+    }
+
+    /// Sends an insert into `columns` and then the blocks `build` makes
+    /// from the header block and the table's description, if any.
+    ///
+    /// If the header does not have exactly `columns` or `build` fails, the
+    /// insert is ended without sending any rows.
+    pub(crate) async fn send<F>(
+        mut self,
+        columns: &[&str],
+        build: F,
+    ) -> Result<InsertAck>
+    where
+        F: FnOnce(
+            DataPacket,
+            Option<&[(String, DataType)]>,
+        ) -> Result<Vec<DataPacket>>,
+    {
+        let wait_for_flush = self.wait_for_flush();
+        if wait_for_flush.is_some() && self.packet.query_id.is_empty() {
+            self.packet.query_id = Uuid::new_v4().to_string();
+        }
+        let ack = match wait_for_flush {
+            None => InsertAck::Written,
+            Some(true) => InsertAck::Flushed {
+                query_id: self.packet.query_id.clone(),
+            },
+            Some(false) => InsertAck::Queued {
+                query_id: self.packet.query_id.clone(),
+            },
+        };
+        let client = self.client;
+        let (header, table_columns) =
+            begin(client, self.packet, &self.table, columns).await?;
+        let blocks = check_names(&header, columns)
+            .and_then(|()| build(header, table_columns.as_deref()));
+        for block in blocks.iter().flatten() {
+            write_block(client, block).await?;
+        }
+        // the empty block ends the insert, even one that failed
+        end(client).await?;
+        blocks.map(|_| ack)
+    }
+}
+
+/// Sends an insert into `columns` of `table` and reads the header block
+/// the server answers with, and the table's description if it sent one.
+pub(crate) async fn begin(
+    client: &mut Client,
+    mut packet: QueryPacket,
+    table: &str,
+    columns: &[&str],
+) -> Result<(DataPacket, Option<Vec<(String, DataType)>>)> {
+    let names = columns
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    packet.body = format!("INSERT INTO {table} ({names}) VALUES");
+    client.send_query(&packet, &[]).await?;
+
+    let mut table_columns = None;
+    loop {
+        match client.receive(&Sinks::default()).await? {
+            Response::TableColumns(packet) => {
+                table_columns = Some(packet.parse_columns()?);
+            }
+            Response::Data(header) => return Ok((header, table_columns)),
+            Response::EndOfStream => {
+                return Err(ClickHouseClientError::DecodeError(
+                    "insert ended before its header block".to_owned(),
+                ))
+            }
+        }
+    }
+}
+
+/// Sends a block of a begun insert unless it has no rows, returning the
+/// bytes written.
+pub(crate) async fn write_block(
+    client: &mut Client,
+    block: &DataPacket,
+) -> Result<usize> {
+    if block.rows_count == 0 {
+        return Ok(0);
+    }
+    client.write_block(block).await
+}
+
+/// Ends a begun insert with the empty block and reads the rest of the
+/// response, by which time the server has taken the rows.
+pub(crate) async fn end(client: &mut Client) -> Result<()> {
+    client.write_block(&DataPacket::default()).await?;
+    client.flush().await?;
+    loop {
+        match client.receive(&Sinks::default()).await? {
+            Response::Data(_) | Response::TableColumns(_) => {}
+            Response::EndOfStream => return Ok(()),
+        }
+    }
+}
+
+impl<T: Row> Insert<'_, T> {
+    /// Sends `rows` as one block.
+    ///
+    /// The columns the server expects are checked against the fields of `T`
+    /// first, and if one does not match no rows are sent at all.
+    pub async fn execute(
+        self,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<InsertAck> {
+        self.send(T::COLUMN_NAMES, |header, table_columns| {
+            check_types::<T>(&header, table_columns)?;
+            Ok(vec![build_block(header, rows)?])
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl Insert<'_, arrow::array::RecordBatch> {
+    /// Sends each batch as a block, with its columns converted to the types
+    /// of the table. Nothing is sent if there are no batches, which is
+    /// acknowledged as written.
+    ///
+    /// All batches must have the columns of the first one, in any order.
+    pub async fn execute_batches(
+        self,
+        batches: impl IntoIterator<Item = arrow::array::RecordBatch>,
+    ) -> Result<InsertAck> {
+        let batches = batches.into_iter().collect::<Vec<_>>();
+        let Some(first) = batches.first() else {
+            return Ok(InsertAck::Written);
+        };
+        let schema = first.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        self.send(&columns, |header, _| {
+            batches
+                .iter()
+                .map(|batch| DataPacket::from_record_batch(batch, &header))
+                .collect()
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "polars")]
+impl Insert<'_, polars::frame::DataFrame> {
+    /// Sends the frame as one block, with its columns converted to the
+    /// types of the table. Nothing is sent if the frame has no rows, which
+    /// is acknowledged as written.
+    pub async fn execute_frame(
+        self,
+        frame: &polars::frame::DataFrame,
+    ) -> Result<InsertAck> {
+        if frame.height() == 0 {
+            return Ok(InsertAck::Written);
+        }
+        let columns = frame
+            .columns()
+            .iter()
+            .map(|column| column.name().as_str())
+            .collect::<Vec<_>>();
+        self.send(&columns, |header, _| {
+            Ok(vec![DataPacket::from_data_frame(frame, &header)?])
+        })
+        .await
+    }
+}
+
+/// Checks that the header has the given columns, in order.
+pub(crate) fn check_names(header: &DataPacket, columns: &[&str]) -> Result<()> {
+    let names = header.columns.iter().map(|c| c.name.as_str());
+    if !names.eq(columns.iter().copied()) {
+        return Err(ClickHouseClientError::DecodeError(format!(
+            "expected columns {columns:?}, the server has {:?}",
+            header.columns.iter().map(|c| &c.name).collect::<Vec<_>>()
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that each column of the header has a type the field of `T` at
+/// its position can be written to.
+///
+/// Types are taken from the table's description where the server sent one,
+/// as it names the declared types rather than those of the header.
+pub(crate) fn check_types<T: Row>(
+    header: &DataPacket,
+    table_columns: Option<&[(String, DataType)]>,
+) -> Result<()> {
+    let expected = T::data_types();
+    for (index, column) in header.columns.iter().enumerate() {
+        let data_type = table_columns
+            .and_then(|columns| {
+                columns.iter().find(|(name, _)| *name == column.name)
+            })
+            .map_or(&column.column_type, |(_, data_type)| data_type);
+        if !T::accepts(index, data_type) {
+            return Err(ClickHouseClientError::ColumnTypeMismatch {
+                column: column.name.clone(),
+                data_type: data_type.clone(),
+                expected: expected[index].clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Appends `rows` to empty columns shaped like the header.
+fn build_block<T: Row>(
+    header: DataPacket,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<DataPacket> {
+    let (names, mut columns): (Vec<_>, Vec<_>) = header
+        .columns
+        .into_iter()
+        .map(|c| {
+            let data = clickhouse_datatypes::Column::new(&c.column_type);
+            ((c.name, c.column_type), data)
+        })
+        .unzip();
+    for row in rows {
+        row.append_to(&mut columns)?;
+    }
+    let rows_count = columns.first().map_or(0, |c| c.len());
+    Ok(DataPacket {
+        columns_count: columns.len() as u64,
+        rows_count: rows_count as u64,
+        columns: names
+            .into_iter()
+            .zip(columns)
+            .map(|((name, column_type), data)| Column {
+                name,
+                column_type,
+                data,
+            })
+            .collect(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use clickhouse_datatypes::{DataType, Row};
+
+    use crate::binary::ClickHouseEncoder;
+    use crate::client::test::{data, expected_insert, serve};
+    use crate::protocol::client::{
+        ClickHouseWriteQueryPacket, Column, DataPacket, QueryPacket, Settings,
+    };
+    use crate::protocol::server::ServerPacketCode;
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+    use crate::settings::QuerySettings;
+    use crate::{ClickHouseClientError, Client, ClientOptions, InsertAck};
+
+    #[derive(Row)]
+    struct Event {
+        id: u64,
+        #[clickhouse(rename = "event name")]
+        name: String,
+    }
+
+    /// The answer to an insert into a table of `columns`, whose header
+    /// block has the given types.
+    async fn table_response(columns: &str, header: &[&str]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.encode_u8(ServerPacketCode::TableColumns as u8).await?;
+        buf.encode_utf8_string("").await?;
+        buf.encode_utf8_string(columns).await?;
+        let names = ["id", "event name"];
+        let header = header
+            .iter()
+            .zip(names)
+            .map(|(data_type, name)| {
+                Ok((
+                    name,
+                    clickhouse_datatypes::Column::new(&data_type.parse()?),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        buf.extend(data(ServerPacketCode::Data, header).await?);
+        buf.encode_u8(ServerPacketCode::EndOfStream as u8).await?;
+        Ok(buf)
+    }
+
+    #[tokio::test]
+    async fn test_insert() -> Result<()> {
+        let mut responses = table_response(
+            "columns format version: 1\n2 columns:\n`id` UInt64\n\
+             `event name` LowCardinality(String)\n",
+            &["UInt64", "LowCardinality(String)"],
+        )
+        .await?;
+        responses.extend(
+            table_response(
+                "columns format version: 1\n2 columns:\n`id` UInt64\n\
+                 `event name` UInt8\n",
+                &["UInt64", "UInt8"],
+            )
+            .await?,
+        );
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let events = [(1, "start"), (2, "stop")].map(|(id, name)| Event {
+            id,
+            name: name.into(),
+        });
+        client.insert::<Event>("events").execute(events).await?;
+        let error = client
+            .insert::<Event>("events")
+            .execute([Event {
+                id: 3,
+                name: "never sent".into(),
+            }])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ClickHouseClientError::ColumnTypeMismatch { ref column, .. }
+                if column == "event name"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "cannot write String to column `event name` of type UInt8"
+        );
+        drop(client);
+
+        // the mismatched insert is ended without sending its row
+        let mut names = clickhouse_datatypes::Column::new(
+            &"LowCardinality(String)".parse()?,
+        );
+        names.push("start")?;
+        names.push("stop")?;
+        let block = DataPacket {
+            columns_count: 2,
+            rows_count: 2,
+            columns: vec![
+                Column {
+                    name: "id".into(),
+                    column_type: DataType::UInt64,
+                    data: clickhouse_datatypes::Column::UInt64(vec![1, 2]),
+                },
+                Column {
+                    name: "event name".into(),
+                    column_type: names.data_type(),
+                    data: names,
+                },
+            ],
+            ..Default::default()
+        };
+        let query =
+            QueryPacket::new("INSERT INTO events (id, `event name`) VALUES");
+        let mut expected = expected_insert(&query, &[block]).await?;
+        expected.extend(expected_insert(&query, &[]).await?);
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_insert() -> Result<()> {
+        let columns = "columns format version: 1\n2 columns:\n`id` UInt64\n\
+                       `event name` String\n";
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            responses
+                .extend(table_response(columns, &["UInt64", "String"]).await?);
+        }
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let event = || Event {
+            id: 1,
+            name: "start".into(),
+        };
+        let ack = client.insert("events").execute([event()]).await?;
+        assert_eq!(ack, InsertAck::Written);
+        let ack = client
+            .insert("events")
+            .query_id("q1")
+            .async_insert(true)
+            .execute([event()])
+            .await?;
+        assert_eq!(
+            ack,
+            InsertAck::Flushed {
+                query_id: "q1".into()
+            }
+        );
+        // without a query id one is made up to find the insert by
+        let ack = client
+            .insert("events")
+            .async_insert(false)
+            .execute([event()])
+            .await?;
+        let Some(query_id) = ack.query_id() else {
+            panic!("expected a query id, got {ack:?}");
+        };
+        assert!(matches!(ack, InsertAck::Queued { .. }));
+        query_id.parse::<uuid::Uuid>()?;
+
+        let mut query =
+            QueryPacket::new("INSERT INTO events (id, `event name`) VALUES");
+        query.query_id = query_id.to_owned();
+        for (key, value) in
+            [("async_insert", "1"), ("wait_for_async_insert", "0")]
+        {
+            query.settings.push(Settings {
+                important: true,
+                ..Settings::new(key, value)
+            });
+        }
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(&query, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        drop(client);
+        let sent = sent.await??;
+        assert!(sent
+            .windows(expected.len())
+            .any(|window| window == expected));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_async_insert_overridden() -> Result<()> {
+        let columns = "columns format version: 1\n2 columns:\n`id` UInt64\n\
+                       `event name` String\n";
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            responses
+                .extend(table_response(columns, &["UInt64", "String"]).await?);
+        }
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let event = || Event {
+            id: 1,
+            name: "start".into(),
+        };
+        // the acknowledgement goes by the last value of each setting
+        let ack = client
+            .insert("events")
+            .async_insert(false)
+            .setting("async_insert", "0")
+            .execute([event()])
+            .await?;
+        assert_eq!(ack, InsertAck::Written);
+        let ack = client
+            .insert("events")
+            .query_id("q1")
+            .settings(QuerySettings::new().set("async_insert", "1")?)
+            .execute([event()])
+            .await?;
+        assert_eq!(
+            ack,
+            InsertAck::Flushed {
+                query_id: "q1".into()
+            }
+        );
+        // the settings of an asynchronous insert replace those set before
+        let ack = client
+            .insert("events")
+            .query_id("q2")
+            .setting("wait_for_async_insert", "0")
+            .async_insert(true)
+            .execute([event()])
+            .await?;
+        assert_eq!(
+            ack,
+            InsertAck::Flushed {
+                query_id: "q2".into()
+            }
+        );
+        drop(client);
+
+        let mut query =
+            QueryPacket::new("INSERT INTO events (id, `event name`) VALUES");
+        query.query_id = "q2".into();
+        for (key, value) in
+            [("async_insert", "1"), ("wait_for_async_insert", "1")]
+        {
+            query.settings.push(Settings {
+                important: true,
+                ..Settings::new(key, value)
+            });
+        }
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(&query, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        let sent = sent.await??;
+        assert!(sent
+            .windows(expected.len())
+            .any(|window| window == expected));
+        Ok(())
+    }
+}
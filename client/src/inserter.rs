@@ -0,0 +1,408 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use clickhouse_datatypes::{DataType, Row};
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::insert::{begin, check_names, check_types, end, write_block};
+use crate::protocol::client::{Column, DataPacket, QueryPacket, Settings};
+use crate::settings::QuerySettings;
+
+/// What an [`Inserter`] sent with one insert query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitStats {
+    pub rows: u64,
+    /// The bytes of the blocks as sent.
+    pub bytes: u64,
+    /// From sending the insert until the server took its rows.
+    pub duration: Duration,
+}
+
+/// Buffers rows into blocks and sends them over long-running inserts,
+/// built by [`Client::inserter`].
+///
+/// A block is sent once it holds `max_rows` rows or about `max_bytes`
+/// bytes, or once `period` has passed since its first row. The insert is
+/// committed after every block, or with [`Inserter::commit_period`] only
+/// once it has been open that long. Dropping the inserter cancels an insert
+/// that was not committed, see [`Inserter::commit`].
+#[must_use = "inserters do nothing unless rows are written"]
+pub struct Inserter<'a, T> {
+    client: &'a mut Client,
+    table: String,
+    packet: QueryPacket,
+    max_rows: u64,
+    max_bytes: u64,
+    period: Option<Duration>,
+    commit_period: Option<Duration>,
+    open: Option<Open>,
+    _row: PhantomData<fn(T)>,
+}
+
+/// An insert that was begun and is waiting for blocks.
+struct Open {
+    header: Vec<(String, DataType)>,
+    columns: Vec<clickhouse_datatypes::Column>,
+    /// When the first row of the buffered block was written.
+    block_started: Option<Instant>,
+    /// The row count at which to measure the bytes of the block next.
+    measure_at: u64,
+    started: Instant,
+    stats: CommitStats,
+}
+
+impl<'a, T: Row> Inserter<'a, T> {
+    pub(crate) fn new(
+        client: &'a mut Client,
+        table: impl Into<String>,
+    ) -> Self {
+        Inserter {
+            client,
+            table: table.into(),
+            packet: QueryPacket::new(String::new()),
+            // the server's default max_insert_block_size
+            max_rows: 1_048_576,
+            max_bytes: 256 << 20,
+            period: None,
+            commit_period: None,
+            open: None,
+            _row: PhantomData,
+        }
+    }
+
+    /// Sets a setting for the inserts.
+    pub fn setting(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.packet.settings.push(Settings::new(key, value));
+        self
+    }
+
+    /// Adds the typed settings for the inserts.
+    pub fn settings(mut self, settings: QuerySettings) -> Self {
+        self.packet.settings.extend(settings.into_vec());
+        self
+    }
+
+    /// Sends a block once it has this many rows.
+    pub fn max_rows(mut self, max_rows: u64) -> Self {
+        self.max_rows = max_rows.max(1);
+        self
+    }
+
+    /// Sends a block once its columns take about this many bytes.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes.max(1);
+        self
+    }
+
+    /// Sends a block once this long has passed since its first row.
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    /// Keeps an insert open for this long, instead of committing it after
+    /// every block, and then commits it with the buffered rows.
+    pub fn commit_period(mut self, commit_period: Duration) -> Self {
+        self.commit_period = Some(commit_period);
+        self
+    }
+
+    /// Buffers `row`, beginning an insert first if none is open, and then
+    /// does what [`Inserter::tick`] does.
+    ///
+    /// Beginning an insert checks the columns the server expects against
+    /// the fields of `T`. A row that does not fit is not buffered.
+    pub async fn write(&mut self, row: T) -> Result<Option<CommitStats>> {
+        if self.open.is_none() {
+            self.open = Some(self.begin().await?);
+        }
+        let Some(open) = &mut self.open else {
+            return Ok(None);
+        };
+        row.append_to(&mut open.columns)?;
+        open.block_started.get_or_insert_with(Instant::now);
+        self.tick().await
+    }
+
+    /// Commits the insert with the buffered rows if its `commit_period` has
+    /// passed, or else sends the buffered block if it is due, returning what
+    /// was committed.
+    ///
+    /// Writing a row ticks, so this only needs calling from a timer for a
+    /// `period` or `commit_period` to be kept while no rows come in.
+    pub async fn tick(&mut self) -> Result<Option<CommitStats>> {
+        let Some(open) = &mut self.open else {
+            return Ok(None);
+        };
+        let elapsed = |since: Option<Instant>, period: Option<Duration>| {
+            since.zip(period).is_some_and(|(s, p)| s.elapsed() >= p)
+        };
+        if elapsed(Some(open.started), self.commit_period) {
+            return self.commit().await;
+        }
+        if !(open.is_full(self.max_rows, self.max_bytes)
+            || elapsed(open.block_started, self.period))
+        {
+            return Ok(None);
+        }
+        if self.commit_period.is_none() {
+            return self.commit().await;
+        }
+        self.send_block().await?;
+        Ok(None)
+    }
+
+    /// Sends the buffered rows and commits the open insert now, returning
+    /// what it sent, or `None` if no insert was open.
+    pub async fn commit(&mut self) -> Result<Option<CommitStats>> {
+        if self.open.is_none() {
+            return Ok(None);
+        }
+        self.send_block().await?;
+        let Some(open) = self.open.take() else {
+            return Ok(None);
+        };
+        end(self.client).await?;
+        Ok(Some(CommitStats {
+            duration: open.started.elapsed(),
+            ..open.stats
+        }))
+    }
+
+    async fn begin(&mut self) -> Result<Open> {
+        let started = Instant::now();
+        let (header, table_columns) = begin(
+            self.client,
+            self.packet.clone(),
+            &self.table,
+            T::COLUMN_NAMES,
+        )
+        .await?;
+        let checked = check_names(&header, T::COLUMN_NAMES)
+            .and_then(|()| check_types::<T>(&header, table_columns.as_deref()));
+        if let Err(e) = checked {
+            end(self.client).await?;
+            return Err(e);
+        }
+        let header = header
+            .columns
+            .into_iter()
+            .map(|column| (column.name, column.column_type))
+            .collect::<Vec<_>>();
+        Ok(Open {
+            columns: header
+                .iter()
+                .map(|(_, data_type)| {
+                    clickhouse_datatypes::Column::new(data_type)
+                })
+                .collect(),
+            header,
+            block_started: None,
+            measure_at: 1,
+            started,
+            stats: CommitStats::default(),
+        })
+    }
+
+    /// Sends the buffered rows as a block of the open insert.
+    async fn send_block(&mut self) -> Result<()> {
+        let Some(open) = &mut self.open else {
+            return Ok(());
+        };
+        let block = open.take_block();
+        let bytes = write_block(self.client, &block).await?;
+        // the server may take the block while the insert stays open
+        self.client.flush().await?;
+        open.stats.rows += block.rows_count;
+        open.stats.bytes += bytes as u64;
+        Ok(())
+    }
+}
+
+impl<T> Drop for Inserter<'_, T> {
+    fn drop(&mut self) {
+        // the buffered rows are dropped with the insert they were meant for
+        if self.open.is_some() {
+            self.client.try_cancel();
+        }
+    }
+}
+
+impl Open {
+    fn rows(&self) -> u64 {
+        self.columns.first().map_or(0, |c| c.len()) as u64
+    }
+
+    /// Whether the buffered block has reached either limit.
+    ///
+    /// Measuring the block walks its values, so it is only done once the
+    /// rows written since are expected to have covered half of the bytes
+    /// left, going by the average size of a row so far.
+    fn is_full(&mut self, max_rows: u64, max_bytes: u64) -> bool {
+        let rows = self.rows();
+        if rows >= max_rows {
+            return true;
+        }
+        if rows < self.measure_at {
+            return false;
+        }
+        let bytes = self
+            .columns
+            .iter()
+            .map(|column| column.byte_size() as u64)
+            .sum::<u64>();
+        if bytes >= max_bytes {
+            return true;
+        }
+        let row_bytes = bytes.div_ceil(rows).max(1);
+        self.measure_at = rows + ((max_bytes - bytes) / row_bytes / 2).max(1);
+        false
+    }
+
+    /// Takes the buffered rows as a block, leaving empty columns.
+    fn take_block(&mut self) -> DataPacket {
+        let rows_count = self.rows();
+        let columns = self
+            .header
+            .iter()
+            .zip(&mut self.columns)
+            .map(|((name, column_type), data)| Column {
+                name: name.clone(),
+                column_type: column_type.clone(),
+                data: mem::replace(
+                    data,
+                    clickhouse_datatypes::Column::new(column_type),
+                ),
+            })
+            .collect::<Vec<_>>();
+        self.block_started = None;
+        self.measure_at = 1;
+        DataPacket {
+            columns_count: columns.len() as u64,
+            rows_count,
+            columns,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use clickhouse_datatypes::Row;
+
+    use crate::binary::ClickHouseEncoder;
+    use crate::client::test::{block, expected_insert, response, serve};
+    use crate::protocol::client::{
+        ClickHouseWriteDataPacket, ClickHouseWriteQueryPacket, DataPacket,
+        QueryPacket,
+    };
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+    use crate::{Client, ClientOptions};
+
+    #[derive(Row)]
+    struct Event {
+        id: u64,
+        name: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_inserter() -> Result<()> {
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            responses.extend(response(&[block(vec![], vec![])?]).await?);
+        }
+        let (addr, sent) = serve(responses).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let event = |id, name: &str| Event {
+            id,
+            name: Some(name.into()),
+        };
+
+        // the first row takes 25 bytes, with 8 for its id, 8 for its key
+        // and 9 for the dictionary of NULL and its name
+        let mut inserter = client.inserter::<Event>("events").max_bytes(30);
+        assert_eq!(inserter.write(event(1, "start")).await?, None);
+        let stats = inserter.write(event(2, "pause")).await?;
+        assert_eq!(stats.map(|s| s.rows), Some(2));
+        assert_eq!(inserter.commit().await?, None);
+        drop(inserter);
+
+        // blocks are sent one row at a time, and committed together
+        let mut inserter = client
+            .inserter::<Event>("events")
+            .max_rows(1)
+            .commit_period(Duration::from_secs(3600));
+        assert_eq!(inserter.write(event(3, "resume")).await?, None);
+        assert_eq!(inserter.write(event(4, "stop")).await?, None);
+        let stats = inserter.commit().await?;
+        assert_eq!(stats.map(|s| s.rows), Some(2));
+        drop(inserter);
+
+        // a tick commits the buffered rows once the commit period is over
+        let commit_period = Duration::from_millis(50);
+        let mut inserter = client
+            .inserter::<Event>("events")
+            .commit_period(commit_period);
+        assert_eq!(inserter.write(event(5, "idle")).await?, None);
+        assert_eq!(inserter.tick().await?, None);
+        tokio::time::sleep(commit_period).await;
+        let stats = inserter.tick().await?;
+        assert_eq!(stats.map(|s| s.rows), Some(1));
+        assert_eq!(inserter.tick().await?, None);
+        drop(inserter);
+        drop(client);
+
+        let query = QueryPacket::new("INSERT INTO events (id, name) VALUES");
+        let mut expected = Vec::new();
+        for blocks in [
+            vec![block(vec![1, 2], vec![Some("start"), Some("pause")])?],
+            vec![
+                block(vec![3], vec![Some("resume")])?,
+                block(vec![4], vec![Some("stop")])?,
+            ],
+            vec![block(vec![5], vec![Some("idle")])?],
+        ] {
+            expected.extend(expected_insert(&query, &blocks).await?);
+        }
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_cancelled_on_drop() -> Result<()> {
+        let (addr, sent) =
+            serve(response(&[block(vec![], vec![])?]).await?).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let mut inserter = client.inserter::<Event>("events");
+        let event = Event { id: 1, name: None };
+        assert_eq!(inserter.write(event).await?, None);
+        // the cancel is sent without the client being used again
+        drop(inserter);
+        drop(client);
+
+        let query = QueryPacket::new("INSERT INTO events (id, name) VALUES");
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(&query, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        expected
+            .write_data_packet(
+                &DataPacket::default(),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        expected.encode_var_uint(3).await?;
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+}
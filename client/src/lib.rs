@@ -1,5 +1,30 @@
+#[cfg(feature = "arrow")]
+mod arrow;
 pub mod binary;
+mod client;
+mod connection;
 mod error;
+mod external;
+mod insert;
+mod inserter;
+#[cfg(feature = "polars")]
+mod polars;
 pub mod protocol;
+mod query;
+mod row;
+mod settings;
+mod sql;
+#[cfg(feature = "tls")]
+mod tls;
 
+pub use client::{Client, ClientOptions};
 pub use error::*;
+pub use external::ExternalTable;
+pub use insert::{Insert, InsertAck};
+pub use inserter::{CommitStats, Inserter};
+pub use query::{BlockStream, Query};
+pub use row::ValueRow;
+pub use settings::{LogLevel, OverflowMode, QuerySettings, Readonly};
+pub use sql::{Identifier, Sql, SqlArg};
+#[cfg(feature = "tls")]
+pub use tls::TlsOptions;
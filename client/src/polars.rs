@@ -0,0 +1,176 @@
+use polars::frame::DataFrame;
+use polars::prelude::IntoColumn;
+
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{Column, DataPacket};
+
+impl DataPacket {
+    /// Converts the block into a Polars data frame with a series per
+    /// column, see [`clickhouse_datatypes::Column::into_series`] for how the
+    /// types map. Errors name the column that could not be converted.
+    pub fn into_data_frame(self) -> Result<DataFrame> {
+        self.into_data_frame_with(false)
+    }
+
+    /// Like [`DataPacket::into_data_frame`], but converts `String` columns
+    /// to `String` and `LowCardinality(String)` ones to `Categorical`,
+    /// failing on values that are not UTF-8.
+    pub fn into_data_frame_utf8(self) -> Result<DataFrame> {
+        self.into_data_frame_with(true)
+    }
+
+    fn into_data_frame_with(self, utf8: bool) -> Result<DataFrame> {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|column| {
+                let series = if utf8 {
+                    column.data.into_series_utf8(&column.name)
+                } else {
+                    column.data.into_series(&column.name)
+                };
+                let series = series.map_err(|e| {
+                    ClickHouseClientError::EncodeError(format!(
+                        "column `{}`: {e}",
+                        column.name
+                    ))
+                })?;
+                Ok(series.into_column())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        DataFrame::new(self.rows_count as usize, columns)
+            .map_err(|e| ClickHouseClientError::EncodeError(e.to_string()))
+    }
+
+    /// Builds a block shaped like `header` from the frame columns of the
+    /// same names, converting them to the header's types.
+    pub fn from_data_frame(
+        frame: &DataFrame,
+        header: &DataPacket,
+    ) -> Result<DataPacket> {
+        let columns = header
+            .columns
+            .iter()
+            .map(|column| {
+                let error = |message: String| {
+                    ClickHouseClientError::EncodeError(format!(
+                        "column `{}`: {message}",
+                        column.name
+                    ))
+                };
+                let series = frame
+                    .column(&column.name)
+                    .map_err(|e| error(e.to_string()))?
+                    .as_materialized_series();
+                let data = clickhouse_datatypes::Column::from_series(
+                    series,
+                    &column.column_type,
+                )
+                .map_err(|e| error(e.to_string()))?;
+                Ok(Column {
+                    name: column.name.clone(),
+                    column_type: column.column_type.clone(),
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DataPacket {
+            columns_count: columns.len() as u64,
+            rows_count: frame.height() as u64,
+            columns,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use polars::frame::DataFrame;
+    use polars::prelude::{
+        DataType as PolarsType, IntoColumn, NamedFrom, Series,
+    };
+
+    use crate::client::test::{block, data, expected_insert, response, serve};
+    use crate::protocol::client::QueryPacket;
+    use crate::protocol::server::ServerPacketCode;
+    use crate::{ClickHouseClientError, Client, ClientOptions};
+
+    #[tokio::test]
+    async fn test_fetch_and_insert() -> Result<()> {
+        let header = block(vec![], vec![])?;
+        let mut responses = response(&[
+            header.clone(),
+            block(vec![1, 2], vec![Some("a"), None])?,
+            block(vec![3], vec![Some("b")])?,
+        ])
+        .await?;
+        // an empty result, then the header of the insert
+        let empty = response(&[header]).await?;
+        for _ in 0..2 {
+            responses.extend(&empty);
+        }
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let frame = client
+            .query("SELECT id, name")
+            .fetch_data_frame_utf8()
+            .await?;
+        assert_eq!(frame.shape(), (3, 2));
+        assert_eq!(frame.column("id")?.u64()?.get(2), Some(3));
+        let names = frame.column("name")?;
+        assert!(matches!(names.dtype(), PolarsType::Categorical(..)));
+        assert_eq!(names.null_count(), 1);
+
+        // an empty result still has the columns
+        let frame = client.query("SELECT id, name").fetch_data_frame().await?;
+        assert_eq!(frame.shape(), (0, 2));
+
+        // the frame columns are cast to the types of the table
+        let frame = DataFrame::new(
+            2,
+            vec![
+                Series::new("id".into(), [1_i64, 2]).into_column(),
+                Series::new("name".into(), [Some("a"), None]).into_column(),
+            ],
+        )?;
+        client
+            .insert_data_frame("events")
+            .execute_frame(&frame)
+            .await?;
+        drop(client);
+
+        let query = QueryPacket::new("INSERT INTO events (id, name) VALUES");
+        let rows = block(vec![1, 2], vec![Some("a"), None])?;
+        let expected = expected_insert(&query, &[rows]).await?;
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_string_frames() -> Result<()> {
+        // only the last block holds a value that is not UTF-8
+        let mut result = Vec::new();
+        for values in [vec![], vec![b"a".to_vec()], vec![vec![0xff]]] {
+            let column = clickhouse_datatypes::Column::String(
+                values.into_iter().map(Into::into).collect(),
+            );
+            let block = vec![("name", column)];
+            result.extend(data(ServerPacketCode::Data, block).await?);
+        }
+        result.push(ServerPacketCode::EndOfStream as u8);
+        let (addr, _) = serve(result.repeat(2)).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let frame = client.query("SELECT name").fetch_data_frame().await?;
+        let names = frame.column("name")?;
+        assert_eq!(names.dtype(), &PolarsType::Binary);
+        assert_eq!(names.binary()?.get(1), Some(&[0xff][..]));
+
+        let error = client.query("SELECT name").fetch_data_frame_utf8().await;
+        assert!(matches!(error, Err(ClickHouseClientError::EncodeError(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+use tokio::io::AsyncWrite;
+
+use crate::error::Result;
+use crate::protocol::client::{ClickHouseWritePacketCode, ClientPacketCode};
+
+pub trait ClickHouseWriteCancelPacket: ClickHouseWritePacketCode {
+    fn write_cancel_packet(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<usize>> + Send;
+}
+
+impl<R> ClickHouseWriteCancelPacket for R
+where
+    R: AsyncWrite + Unpin + Send + Sync,
+{
+    async fn write_cancel_packet(&mut self) -> Result<usize> {
+        self.write_packet_code(ClientPacketCode::Cancel).await
+    }
+}
@@ -1,11 +1,41 @@
-#[derive(Debug, Clone)]
+use clickhouse_datatypes::DataType;
+use serde::Deserialize;
+use tokio::io::AsyncWrite;
+
+use crate::binary::ClickHouseEncoder;
+use crate::error::Result;
+use crate::protocol::client::{ClickHouseWritePacketCode, ClientPacketCode};
+use crate::protocol::DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION;
+use crate::row::{value_rows, RowDeserializer, ValueRow};
+
+/// A block of columns. The default is the empty block that ends a stream of
+/// blocks.
+#[derive(Debug, Clone, Default)]
 pub struct DataPacket {
+    pub table_name: String,
     pub info: BlockInfo,
     pub columns_count: u64,
     pub rows_count: u64,
     pub columns: Vec<Column>,
 }
 
+impl DataPacket {
+    /// Deserializes the rows of the block, mapping columns to struct fields
+    /// by name. Errors name the column whose value did not fit.
+    pub fn rows<'a, T: Deserialize<'a>>(
+        &'a self,
+    ) -> impl Iterator<Item = Result<T>> + 'a {
+        (0..self.rows_count as usize)
+            .map(|row| T::deserialize(RowDeserializer::new(&self.columns, row)))
+    }
+
+    /// Reads the rows of the block as values of whatever type their columns
+    /// have.
+    pub fn value_rows(&self) -> impl Iterator<Item = Result<ValueRow>> + '_ {
+        value_rows(self)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BlockInfo {
     pub is_overflows: bool,
@@ -24,7 +54,253 @@ impl Default for BlockInfo {
 #[derive(Debug, Clone)]
 pub struct Column {
     pub name: String,
-    pub column_type: String,
-    pub data: Vec<u8>,
+    pub column_type: DataType,
+    pub data: clickhouse_datatypes::Column,
+}
+
+pub trait ClickHouseWriteDataPacket: ClickHouseWritePacketCode {
+    /// Writes the block for a server that speaks protocol `revision`.
+    fn write_data_packet(
+        &mut self,
+        x: &DataPacket,
+        revision: u64,
+    ) -> impl std::future::Future<Output = Result<usize>> + Send;
 }
 
+impl<R> ClickHouseWriteDataPacket for R
+where
+    R: AsyncWrite + Unpin + Send + Sync,
+{
+    async fn write_data_packet(
+        &mut self,
+        x: &DataPacket,
+        revision: u64,
+    ) -> Result<usize> {
+        let mut len: usize = 0;
+        len += self.write_packet_code(ClientPacketCode::Data).await?;
+        len += self.encode_utf8_string(&x.table_name).await?;
+
+        len += self.encode_var_uint(1).await?;
+        len += self.encode_bool(x.info.is_overflows).await?;
+        len += self.encode_var_uint(2).await?;
+        len += self.encode_i32(x.info.bucket_num).await?;
+        len += self.encode_var_uint(0).await?;
+
+        // tables store `Nested` as one `name.field Array(T)` column per
+        // field, so that is what the server expects on insert
+        let columns_count = x
+            .columns
+            .iter()
+            .map(|column| match &column.data {
+                clickhouse_datatypes::Column::Nested(nested) => {
+                    nested.fields().len() as u64
+                }
+                _ => 1,
+            })
+            .sum();
+        len += self.encode_var_uint(columns_count).await?;
+        len += self.encode_var_uint(x.rows_count).await?;
+        for column in &x.columns {
+            if let clickhouse_datatypes::Column::Nested(nested) = &column.data {
+                for (field, array) in nested.to_arrays() {
+                    let array = clickhouse_datatypes::Column::Array(array);
+                    len += self
+                        .encode_utf8_string(format!("{}.{field}", column.name))
+                        .await?;
+                    let data_type = array.data_type().with_json_as_string();
+                    len +=
+                        self.encode_utf8_string(data_type.to_string()).await?;
+                    if revision >= DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION {
+                        len += self.encode_bool(false).await?;
+                    }
+                    if x.rows_count > 0 {
+                        len += array.write(self).await?;
+                    }
+                }
+                continue;
+            }
+            len += self.encode_utf8_string(&column.name).await?;
+            // the server converts JSON text back into `JSON` columns
+            len += self
+                .encode_utf8_string(
+                    column.column_type.with_json_as_string().to_string(),
+                )
+                .await?;
+            // columns are always sent in their default serialization
+            if revision >= DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION {
+                len += self.encode_bool(false).await?;
+            }
+            if x.rows_count > 0 {
+                len += column.data.write(self).await?;
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use clickhouse_datatypes::{ArrayColumn, DataType, NestedColumn};
+
+    use crate::binary::ClickHouseDecoder;
+    use crate::protocol::client::{
+        BlockInfo, ClickHouseWriteDataPacket, ClientPacketCode, Column,
+        DataPacket,
+    };
+    use crate::protocol::server::ClickHouseRead;
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+
+    #[tokio::test]
+    async fn test_data_packet_round_trip() -> Result<()> {
+        let column_type: DataType = "Nullable(String)".parse()?;
+        let mut data = clickhouse_datatypes::Column::new(&column_type);
+        data.push(Some("hello"))?;
+        data.push(None::<&str>)?;
+
+        let packet = DataPacket {
+            table_name: String::new(),
+            info: BlockInfo::default(),
+            columns_count: 1,
+            rows_count: 2,
+            columns: vec![Column {
+                name: "greeting".into(),
+                column_type,
+                data,
+            }],
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        let len = buf
+            .write_data_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        assert_eq!(len, buf.len());
+
+        // server and client Data packets only differ in their packet code
+        let mut reader = buf.as_slice();
+        assert_eq!(reader.decode_u8().await?, ClientPacketCode::Data as u8);
+        let decoded =
+            reader.read_data_packet(CLICKHOUSE_PROTOCOL_VERSION).await?;
+        assert!(reader.is_empty());
+
+        assert_eq!(decoded.rows_count, 2);
+        assert_eq!(decoded.info.bucket_num, -1);
+        assert_eq!(decoded.columns[0].name, "greeting");
+        assert_eq!(decoded.columns[0].data, packet.columns[0].data);
+        assert_eq!(
+            decoded.columns[0].data.get::<Option<&str>>(0)?,
+            Some("hello")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nested_is_flattened() -> Result<()> {
+        let column_type: DataType = "Nested(id UInt8, tag String)".parse()?;
+        let ids = ArrayColumn::new(
+            vec![2, 3],
+            clickhouse_datatypes::Column::UInt8(vec![1, 2, 3]),
+        )?;
+        let tags = ArrayColumn::new(
+            vec![2, 3],
+            clickhouse_datatypes::Column::String(vec!["a".into(); 3]),
+        )?;
+        let data =
+            clickhouse_datatypes::Column::Nested(NestedColumn::from_arrays(
+                vec![("id".into(), ids.clone()), ("tag".into(), tags)],
+            )?);
+        let packet = DataPacket {
+            table_name: String::new(),
+            info: BlockInfo::default(),
+            columns_count: 1,
+            rows_count: 2,
+            columns: vec![Column {
+                name: "n".into(),
+                column_type,
+                data,
+            }],
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_data_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+
+        let mut reader = &buf[1..];
+        let decoded =
+            reader.read_data_packet(CLICKHOUSE_PROTOCOL_VERSION).await?;
+        assert_eq!(decoded.columns_count, 2);
+        assert_eq!(decoded.columns[0].name, "n.id");
+        assert_eq!(decoded.columns[0].column_type.to_string(), "Array(UInt8)");
+        assert_eq!(
+            decoded.columns[0].data,
+            clickhouse_datatypes::Column::Array(ids)
+        );
+        assert_eq!(decoded.columns[1].name, "n.tag");
+        assert_eq!(
+            decoded.columns[1].data.get::<Vec<String>>(1)?,
+            vec!["a".to_owned()]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_is_sent_as_string() -> Result<()> {
+        let column_type: DataType = "Array(JSON)".parse()?;
+        let mut data = clickhouse_datatypes::Column::new(&column_type);
+        data.push(vec![r#"{"a":1}"#])?;
+        let packet = DataPacket {
+            table_name: String::new(),
+            info: BlockInfo::default(),
+            columns_count: 1,
+            rows_count: 1,
+            columns: vec![Column {
+                name: "doc".into(),
+                column_type,
+                data,
+            }],
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_data_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+
+        let mut reader = &buf[1..];
+        let decoded =
+            reader.read_data_packet(CLICKHOUSE_PROTOCOL_VERSION).await?;
+        assert_eq!(decoded.columns[0].column_type.to_string(), "Array(String)");
+        assert_eq!(
+            decoded.columns[0].data.get::<Vec<&str>>(0)?,
+            vec![r#"{"a":1}"#]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_block_has_no_column_data() -> Result<()> {
+        let column_type: DataType = "LowCardinality(String)".parse()?;
+        let packet = DataPacket {
+            table_name: String::new(),
+            info: BlockInfo::default(),
+            columns_count: 1,
+            rows_count: 0,
+            columns: vec![Column {
+                name: "tag".into(),
+                data: clickhouse_datatypes::Column::new(&column_type),
+                column_type,
+            }],
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_data_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        assert!(buf.ends_with(b"\x03tag\x16LowCardinality(String)\x00"));
+
+        let mut reader = &buf[1..];
+        let decoded =
+            reader.read_data_packet(CLICKHOUSE_PROTOCOL_VERSION).await?;
+        assert!(reader.is_empty());
+        assert!(decoded.columns[0].data.is_empty());
+        Ok(())
+    }
+}
@@ -7,7 +7,7 @@ use crate::protocol::{
     CLICKHOUSE_CLIENT_NAME, CLICKHOUSE_DEFAULT_DATABASE,
     CLICKHOUSE_DEFAULT_PASSWORD, CLICKHOUSE_DEFAULT_USERNAME,
     CLICKHOUSE_PROTOCOL_VERSION, CLICKHOUSE_VERSION_MAJOR,
-    CLICKHOUSE_VERSION_MINOR,
+    CLICKHOUSE_VERSION_MINOR, DBMS_MIN_PROTOCOL_VERSION_WITH_ADDENDUM,
 };
 
 #[derive(Debug, Clone)]
@@ -64,6 +64,14 @@ pub trait ClickHouseWriteHelloPacket: ClickHouseWritePacketCode {
         &mut self,
         x: HelloPacket,
     ) -> impl std::future::Future<Output = Result<usize>> + Send;
+
+    /// Writes what follows the hello once the server has answered with its
+    /// `revision`: the quota key, of which there is none, if both sides
+    /// expect it, and nothing otherwise.
+    fn write_hello_addendum(
+        &mut self,
+        revision: u64,
+    ) -> impl std::future::Future<Output = Result<usize>> + Send;
 }
 
 impl<R> ClickHouseWriteHelloPacket for R
@@ -83,6 +91,15 @@ where
 
         Ok(len)
     }
+
+    async fn write_hello_addendum(&mut self, revision: u64) -> Result<usize> {
+        if revision.min(CLICKHOUSE_PROTOCOL_VERSION)
+            < DBMS_MIN_PROTOCOL_VERSION_WITH_ADDENDUM
+        {
+            return Ok(0);
+        }
+        self.encode_utf8_string("").await
+    }
 }
 
 #[cfg(test)]
@@ -104,7 +121,7 @@ mod test {
 
         let hello_packet: [u8; 48] = [
             0, 24, 99, 108, 105, 99, 107, 104, 111, 117, 115, 101, 45, 110, 97,
-            116, 105, 118, 101, 45, 99, 108, 105, 101, 110, 116, 0, 1, 179,
+            116, 105, 118, 101, 45, 99, 108, 105, 101, 110, 116, 0, 1, 187,
             169, 3, 7, 100, 101, 102, 97, 117, 108, 116, 7, 100, 101, 102, 97,
             117, 108, 116, 0,
         ];
@@ -114,6 +131,16 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_hello_addendum() -> Result<()> {
+        let mut buf: Vec<u8> = Vec::new();
+        assert_eq!(buf.write_hello_addendum(54458).await?, 1);
+        assert_eq!(buf, [0]);
+        assert_eq!(buf.write_hello_addendum(54457).await?, 0);
+        assert_eq!(buf, [0]);
+        Ok(())
+    }
+
     fn vec_compare(va: &[u8], vb: &[u8]) -> bool {
         (va.len() == vb.len()) &&  // zip stops at the shortest
          va.iter()
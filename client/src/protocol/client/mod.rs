@@ -1,13 +1,16 @@
+mod cancel;
 mod data;
 mod hello;
 mod ping;
 mod query;
 
-pub use data::{BlockInfo, Column, DataPacket};
+pub use cancel::ClickHouseWriteCancelPacket;
+pub use data::{BlockInfo, ClickHouseWriteDataPacket, Column, DataPacket};
 pub use hello::{ClickHouseWriteHelloPacket, HelloPacket};
 pub use ping::ClickHouseWritePingPacket;
 pub use query::{
-    ClientInfo, ClientQueryKind, Interface, QueryPacket, Settings, Stage,
+    ClickHouseWriteQueryPacket, ClientInfo, ClientQueryKind, Interface,
+    Parameter, QueryPacket, Settings, Stage,
 };
 
 use tokio::io::AsyncWrite;
@@ -1,3 +1,23 @@
+use clickhouse_datatypes::Value;
+use tokio::io::AsyncWrite;
+
+use crate::binary::ClickHouseEncoder;
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{ClickHouseWritePacketCode, ClientPacketCode};
+use crate::protocol::{
+    CLICKHOUSE_CLIENT_NAME, CLICKHOUSE_PROTOCOL_VERSION,
+    CLICKHOUSE_VERSION_MAJOR, CLICKHOUSE_VERSION_MINOR,
+    DBMS_MIN_PROTOCOL_VERSION_WITH_DISTRIBUTED_DEPTH,
+    DBMS_MIN_PROTOCOL_VERSION_WITH_INITIAL_QUERY_START_TIME,
+    DBMS_MIN_PROTOCOL_VERSION_WITH_PARAMETERS,
+    DBMS_MIN_REVISION_WITH_CLIENT_INFO, DBMS_MIN_REVISION_WITH_INTERSERVER_SECRET,
+    DBMS_MIN_REVISION_WITH_OPENTELEMETRY,
+    DBMS_MIN_REVISION_WITH_PARALLEL_REPLICAS,
+    DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO,
+    DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS,
+    DBMS_MIN_REVISION_WITH_VERSION_PATCH,
+};
+
 #[derive(Debug, Clone)]
 pub struct QueryPacket {
     pub query_id: String,
@@ -7,6 +27,32 @@ pub struct QueryPacket {
     pub stage: Stage,
     pub compression: u64,
     pub body: String,
+    pub parameters: Vec<Parameter>,
+}
+
+impl QueryPacket {
+    /// A query run to completion, with the settings every query needs.
+    pub fn new(body: impl Into<String>) -> Self {
+        QueryPacket {
+            query_id: String::new(),
+            client_info: ClientInfo::default(),
+            settings: Settings::json_as_string(),
+            secret: String::new(),
+            stage: Stage::Complete,
+            compression: 0,
+            body: body.into(),
+            parameters: Vec::new(),
+        }
+    }
+}
+
+/// A value for a `{name:Type}` placeholder of the query body.
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    /// The value as text in the escaped format the server parses it from,
+    /// see [`clickhouse_datatypes::Value::to_parameter`].
+    pub value: String,
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +83,41 @@ pub struct ClientInfo {
     pub span_id: String,
     pub trace_state: String,
     pub trace_flags: u8,
+
+    pub collaborate_with_initiator: bool,
+    pub count_participating_replicas: u64,
+    pub number_of_current_replica: u64,
+}
+
+impl Default for ClientInfo {
+    fn default() -> Self {
+        Self {
+            protocol_version: CLICKHOUSE_PROTOCOL_VERSION,
+            version_major: CLICKHOUSE_VERSION_MAJOR,
+            version_minor: CLICKHOUSE_VERSION_MINOR,
+            version_patch: 0,
+            interface: Interface::TCP,
+            query_kind: ClientQueryKind::Initial,
+            initial_user: String::new(),
+            initial_query_id: String::new(),
+            initial_address: "0.0.0.0:0".to_owned(),
+            // the server fills in the start time of initial queries
+            initial_time: 0,
+            os_user: std::env::var("USER").unwrap_or_default(),
+            client_hostname: String::new(),
+            client_name: CLICKHOUSE_CLIENT_NAME.to_owned(),
+            quota_key: String::new(),
+            distributed_depth: 0,
+            otel: false,
+            trace_id: String::new(),
+            span_id: String::new(),
+            trace_state: String::new(),
+            trace_flags: 0,
+            collaborate_with_initiator: false,
+            count_participating_replicas: 0,
+            number_of_current_replica: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,8 +125,35 @@ pub struct Settings {
     pub key: String,
     pub value: String,
     pub important: bool,
+    /// A setting the server does not define, whose value is sent as a
+    /// quoted string for the query to read with `getSetting`.
+    pub custom: bool,
 }
 
+impl Settings {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Settings {
+            key: key.into(),
+            value: value.into(),
+            important: false,
+            custom: false,
+        }
+    }
+
+    /// Settings that exchange `JSON` columns as text, which is the only
+    /// JSON serialization the column layer understands. Queries should
+    /// always send them.
+    pub fn json_as_string() -> Vec<Settings> {
+        vec![
+            Settings::new("output_format_native_write_json_as_string", "1"),
+            Settings::new("input_format_native_allow_types_conversion", "1"),
+        ]
+    }
+}
+
+const SETTING_FLAG_IMPORTANT: u64 = 0x01;
+const SETTING_FLAG_CUSTOM: u64 = 0x02;
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Stage {
     FetchColumns = 0,
@@ -65,3 +173,274 @@ pub enum Interface {
     TCP = 1,
     HTTP = 2,
 }
+
+pub trait ClickHouseWriteQueryPacket: ClickHouseWritePacketCode {
+    /// Writes the packet for a server that speaks protocol `revision`,
+    /// leaving out the fields it does not know about.
+    fn write_query_packet(
+        &mut self,
+        x: &QueryPacket,
+        revision: u64,
+    ) -> impl std::future::Future<Output = Result<usize>> + Send;
+}
+
+impl<R> ClickHouseWriteQueryPacket for R
+where
+    R: AsyncWrite + Unpin + Send + Sync,
+{
+    async fn write_query_packet(
+        &mut self,
+        x: &QueryPacket,
+        revision: u64,
+    ) -> Result<usize> {
+        let mut len: usize = 0;
+        len += self.write_packet_code(ClientPacketCode::Query).await?;
+        len += self.encode_utf8_string(&x.query_id).await?;
+        if revision >= DBMS_MIN_REVISION_WITH_CLIENT_INFO {
+            len += write_client_info(self, &x.client_info, revision).await?;
+        }
+
+        if revision < DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS {
+            return Err(ClickHouseClientError::EncodeError(format!(
+                "cannot send settings to a server of revision {revision}"
+            )));
+        }
+        for setting in &x.settings {
+            len += self.encode_utf8_string(&setting.key).await?;
+            let flags = if setting.important {
+                SETTING_FLAG_IMPORTANT
+            } else {
+                0
+            };
+            if setting.custom {
+                len += self.encode_var_uint(flags | SETTING_FLAG_CUSTOM).await?;
+                // quoted like the value of a parameter
+                let value = Value::from(setting.value.as_str()).to_string();
+                len += self.encode_utf8_string(value).await?;
+            } else {
+                len += self.encode_var_uint(flags).await?;
+                len += self.encode_utf8_string(&setting.value).await?;
+            }
+        }
+        // an empty name ends the settings
+        len += self.encode_utf8_string("").await?;
+
+        if revision >= DBMS_MIN_REVISION_WITH_INTERSERVER_SECRET {
+            len += self.encode_utf8_string(&x.secret).await?;
+        }
+        len += self.encode_var_uint(x.stage.clone() as u64).await?;
+        len += self.encode_var_uint(x.compression).await?;
+        len += self.encode_utf8_string(&x.body).await?;
+
+        if revision >= DBMS_MIN_PROTOCOL_VERSION_WITH_PARAMETERS {
+            // parameters go like custom settings, whose values are quoted
+            for parameter in &x.parameters {
+                len += self.encode_utf8_string(&parameter.name).await?;
+                len += self.encode_var_uint(SETTING_FLAG_CUSTOM).await?;
+                let value = Value::from(parameter.value.as_str()).to_string();
+                len += self.encode_utf8_string(value).await?;
+            }
+            len += self.encode_utf8_string("").await?;
+        } else if !x.parameters.is_empty() {
+            return Err(ClickHouseClientError::EncodeError(format!(
+                "cannot send parameters to a server of revision {revision}"
+            )));
+        }
+
+        Ok(len)
+    }
+}
+
+async fn write_client_info<W>(
+    writer: &mut W,
+    x: &ClientInfo,
+    revision: u64,
+) -> Result<usize>
+where
+    W: AsyncWrite + Unpin + Send + Sync,
+{
+    let mut len: usize = 0;
+    len += writer.encode_u8(x.query_kind.clone() as u8).await?;
+    if x.query_kind == ClientQueryKind::None {
+        return Ok(len);
+    }
+
+    len += writer.encode_utf8_string(&x.initial_user).await?;
+    len += writer.encode_utf8_string(&x.initial_query_id).await?;
+    len += writer.encode_utf8_string(&x.initial_address).await?;
+    if revision >= DBMS_MIN_PROTOCOL_VERSION_WITH_INITIAL_QUERY_START_TIME {
+        len += writer.encode_i64(x.initial_time).await?;
+    }
+
+    if x.interface != Interface::TCP {
+        return Err(ClickHouseClientError::EncodeError(
+            "only the TCP interface can be sent in client info".into(),
+        ));
+    }
+    len += writer.encode_u8(x.interface.clone() as u8).await?;
+    len += writer.encode_utf8_string(&x.os_user).await?;
+    len += writer.encode_utf8_string(&x.client_hostname).await?;
+    len += writer.encode_utf8_string(&x.client_name).await?;
+    len += writer.encode_var_uint(x.version_major).await?;
+    len += writer.encode_var_uint(x.version_minor).await?;
+    len += writer.encode_var_uint(x.protocol_version).await?;
+
+    if revision >= DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO {
+        len += writer.encode_utf8_string(&x.quota_key).await?;
+    }
+    if revision >= DBMS_MIN_PROTOCOL_VERSION_WITH_DISTRIBUTED_DEPTH {
+        len += writer.encode_var_uint(x.distributed_depth).await?;
+    }
+    if revision >= DBMS_MIN_REVISION_WITH_VERSION_PATCH {
+        len += writer.encode_var_uint(x.version_patch).await?;
+    }
+    if revision >= DBMS_MIN_REVISION_WITH_OPENTELEMETRY {
+        len += writer.encode_bool(x.otel).await?;
+        if x.otel {
+            // the trace id goes over the wire like a `UUID` column
+            let trace_id = parse_hex(&x.trace_id.replace('-', ""))?;
+            len += writer.encode_u64((trace_id >> 64) as u64).await?;
+            len += writer.encode_u64(trace_id as u64).await?;
+            len += writer.encode_u64(parse_hex(&x.span_id)? as u64).await?;
+            len += writer.encode_utf8_string(&x.trace_state).await?;
+            len += writer.encode_u8(x.trace_flags).await?;
+        }
+    }
+    if revision >= DBMS_MIN_REVISION_WITH_PARALLEL_REPLICAS {
+        let collaborate = x.collaborate_with_initiator as u64;
+        len += writer.encode_var_uint(collaborate).await?;
+        len += writer.encode_var_uint(x.count_participating_replicas).await?;
+        len += writer.encode_var_uint(x.number_of_current_replica).await?;
+    }
+
+    Ok(len)
+}
+
+fn parse_hex(x: &str) -> Result<u128> {
+    u128::from_str_radix(x, 16).map_err(|_| {
+        ClickHouseClientError::EncodeError(format!("invalid trace id {x:?}"))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::binary::ClickHouseDecoder;
+    use crate::protocol::client::{
+        ClickHouseWriteQueryPacket, ClientPacketCode, Parameter, QueryPacket,
+        Settings,
+    };
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+
+    #[tokio::test]
+    async fn test_query_packet() -> Result<()> {
+        let mut packet = QueryPacket::new("SELECT 1");
+        packet.query_id = "q1".into();
+        packet.client_info.os_user = "me".into();
+        packet.parameters.push(Parameter {
+            name: "name".into(),
+            value: r"it\'s".into(),
+        });
+        packet.settings.push(Settings {
+            important: true,
+            ..Settings::new("max_threads", "2")
+        });
+        packet.settings.push(Settings {
+            custom: true,
+            ..Settings::new("custom_tag", "it's")
+        });
+
+        let mut buf: Vec<u8> = Vec::new();
+        let len = buf
+            .write_query_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        assert_eq!(len, buf.len());
+
+        let mut reader = buf.as_slice();
+        assert_eq!(reader.decode_u8().await?, ClientPacketCode::Query as u8);
+        assert_eq!(reader.decode_utf8_string().await?, "q1");
+        // initial query, empty user and query id, then the address
+        assert_eq!(reader.decode_u8().await?, 1);
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(reader.decode_utf8_string().await?, "0.0.0.0:0");
+        assert_eq!(&reader[..8], [0; 8]);
+        reader = &reader[8..];
+        assert_eq!(reader.decode_u8().await?, 1);
+        assert_eq!(reader.decode_utf8_string().await?, "me");
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(
+            reader.decode_utf8_string().await?,
+            "clickhouse-native-client"
+        );
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert_eq!(reader.decode_var_uint().await?, 1);
+        assert_eq!(
+            reader.decode_var_uint().await?,
+            CLICKHOUSE_PROTOCOL_VERSION
+        );
+        // quota key, distributed depth, version patch and no trace
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert!(!reader.decode_bool().await?);
+        // no parallel replicas
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert_eq!(reader.decode_var_uint().await?, 0);
+
+        let defaults = packet.settings.len() - 2;
+        for setting in &packet.settings[..defaults] {
+            assert_eq!(reader.decode_utf8_string().await?, setting.key);
+            assert_eq!(reader.decode_var_uint().await?, 0);
+            assert_eq!(reader.decode_utf8_string().await?, setting.value);
+        }
+        assert_eq!(reader.decode_utf8_string().await?, "max_threads");
+        assert_eq!(reader.decode_var_uint().await?, 1);
+        assert_eq!(reader.decode_utf8_string().await?, "2");
+        // a custom setting has its value quoted, like a parameter
+        assert_eq!(reader.decode_utf8_string().await?, "custom_tag");
+        assert_eq!(reader.decode_var_uint().await?, 2);
+        assert_eq!(reader.decode_utf8_string().await?, r"'it\'s'");
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert_eq!(reader.decode_var_uint().await?, 2);
+        assert_eq!(reader.decode_var_uint().await?, 0);
+        assert_eq!(reader.decode_utf8_string().await?, "SELECT 1");
+        // a custom setting, quoted once more
+        assert_eq!(reader.decode_utf8_string().await?, "name");
+        assert_eq!(reader.decode_var_uint().await?, 2);
+        assert_eq!(reader.decode_utf8_string().await?, r"'it\\\'s'");
+        assert_eq!(reader.decode_utf8_string().await?, "");
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_packet_for_old_server() -> Result<()> {
+        let packet = QueryPacket::new("SELECT 1");
+        let mut old: Vec<u8> = Vec::new();
+        old.write_query_packet(&packet, 54442).await?;
+        let mut new: Vec<u8> = Vec::new();
+        new.write_query_packet(&packet, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        // no start time, distributed depth, parallel replicas or parameters
+        assert_eq!(new.len() - old.len(), 13);
+
+        assert!(QueryPacket::new("SELECT 1")
+            .settings
+            .iter()
+            .any(|s| s.key == "output_format_native_write_json_as_string"));
+        let mut buf: Vec<u8> = Vec::new();
+        assert!(buf.write_query_packet(&packet, 54428).await.is_err());
+
+        let mut packet = packet;
+        packet.parameters.push(Parameter {
+            name: "id".into(),
+            value: "42".into(),
+        });
+        assert!(buf.write_query_packet(&packet, 54458).await.is_err());
+        Ok(())
+    }
+}
@@ -4,3 +4,20 @@ pub mod server;
 mod value;
 
 pub use value::*;
+
+// protocol revisions from which the server and client exchange the fields
+// that are only sent conditionally
+pub const DBMS_MIN_REVISION_WITH_TOTAL_ROWS_IN_PROGRESS: u64 = 51554;
+pub const DBMS_MIN_REVISION_WITH_CLIENT_INFO: u64 = 54032;
+pub const DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO: u64 = 54060;
+pub const DBMS_MIN_REVISION_WITH_VERSION_PATCH: u64 = 54401;
+pub const DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO: u64 = 54420;
+pub const DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS: u64 = 54429;
+pub const DBMS_MIN_REVISION_WITH_INTERSERVER_SECRET: u64 = 54441;
+pub const DBMS_MIN_REVISION_WITH_OPENTELEMETRY: u64 = 54442;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_DISTRIBUTED_DEPTH: u64 = 54448;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_INITIAL_QUERY_START_TIME: u64 = 54449;
+pub const DBMS_MIN_REVISION_WITH_PARALLEL_REPLICAS: u64 = 54453;
+pub const DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION: u64 = 54454;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_ADDENDUM: u64 = 54458;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_PARAMETERS: u64 = 54459;
@@ -1,8 +1,15 @@
 use crate::binary::ClickHouseDecoder;
-use crate::error::Result;
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::{BlockInfo, Column, DataPacket};
+use crate::protocol::{
+    DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO,
+    DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION,
+    DBMS_MIN_REVISION_WITH_TOTAL_ROWS_IN_PROGRESS,
+};
+use clickhouse_datatypes::DataType;
 use tokio::io::AsyncRead;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ServerPacketCode {
     Hello = 0,
     Data = 1,
@@ -64,9 +71,122 @@ pub struct ExceptionPacket {
     pub nested: bool,
 }
 
+impl From<ExceptionPacket> for ClickHouseClientError {
+    fn from(x: ExceptionPacket) -> Self {
+        ClickHouseClientError::ServerException {
+            code: x.code,
+            name: x.name,
+            message: x.message,
+            stack_trace: x.stack_trace,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PongPacket {}
 
+/// Rows and bytes processed since the previous progress packet.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressPacket {
+    pub rows: u64,
+    pub bytes: u64,
+    pub total_rows: u64,
+    pub written_rows: u64,
+    pub written_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileInfoPacket {
+    pub rows: u64,
+    pub blocks: u64,
+    pub bytes: u64,
+    pub applied_limit: bool,
+    pub rows_before_limit: u64,
+    pub calculated_rows_before_limit: bool,
+}
+
+/// The columns of the table an `INSERT` writes to, as the text the server
+/// uses to describe them.
+#[derive(Debug, Clone)]
+pub struct TableColumnsPacket {
+    pub table_name: String,
+    pub columns: String,
+}
+
+impl TableColumnsPacket {
+    /// The names and types of the columns, leaving out their defaults,
+    /// comments and codecs.
+    pub fn parse_columns(&self) -> Result<Vec<(String, DataType)>> {
+        let invalid = || {
+            ClickHouseClientError::DecodeError(format!(
+                "invalid table columns description {:?}",
+                self.columns
+            ))
+        };
+        let mut lines = self.columns.lines();
+        if lines.next() != Some("columns format version: 1") {
+            return Err(invalid());
+        }
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.strip_suffix(" columns:"))
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(invalid)?;
+
+        let mut columns = Vec::with_capacity(count);
+        for line in lines.take(count) {
+            // `name` Type, then tab separated extras
+            let line = line.strip_prefix('`').ok_or_else(invalid)?;
+            let end = unescaped_end(line, '`').ok_or_else(invalid)?;
+            let name = unescape(&line[..end]);
+            let data_type = line[end + 1..]
+                .strip_prefix(' ')
+                .and_then(|rest| rest.split('\t').next())
+                .ok_or_else(invalid)?;
+            columns.push((name, unescape(data_type).parse()?));
+        }
+        if columns.len() != count {
+            return Err(invalid());
+        }
+        Ok(columns)
+    }
+}
+
+/// The position of the first `quote` that is not escaped with a backslash.
+fn unescaped_end(input: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resolves the backslash escapes the server writes text with.
+fn unescape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('0') => output.push('\0'),
+            Some(c) => output.push(c),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
 pub trait ClickHouseRead {
     fn read_packet_code(
         &mut self,
@@ -77,6 +197,20 @@ pub trait ClickHouseRead {
     fn read_exception_packet(
         &mut self,
     ) -> impl std::future::Future<Output = Result<Vec<ExceptionPacket>>> + Send;
+    fn read_data_packet(
+        &mut self,
+        revision: u64,
+    ) -> impl std::future::Future<Output = Result<DataPacket>> + Send;
+    fn read_progress_packet(
+        &mut self,
+        revision: u64,
+    ) -> impl std::future::Future<Output = Result<ProgressPacket>> + Send;
+    fn read_profile_info_packet(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<ProfileInfoPacket>> + Send;
+    fn read_table_columns_packet(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<TableColumnsPacket>> + Send;
 }
 
 impl<R> ClickHouseRead for R
@@ -84,7 +218,13 @@ where
     R: AsyncRead + Unpin + Send + Sync,
 {
     async fn read_packet_code(&mut self) -> Result<ServerPacketCode> {
-        Ok(ServerPacketCode::from(self.decode_u8().await?))
+        let code = self.decode_u8().await?;
+        if code > ServerPacketCode::ProfileEvents as u8 {
+            return Err(ClickHouseClientError::DecodeError(format!(
+                "unknown server packet code {code}"
+            )));
+        }
+        Ok(ServerPacketCode::from(code))
     }
 
     async fn read_hello_packet(&mut self) -> Result<HelloPacket> {
@@ -127,6 +267,104 @@ where
         }
         Ok(exception_list)
     }
+
+    async fn read_data_packet(&mut self, revision: u64) -> Result<DataPacket> {
+        let table_name = self.decode_utf8_string().await?;
+
+        let mut info = BlockInfo::default();
+        loop {
+            match self.decode_var_uint().await? {
+                0 => break,
+                1 => info.is_overflows = self.decode_bool().await?,
+                2 => info.bucket_num = self.decode_i32().await?,
+                field => {
+                    return Err(ClickHouseClientError::DecodeError(format!(
+                        "unknown block info field {field}"
+                    )))
+                }
+            }
+        }
+
+        let columns_count = self.decode_var_uint().await?;
+        let rows_count = self.decode_var_uint().await?;
+        let mut columns = Vec::with_capacity(columns_count as usize);
+        for _ in 0..columns_count {
+            let name = self.decode_utf8_string().await?;
+            let column_type: DataType =
+                self.decode_utf8_string().await?.parse()?;
+            // sparse columns are only sent to clients that ask for them
+            if revision >= DBMS_MIN_REVISION_WITH_CUSTOM_SERIALIZATION
+                && self.decode_bool().await?
+            {
+                return Err(ClickHouseClientError::DecodeError(format!(
+                    "column `{name}` has a custom serialization"
+                )));
+            }
+            // empty blocks carry no column data at all
+            let data = if rows_count == 0 {
+                clickhouse_datatypes::Column::new(&column_type)
+            } else {
+                clickhouse_datatypes::Column::read(
+                    &column_type,
+                    rows_count as usize,
+                    self,
+                )
+                .await?
+            };
+            columns.push(Column {
+                name,
+                column_type,
+                data,
+            });
+        }
+
+        Ok(DataPacket {
+            table_name,
+            info,
+            columns_count,
+            rows_count,
+            columns,
+        })
+    }
+
+    async fn read_progress_packet(
+        &mut self,
+        revision: u64,
+    ) -> Result<ProgressPacket> {
+        let mut progress = ProgressPacket {
+            rows: self.decode_var_uint().await?,
+            bytes: self.decode_var_uint().await?,
+            ..Default::default()
+        };
+        if revision >= DBMS_MIN_REVISION_WITH_TOTAL_ROWS_IN_PROGRESS {
+            progress.total_rows = self.decode_var_uint().await?;
+        }
+        if revision >= DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO {
+            progress.written_rows = self.decode_var_uint().await?;
+            progress.written_bytes = self.decode_var_uint().await?;
+        }
+        Ok(progress)
+    }
+
+    async fn read_profile_info_packet(&mut self) -> Result<ProfileInfoPacket> {
+        Ok(ProfileInfoPacket {
+            rows: self.decode_var_uint().await?,
+            blocks: self.decode_var_uint().await?,
+            bytes: self.decode_var_uint().await?,
+            applied_limit: self.decode_bool().await?,
+            rows_before_limit: self.decode_var_uint().await?,
+            calculated_rows_before_limit: self.decode_bool().await?,
+        })
+    }
+
+    async fn read_table_columns_packet(
+        &mut self,
+    ) -> Result<TableColumnsPacket> {
+        Ok(TableColumnsPacket {
+            table_name: self.decode_utf8_string().await?,
+            columns: self.decode_utf8_string().await?,
+        })
+    }
 }
 
 // #[cfg(test)]
@@ -142,3 +380,44 @@ where
 
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::protocol::server::TableColumnsPacket;
+
+    #[test]
+    fn test_parse_table_columns() -> Result<()> {
+        let packet = TableColumnsPacket {
+            table_name: String::new(),
+            columns: "columns format version: 1\n\
+                      3 columns:\n\
+                      `id` UInt64\n\
+                      `odd \\`name` Enum8(\\'a\\' = 1)\tDEFAULT\t\\'a\\'\n\
+                      `at` DateTime(\\'UTC\\')\tCOMMENT\t\\'when\\'\n"
+                .into(),
+        };
+        let columns = packet.parse_columns()?;
+        let columns: Vec<(&str, String)> = columns
+            .iter()
+            .map(|(name, data_type)| (name.as_str(), data_type.to_string()))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("id", "UInt64".to_owned()),
+                ("odd `name", "Enum8('a' = 1)".to_owned()),
+                ("at", "DateTime('UTC')".to_owned()),
+            ]
+        );
+
+        let truncated = TableColumnsPacket {
+            table_name: String::new(),
+            columns: "columns format version: 1\n2 columns:\n`id` UInt64\n"
+                .into(),
+        };
+        assert!(truncated.parse_columns().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,716 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use clickhouse_datatypes::Value;
+use futures::future::{BoxFuture, FutureExt};
+use futures::ready;
+use futures::stream::Stream;
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::client::{Client, Response, Sinks};
+use crate::error::Result;
+use crate::external::ExternalTable;
+use crate::protocol::client::{DataPacket, Parameter, QueryPacket, Settings};
+use crate::protocol::server::ProgressPacket;
+use crate::row::ValueRow;
+use crate::settings::QuerySettings;
+
+/// A query built by [`Client::query`]. Nothing is sent until it is run.
+#[must_use = "queries do nothing unless they are run"]
+pub struct Query<'a> {
+    client: &'a mut Client,
+    packet: QueryPacket,
+    external: Vec<ExternalTable>,
+    sinks: Sinks,
+}
+
+impl<'a> Query<'a> {
+    pub(crate) fn new(client: &'a mut Client, body: impl Into<String>) -> Self {
+        Query {
+            client,
+            packet: QueryPacket::new(body),
+            external: Vec::new(),
+            sinks: Sinks::default(),
+        }
+    }
+
+    pub fn query_id(mut self, query_id: impl Into<String>) -> Self {
+        self.packet.query_id = query_id.into();
+        self
+    }
+
+    /// Sets a setting for this query only.
+    pub fn setting(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.packet.settings.push(Settings::new(key, value));
+        self
+    }
+
+    /// Adds the typed settings for this query only.
+    pub fn settings(mut self, settings: QuerySettings) -> Self {
+        self.packet.settings.extend(settings.into_vec());
+        self
+    }
+
+    /// Binds `value` to the `{name:Type}` placeholders of the body, which
+    /// the server parses as `Type`, so the value is never spliced into the
+    /// SQL text.
+    pub fn bind(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.packet.parameters.push(Parameter {
+            name: name.into(),
+            value: value.into().to_parameter(),
+        });
+        self
+    }
+
+    /// Sends `table` with the query, which reads it by its name.
+    pub fn external_table(mut self, table: ExternalTable) -> Self {
+        self.external.push(table);
+        self
+    }
+
+    /// Sends each progress packet of the query to `progress` as it
+    /// arrives, instead of skipping it.
+    pub fn progress_to(
+        mut self,
+        progress: UnboundedSender<ProgressPacket>,
+    ) -> Self {
+        self.sinks.progress = Some(progress);
+        self
+    }
+
+    /// Sends each block of server logs to `logs`. The server only sends
+    /// logs if the `send_logs_level` setting asks for them.
+    pub fn logs_to(mut self, logs: UnboundedSender<DataPacket>) -> Self {
+        self.sinks.logs = Some(logs);
+        self
+    }
+
+    /// Sends each block of profile events, the counters the server keeps
+    /// while running the query, to `profile_events`.
+    pub fn profile_events_to(
+        mut self,
+        profile_events: UnboundedSender<DataPacket>,
+    ) -> Self {
+        self.sinks.profile_events = Some(profile_events);
+        self
+    }
+
+    /// Runs the query, discarding any rows it returns.
+    pub async fn execute(self) -> Result<()> {
+        self.run(|_| Ok(())).await
+    }
+
+    /// Runs the query and deserializes the rows of its result.
+    ///
+    /// Columns map to struct fields by name and to tuple elements in order.
+    /// If a value does not fit its field, the error names the column.
+    pub async fn fetch<T: DeserializeOwned>(self) -> Result<Vec<T>> {
+        let mut rows = Vec::new();
+        self.run(|block| {
+            for row in block.rows() {
+                rows.push(row?);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(rows)
+    }
+
+    /// Runs the query and reads the rows of its result as values, for
+    /// results whose schema is not known ahead of time.
+    pub async fn fetch_values(self) -> Result<Vec<ValueRow>> {
+        let mut rows = Vec::new();
+        self.run(|block| {
+            for row in block.value_rows() {
+                rows.push(row?);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(rows)
+    }
+
+    /// Runs the query and converts each block of its result into an Arrow
+    /// record batch, see [`DataPacket::into_record_batch`].
+    #[cfg(feature = "arrow")]
+    pub async fn fetch_arrow(self) -> Result<Vec<arrow::array::RecordBatch>> {
+        self.fetch_arrow_with(false).await
+    }
+
+    /// Like [`Query::fetch_arrow`], but converts `String` columns to
+    /// `Utf8`, failing on values that are not UTF-8.
+    #[cfg(feature = "arrow")]
+    pub async fn fetch_arrow_utf8(
+        self,
+    ) -> Result<Vec<arrow::array::RecordBatch>> {
+        self.fetch_arrow_with(true).await
+    }
+
+    #[cfg(feature = "arrow")]
+    async fn fetch_arrow_with(
+        self,
+        utf8: bool,
+    ) -> Result<Vec<arrow::array::RecordBatch>> {
+        let mut batches = Vec::new();
+        self.run(|block| {
+            if block.rows_count > 0 {
+                batches.push(if utf8 {
+                    block.into_record_batch_utf8()?
+                } else {
+                    block.into_record_batch()?
+                });
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(batches)
+    }
+
+    /// Runs the query and gathers the blocks of its result into one data
+    /// frame, which has the columns of the result even if it has no rows.
+    /// See [`DataPacket::into_data_frame`] for the types of its columns.
+    #[cfg(feature = "polars")]
+    pub async fn fetch_data_frame(self) -> Result<polars::frame::DataFrame> {
+        self.fetch_data_frame_with(false).await
+    }
+
+    /// Like [`Query::fetch_data_frame`], but converts `String` columns to
+    /// `String` and `LowCardinality(String)` ones to `Categorical`, failing
+    /// on values that are not UTF-8.
+    #[cfg(feature = "polars")]
+    pub async fn fetch_data_frame_utf8(
+        self,
+    ) -> Result<polars::frame::DataFrame> {
+        self.fetch_data_frame_with(true).await
+    }
+
+    #[cfg(feature = "polars")]
+    async fn fetch_data_frame_with(
+        self,
+        utf8: bool,
+    ) -> Result<polars::frame::DataFrame> {
+        let mut frame: Option<polars::frame::DataFrame> = None;
+        self.run(|block| {
+            let block = if utf8 {
+                block.into_data_frame_utf8()?
+            } else {
+                block.into_data_frame()?
+            };
+            match &mut frame {
+                Some(frame) => {
+                    frame.vstack_mut_owned(block).map_err(|e| {
+                        crate::ClickHouseClientError::DecodeError(e.to_string())
+                    })?;
+                }
+                None => frame = Some(block),
+            }
+            Ok(())
+        })
+        .await?;
+        let mut frame = frame.unwrap_or_default();
+        frame.rechunk_mut();
+        Ok(frame)
+    }
+
+    /// Sends the query and streams the blocks of its result as they are
+    /// read, skipping those without rows. A block is only read when the
+    /// stream is polled for it, so a slow consumer holds the server back.
+    ///
+    /// Dropping the stream before its end cancels the query at once, or,
+    /// while a block is awaited, over TLS or if the socket is full, once the
+    /// client is next used. The rest of its response is skipped before the
+    /// client runs anything else, unless the stream was dropped partway
+    /// through a packet, which breaks the connection.
+    pub async fn stream(self) -> Result<BlockStream<'a>> {
+        self.client.send_query(&self.packet, &self.external).await?;
+        Ok(BlockStream {
+            idle: Some((self.client, self.sinks)),
+            reading: None,
+        })
+    }
+
+    /// Sends the query and hands each block of its result to `f`, starting
+    /// with the header block, which has the columns but no rows.
+    ///
+    /// Once `f` fails the query is cancelled and the error returned, as
+    /// for a dropped [`BlockStream`].
+    async fn run(
+        self,
+        mut f: impl FnMut(DataPacket) -> Result<()>,
+    ) -> Result<()> {
+        self.client.send_query(&self.packet, &self.external).await?;
+        loop {
+            match self.client.receive(&self.sinks).await? {
+                Response::Data(block) => {
+                    if let Err(e) = f(block) {
+                        self.client.try_cancel();
+                        return Err(e);
+                    }
+                }
+                Response::TableColumns(_) => {}
+                Response::EndOfStream => return Ok(()),
+            }
+        }
+    }
+}
+
+/// The blocks of a query's result as they arrive, see [`Query::stream`].
+pub struct BlockStream<'a> {
+    /// The client between blocks, and `None` once the result has ended.
+    idle: Option<(&'a mut Client, Sinks)>,
+    /// Reads the next block, handing back the client.
+    reading: Option<BoxFuture<'a, Reading<'a>>>,
+}
+
+type Reading<'a> = ((&'a mut Client, Sinks), Result<Option<DataPacket>>);
+
+/// Reads up to the next block with rows, or the end of the result.
+async fn next_block(client: &mut Client, sinks: Sinks) -> Reading<'_> {
+    let block = loop {
+        match client.receive(&sinks).await {
+            Ok(Response::Data(block)) if block.rows_count > 0 => {
+                break Ok(Some(block))
+            }
+            Ok(Response::Data(_) | Response::TableColumns(_)) => {}
+            Ok(Response::EndOfStream) => break Ok(None),
+            Err(e) => break Err(e),
+        }
+    };
+    ((client, sinks), block)
+}
+
+impl Stream for BlockStream<'_> {
+    type Item = Result<DataPacket>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let reading = match &mut this.reading {
+            Some(reading) => reading,
+            None => {
+                let Some((client, sinks)) = this.idle.take() else {
+                    return Poll::Ready(None);
+                };
+                this.reading.insert(next_block(client, sinks).boxed())
+            }
+        };
+        let (idle, block) = ready!(reading.poll_unpin(cx));
+        this.reading = None;
+        // the stream ends after its last block or an error
+        if let Ok(Some(_)) = block {
+            this.idle = Some(idle);
+        }
+        Poll::Ready(block.transpose())
+    }
+}
+
+impl Drop for BlockStream<'_> {
+    fn drop(&mut self) {
+        // a stream dropped while reading a block has no client to cancel
+        // with, which leaves it to the next use of the client
+        if let Some((client, _)) = &mut self.idle {
+            client.try_cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use futures::StreamExt;
+    use serde::Deserialize;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use crate::binary::ClickHouseEncoder;
+    use crate::client::test::{data, hello, serve};
+    use crate::external::ExternalTable;
+    use crate::protocol::client::{
+        ClickHouseWriteDataPacket, ClickHouseWriteQueryPacket, DataPacket,
+        Parameter, QueryPacket,
+    };
+    use crate::protocol::server::ServerPacketCode;
+    use crate::protocol::CLICKHOUSE_PROTOCOL_VERSION;
+    use crate::{ClickHouseClientError, Client, ClientOptions};
+
+    async fn result(rows: &[(u64, &str)]) -> Result<Vec<u8>> {
+        let ids = clickhouse_datatypes::Column::UInt64(
+            rows.iter().map(|(id, _)| *id).collect(),
+        );
+        let names = clickhouse_datatypes::Column::String(
+            rows.iter()
+                .map(|(_, name)| name.to_string().into())
+                .collect(),
+        );
+        let mut buf = data(
+            ServerPacketCode::Data,
+            vec![
+                ("id", clickhouse_datatypes::Column::new(&ids.data_type())),
+                (
+                    "name",
+                    clickhouse_datatypes::Column::new(&names.data_type()),
+                ),
+            ],
+        )
+        .await?;
+        buf.encode_u8(ServerPacketCode::Progress as u8).await?;
+        for value in [2, 16, 2, 0, 0] {
+            buf.encode_var_uint(value).await?;
+        }
+        buf.extend(
+            data(ServerPacketCode::Data, vec![("id", ids), ("name", names)])
+                .await?,
+        );
+        let events = vec![(
+            "name",
+            clickhouse_datatypes::Column::String(vec!["SelectedRows".into()]),
+        )];
+        buf.extend(data(ServerPacketCode::ProfileEvents, events).await?);
+        buf.encode_u8(ServerPacketCode::EndOfStream as u8).await?;
+        Ok(buf)
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        id: u64,
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_bind() -> Result<()> {
+        let mut responses = Vec::new();
+        responses
+            .encode_u8(ServerPacketCode::EndOfStream as u8)
+            .await?;
+        let (addr, sent) = serve(responses).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let body = "SELECT {id:UInt64}, {name:String}";
+        client
+            .query(body)
+            .bind("id", 42u64)
+            .bind("name", "it's")
+            .execute()
+            .await?;
+        drop(client);
+
+        let mut query = QueryPacket::new(body);
+        query.parameters = vec![
+            Parameter {
+                name: "id".into(),
+                value: "42".into(),
+            },
+            Parameter {
+                name: "name".into(),
+                value: r"it\'s".into(),
+            },
+        ];
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(&query, CLICKHOUSE_PROTOCOL_VERSION)
+            .await?;
+        expected
+            .write_data_packet(
+                &DataPacket::default(),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_external_table() -> Result<()> {
+        let mut responses = Vec::new();
+        responses
+            .encode_u8(ServerPacketCode::EndOfStream as u8)
+            .await?;
+        let (addr, sent) = serve(responses).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let ids = clickhouse_datatypes::Column::UInt64(vec![1, 2, 3]);
+        let allowed = ExternalTable::new("allowed").column("id", ids)?;
+        let body = "SELECT count() FROM events WHERE id IN allowed";
+        client
+            .query(body)
+            .external_table(allowed.clone())
+            .execute()
+            .await?;
+        drop(client);
+
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(
+                &QueryPacket::new(body),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        for block in [allowed.block(), &DataPacket::default()] {
+            expected
+                .write_data_packet(block, CLICKHOUSE_PROTOCOL_VERSION)
+                .await?;
+        }
+        assert!(sent.await??.ends_with(&expected));
+
+        let error = allowed
+            .column("name", clickhouse_datatypes::Column::String(Vec::new()))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "encode error: column `name` of external table `allowed` has 0 \
+             rows, expected 3"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch() -> Result<()> {
+        let mut responses = result(&[(1, "one"), (2, "two")]).await?;
+        responses.extend(result(&[(3, "three")]).await?);
+        responses.extend(result(&[(4, "it's")]).await?);
+        responses
+            .encode_u8(ServerPacketCode::Exception as u8)
+            .await?;
+        responses.encode_i32(60).await?;
+        responses.encode_utf8_string("DB::Exception").await?;
+        responses.encode_utf8_string("Unknown table").await?;
+        responses.encode_utf8_string("").await?;
+        responses.encode_bool(false).await?;
+        let (addr, _) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        assert_eq!(client.server().display_name, "test");
+
+        let rows = client.query("SELECT id, name").fetch::<Row>().await?;
+        assert_eq!(
+            rows,
+            [
+                Row {
+                    id: 1,
+                    name: "one".into()
+                },
+                Row {
+                    id: 2,
+                    name: "two".into()
+                }
+            ]
+        );
+
+        // a row that does not fit still leaves the connection usable
+        let error = client
+            .query("SELECT id, name")
+            .fetch::<(u64, u64)>()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot deserialize column `name`: invalid type: string \
+             \"three\", expected u64"
+        );
+
+        let rows = client.query("SELECT id, name").fetch_values().await?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].columns()[1].0, "name");
+        assert_eq!(rows[0].get("name"), Some(&"it's".into()));
+        assert_eq!(rows[0][0].to_string(), "4");
+        assert_eq!(rows[0][1].to_string(), r"'it\'s'");
+
+        let error = client.query("SELECT * FROM missing").execute().await;
+        assert!(matches!(
+            error,
+            Err(ClickHouseClientError::ServerException { code: 60, .. })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream() -> Result<()> {
+        let mut responses = result(&[(1, "one"), (2, "two")]).await?;
+        responses.extend(result(&[(3, "three")]).await?);
+        responses.extend(result(&[(4, "four")]).await?);
+        let (addr, sent) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let (progress, mut progress_rx) = mpsc::unbounded_channel();
+        let (events, mut events_rx) = mpsc::unbounded_channel();
+        let mut blocks = client
+            .query("SELECT id, name")
+            .progress_to(progress)
+            .profile_events_to(events)
+            .stream()
+            .await?;
+        let block = blocks.next().await.transpose()?;
+        assert_eq!(block.map(|block| block.rows_count), Some(2));
+        assert!(blocks.next().await.is_none());
+        drop(blocks);
+        assert_eq!(progress_rx.recv().await.map(|p| p.rows), Some(2));
+        assert_eq!(events_rx.recv().await.map(|e| e.rows_count), Some(1));
+
+        // dropping a stream early cancels the rest of its query
+        let mut blocks = client.query("SELECT id, name").stream().await?;
+        assert!(blocks.next().await.is_some());
+        drop(blocks);
+        let rows = client.query("SELECT id, name").fetch::<Row>().await?;
+        assert_eq!(rows[0].id, 4);
+        drop(client);
+
+        let query = QueryPacket::new("SELECT id, name");
+        let mut expected = Vec::new();
+        for cancel in [false, true, false] {
+            expected
+                .write_query_packet(&query, CLICKHOUSE_PROTOCOL_VERSION)
+                .await?;
+            expected
+                .write_data_packet(
+                    &DataPacket::default(),
+                    CLICKHOUSE_PROTOCOL_VERSION,
+                )
+                .await?;
+            if cancel {
+                expected.encode_var_uint(3).await?;
+            }
+        }
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_cancelled_on_drop() -> Result<()> {
+        let (addr, sent) = serve(result(&[(1, "one")]).await?).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let blocks = client.query("SELECT id, name").stream().await?;
+        // the cancel is sent without the client being used again
+        drop(blocks);
+        drop(client);
+
+        let mut expected = Vec::new();
+        expected
+            .write_query_packet(
+                &QueryPacket::new("SELECT id, name"),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        expected
+            .write_data_packet(
+                &DataPacket::default(),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        expected.encode_var_uint(3).await?;
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_on_error() -> Result<()> {
+        #[derive(Debug, Deserialize)]
+        struct TextId {
+            #[allow(dead_code)]
+            id: String,
+        }
+
+        let (addr, sent) =
+            serve(result(&[(1, "one")]).await?.repeat(2)).await?;
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let error = client.query("SELECT id, name").fetch::<TextId>().await;
+        assert!(error.is_err());
+        // the first result is skipped
+        let rows = client.query("SELECT id, name").fetch::<Row>().await?;
+        assert_eq!(rows.len(), 1);
+        drop(client);
+
+        let mut query = Vec::new();
+        query
+            .write_query_packet(
+                &QueryPacket::new("SELECT id, name"),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        query
+            .write_data_packet(
+                &DataPacket::default(),
+                CLICKHOUSE_PROTOCOL_VERSION,
+            )
+            .await?;
+        let mut expected = query.clone();
+        // the cancel goes out as soon as the first block fails
+        expected.encode_var_uint(3).await?;
+        expected.extend(query);
+        assert!(sent.await??.ends_with(&expected));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_dropped_while_waiting() -> Result<()> {
+        // the server answers only once the client has stopped waiting
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        let responses = result(&[(1, "one")]).await?.repeat(2);
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            socket.write_all(&hello().await?).await?;
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            socket.write_all(&responses).await?;
+            socket.read_to_end(&mut Vec::new()).await?;
+            anyhow::Ok(())
+        });
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let mut blocks = client.query("SELECT id, name").stream().await?;
+        let next =
+            tokio::time::timeout(Duration::from_millis(50), blocks.next());
+        assert!(next.await.is_err());
+        drop(blocks);
+        // the first result is skipped
+        let rows = client.query("SELECT id, name").fetch::<Row>().await?;
+        assert_eq!(
+            rows,
+            [Row {
+                id: 1,
+                name: "one".into()
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broken() -> Result<()> {
+        // the server stops in the middle of a block
+        let mut responses = Vec::new();
+        responses.encode_u8(ServerPacketCode::Data as u8).await?;
+        let (addr, _) = serve(responses).await?;
+
+        let mut client =
+            Client::connect(ClientOptions::default().addr(addr)).await?;
+        let mut blocks = client.query("SELECT 1").stream().await?;
+        let next =
+            tokio::time::timeout(Duration::from_millis(50), blocks.next());
+        assert!(next.await.is_err());
+        drop(blocks);
+        let error = client.query("SELECT 1").execute().await;
+        assert!(matches!(
+            error,
+            Err(ClickHouseClientError::ConnectionBroken)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,758 @@
+use clickhouse_datatypes::Column;
+use serde::de::value::{
+    BorrowedStrDeserializer, MapDeserializer, SeqDeserializer,
+};
+use serde::de::{
+    DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client;
+
+/// A named column, which a row maps onto a struct field.
+pub(crate) trait Field {
+    fn name(&self) -> &str;
+    fn column(&self) -> &Column;
+}
+
+impl Field for client::Column {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn column(&self) -> &Column {
+        &self.data
+    }
+}
+
+/// The fields of a `Nested` column.
+impl Field for (String, Column) {
+    fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn column(&self) -> &Column {
+        &self.1
+    }
+}
+
+/// The elements of a `Tuple` column, which only map onto struct fields if
+/// they are all named.
+impl Field for (Option<String>, Column) {
+    fn name(&self) -> &str {
+        self.0.as_deref().unwrap_or_default()
+    }
+
+    fn column(&self) -> &Column {
+        &self.1
+    }
+}
+
+/// Attributes an error to the column it happened in. Errors from within a
+/// named tuple end up with the path to the element, `outer.inner`.
+fn in_column(
+    name: &str,
+    error: ClickHouseClientError,
+) -> ClickHouseClientError {
+    let (column, message) = match error {
+        ClickHouseClientError::ColumnDeserializeError { column, message } => {
+            (format!("{name}.{column}"), message)
+        }
+        ClickHouseClientError::DeserializeError(message) => {
+            (name.to_owned(), message)
+        }
+        error => (name.to_owned(), error.to_string()),
+    };
+    ClickHouseClientError::ColumnDeserializeError { column, message }
+}
+
+/// Deserializes one row of a block.
+///
+/// Structs and maps take the columns by name, tuples and sequences take
+/// them in order, and any other type needs a block with a single column.
+pub(crate) struct RowDeserializer<'de, F> {
+    fields: &'de [F],
+    row: usize,
+}
+
+impl<'de, F: Field> RowDeserializer<'de, F> {
+    pub(crate) fn new(fields: &'de [F], row: usize) -> Self {
+        RowDeserializer { fields, row }
+    }
+
+    fn fields(&self) -> Fields<'de, F> {
+        Fields {
+            fields: self.fields.iter(),
+            row: self.row,
+            value: None,
+        }
+    }
+
+    fn single(&self) -> Result<&'de F> {
+        match self.fields {
+            [field] => Ok(field),
+            fields => Err(ClickHouseClientError::DeserializeError(format!(
+                "expected a single column, got {}",
+                fields.len()
+            ))),
+        }
+    }
+}
+
+macro_rules! forward_to_single_column {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                let field = self.single()?;
+                ValueDeserializer::new(field.column(), self.row)
+                    .$method(visitor)
+                    .map_err(|e| in_column(field.name(), e))
+            }
+        )*
+    };
+}
+
+impl<'de, F: Field> Deserializer<'de> for RowDeserializer<'de, F> {
+    type Error = ClickHouseClientError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(self.fields())
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.fields())
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if len != self.fields.len() {
+            return Err(ClickHouseClientError::DeserializeError(format!(
+                "expected {len} columns, got {}",
+                self.fields.len()
+            )));
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let field = self.single()?;
+        ValueDeserializer::new(field.column(), self.row)
+            .deserialize_unit_struct(name, visitor)
+            .map_err(|e| in_column(field.name(), e))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let field = self.single()?;
+        ValueDeserializer::new(field.column(), self.row)
+            .deserialize_enum(name, variants, visitor)
+            .map_err(|e| in_column(field.name(), e))
+    }
+
+    forward_to_single_column! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_option
+        deserialize_unit deserialize_identifier deserialize_ignored_any
+    }
+}
+
+/// Walks the fields of a row, as a map from their names or as a sequence.
+struct Fields<'de, F> {
+    fields: std::slice::Iter<'de, F>,
+    row: usize,
+    value: Option<&'de F>,
+}
+
+impl<'de, F: Field> Fields<'de, F> {
+    fn deserialize<S: DeserializeSeed<'de>>(
+        &self,
+        field: &'de F,
+        seed: S,
+    ) -> Result<S::Value> {
+        seed.deserialize(ValueDeserializer::new(field.column(), self.row))
+            .map_err(|e| in_column(field.name(), e))
+    }
+}
+
+impl<'de, F: Field> MapAccess<'de> for Fields<'de, F> {
+    type Error = ClickHouseClientError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>> {
+        let Some(field) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.value = Some(field);
+        seed.deserialize(BorrowedStrDeserializer::new(field.name()))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value> {
+        let field = self.value.take().ok_or_else(|| {
+            ClickHouseClientError::DeserializeError(
+                "value requested before its key".into(),
+            )
+        })?;
+        self.deserialize(field, seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+impl<'de, F: Field> SeqAccess<'de> for Fields<'de, F> {
+    type Error = ClickHouseClientError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        match self.fields.next() {
+            Some(field) => self.deserialize(field, seed).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+/// Deserializes the value at `row` of a column.
+#[derive(Clone, Copy)]
+struct ValueDeserializer<'de> {
+    column: &'de Column,
+    row: usize,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    fn new(column: &'de Column, row: usize) -> Self {
+        ValueDeserializer { column, row }
+    }
+
+    /// Looks through `Nullable`, `LowCardinality`, `Variant` and `Dynamic`
+    /// to the column that holds the value, `None` if it is `NULL`.
+    fn resolve(self) -> Result<Option<Self>> {
+        let (column, row) = match self.column {
+            Column::Nullable(nullable) if nullable.is_null(self.row) => {
+                return Ok(None)
+            }
+            Column::Nullable(nullable) => (nullable.inner(), self.row),
+            Column::LowCardinality(column) => {
+                (column.dictionary(), column.key(self.row)?)
+            }
+            Column::Variant(variant) => match variant.value(self.row) {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            Column::Dynamic(dynamic) => match dynamic.value(self.row) {
+                Some(value) => value,
+                // values of the shared variant read as their encoded bytes
+                None if dynamic.shared(self.row).is_some() => {
+                    return Ok(Some(self))
+                }
+                None => return Ok(None),
+            },
+            _ => return Ok(Some(self)),
+        };
+        ValueDeserializer::new(column, row).resolve()
+    }
+
+    /// Visits a value that has been [resolved](Self::resolve).
+    fn visit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let ValueDeserializer { column, row } = self;
+        match column {
+            Column::UInt8(_) => visitor.visit_u8(column.get(row)?),
+            Column::UInt16(_) | Column::Date(_) => {
+                visitor.visit_u16(column.get(row)?)
+            }
+            Column::UInt32(_) | Column::DateTime { .. } => {
+                visitor.visit_u32(column.get(row)?)
+            }
+            Column::UInt64(_) => visitor.visit_u64(column.get(row)?),
+            Column::UInt128(_) => visitor.visit_u128(column.get(row)?),
+            Column::Int8(_) => visitor.visit_i8(column.get(row)?),
+            Column::Int16(_) => visitor.visit_i16(column.get(row)?),
+            Column::Int32(_) | Column::Date32(_) => {
+                visitor.visit_i32(column.get(row)?)
+            }
+            Column::Int64(_) | Column::DateTime64 { .. } => {
+                visitor.visit_i64(column.get(row)?)
+            }
+            Column::Int128(_) => visitor.visit_i128(column.get(row)?),
+            Column::Float32(_) => visitor.visit_f32(column.get(row)?),
+            Column::Float64(_) => visitor.visit_f64(column.get(row)?),
+            Column::Bool(_) => visitor.visit_bool(column.get(row)?),
+            Column::String(_) | Column::FixedString(_) => {
+                match column.get::<&str>(row) {
+                    Ok(value) => visitor.visit_borrowed_str(value),
+                    Err(_) => visitor.visit_borrowed_bytes(column.get(row)?),
+                }
+            }
+            Column::Json { .. } | Column::Enum8(_) | Column::Enum16(_) => {
+                visitor.visit_borrowed_str(column.get(row)?)
+            }
+            Column::Uuid(data) => {
+                visitor.visit_str(&at(data, row)?.to_string())
+            }
+            Column::IPv4(data) => {
+                visitor.visit_str(&at(data, row)?.to_string())
+            }
+            Column::IPv6(data) => {
+                visitor.visit_str(&at(data, row)?.to_string())
+            }
+            Column::Array(array)
+            | Column::Ring(array)
+            | Column::LineString(array)
+            | Column::MultiLineString(array)
+            | Column::Polygon(array)
+            | Column::MultiPolygon(array) => {
+                let inner = array.inner();
+                let mut seq = SeqDeserializer::new(
+                    array
+                        .range(row)?
+                        .map(|row| ValueDeserializer::new(inner, row)),
+                );
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Column::Nested(nested) => {
+                let fields = nested.fields();
+                let mut seq = SeqDeserializer::new(
+                    nested
+                        .range(row)?
+                        .map(|row| RowDeserializer::new(fields, row)),
+                );
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Column::Tuple(tuple) => visitor.visit_seq(Fields {
+                fields: tuple.elements().iter(),
+                row,
+                value: None,
+            }),
+            Column::Map(map) => {
+                let (keys, values) = (map.keys(), map.values());
+                let mut entries = MapDeserializer::new(
+                    map.entries().range(row)?.map(|row| {
+                        (
+                            ValueDeserializer::new(keys, row),
+                            ValueDeserializer::new(values, row),
+                        )
+                    }),
+                );
+                let value = visitor.visit_map(&mut entries)?;
+                entries.end()?;
+                Ok(value)
+            }
+            Column::Point(data) => {
+                let point = at(data, row)?;
+                let mut seq: SeqDeserializer<_, ClickHouseClientError> =
+                    SeqDeserializer::new([point.x, point.y].into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Column::AggregateFunction(_) => {
+                visitor.visit_borrowed_bytes(column.get(row)?)
+            }
+            Column::Dynamic(dynamic) => visitor
+                .visit_borrowed_bytes(dynamic.shared(row).unwrap_or_default()),
+            Column::Nullable(_)
+            | Column::LowCardinality(_)
+            | Column::Variant(_) => {
+                unreachable!("values are resolved before they are visited")
+            }
+        }
+    }
+
+    /// Whether the value is a tuple of named elements, which can be read as
+    /// a struct.
+    fn named_tuple(&self) -> Option<&'de [(Option<String>, Column)]> {
+        match self.column {
+            Column::Tuple(tuple)
+                if tuple.elements().iter().all(|(name, _)| name.is_some()) =>
+            {
+                Some(tuple.elements())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn at<T>(data: &[T], row: usize) -> Result<&T> {
+    data.get(row).ok_or_else(|| {
+        clickhouse_datatypes::DataTypeError::RowOutOfBounds {
+            row,
+            len: data.len(),
+        }
+        .into()
+    })
+}
+
+impl<'de> IntoDeserializer<'de, ClickHouseClientError>
+    for ValueDeserializer<'de>
+{
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de, F: Field> IntoDeserializer<'de, ClickHouseClientError>
+    for RowDeserializer<'de, F>
+{
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = ClickHouseClientError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(value) => value.visit(visitor),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(value) => visitor.visit_some(value),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(value) => value.visit(visitor),
+            None => visitor.visit_unit(),
+        }
+    }
+
+    /// Enum columns also read as their codes.
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(
+                value @ ValueDeserializer {
+                    column: Column::Enum8(_),
+                    row,
+                },
+            ) => visitor.visit_i8(value.column.get(row)?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(
+                value @ ValueDeserializer {
+                    column: Column::Enum16(_),
+                    row,
+                },
+            ) => visitor.visit_i16(value.column.get(row)?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /// `String` columns read as bytes even if they are valid UTF-8.
+    fn deserialize_bytes<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.resolve()? {
+            Some(ValueDeserializer {
+                column:
+                    column @ (Column::String(_)
+                    | Column::FixedString(_)
+                    | Column::AggregateFunction(_)),
+                row,
+            }) => visitor.visit_borrowed_bytes(column.get(row)?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// Rust enums of unit variants are read from the names of enum values,
+    /// or from strings.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let Some(value) = self.resolve()? else {
+            return visitor.visit_none();
+        };
+        match value.column.get::<&str>(value.row) {
+            Ok(name) => visitor.visit_enum(BorrowedStrDeserializer::new(name)),
+            Err(_) => value.visit(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Named tuples read as maps from their element names.
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let Some(value) = self.resolve()? else {
+            return visitor.visit_none();
+        };
+        match value.named_tuple() {
+            Some(elements) => visitor
+                .visit_map(RowDeserializer::new(elements, value.row).fields()),
+            None => value.visit(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string seq
+        tuple tuple_struct unit_struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use clickhouse_datatypes::DataType;
+    use serde::Deserialize;
+
+    use crate::protocol::client::{Column, DataPacket};
+    use crate::ClickHouseClientError;
+
+    fn block(columns: Vec<(&str, &str)>) -> Result<DataPacket> {
+        let columns = columns
+            .into_iter()
+            .map(|(name, column_type)| {
+                let column_type: DataType = column_type.parse()?;
+                Ok(Column {
+                    name: name.to_owned(),
+                    data: clickhouse_datatypes::Column::new(&column_type),
+                    column_type,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DataPacket {
+            columns_count: columns.len() as u64,
+            columns,
+            ..Default::default()
+        })
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Click,
+        View,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Position {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Event<'a> {
+        id: u64,
+        name: &'a str,
+        score: Option<f64>,
+        tags: Vec<String>,
+        kind: Kind,
+        position: Position,
+        attrs: HashMap<String, u8>,
+    }
+
+    #[test]
+    fn test_deserialize_rows() -> Result<()> {
+        let mut packet = block(vec![
+            ("kind", "Enum8('click' = 1, 'view' = 2)"),
+            ("id", "UInt32"),
+            ("name", "LowCardinality(String)"),
+            ("score", "Nullable(Float64)"),
+            ("tags", "Array(String)"),
+            ("position", "Tuple(x Int32, y Int32)"),
+            ("attrs", "Map(String, UInt8)"),
+            ("ignored", "UInt8"),
+        ])?;
+        packet.columns[0].data.push("view")?;
+        packet.columns[1].data.push(7_u32)?;
+        packet.columns[2].data.push("home")?;
+        packet.columns[3].data.push(None::<f64>)?;
+        packet.columns[4].data.push(vec!["a", "b"])?;
+        packet.columns[5].data.push((1_i32, -2_i32))?;
+        packet.columns[6].data.push(vec![("k", 3_u8)])?;
+        packet.columns[7].data.push(1_u8)?;
+        packet.rows_count = 1;
+
+        let rows: Vec<Event> = packet.rows().collect::<crate::Result<_>>()?;
+        assert_eq!(
+            rows,
+            [Event {
+                id: 7,
+                name: "home",
+                score: None,
+                tags: vec!["a".into(), "b".into()],
+                kind: Kind::View,
+                position: Position { x: 1, y: -2 },
+                attrs: HashMap::from([("k".into(), 3)]),
+            }]
+        );
+
+        let mut ids = block(vec![("id", "UInt64")])?;
+        ids.columns[0].data.push(1_u64)?;
+        ids.columns[0].data.push(2_u64)?;
+        ids.rows_count = 2;
+        let rows: Vec<u64> = ids.rows().collect::<crate::Result<_>>()?;
+        assert_eq!(rows, [1, 2]);
+        let rows: Vec<(u64,)> = ids.rows().collect::<crate::Result<_>>()?;
+        assert_eq!(rows, [(1,), (2,)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_errors() -> Result<()> {
+        let mut packet = block(vec![
+            ("id", "Int64"),
+            ("position", "Tuple(x Int32, y Int32)"),
+        ])?;
+        packet.columns[0].data.push(-1_i64)?;
+        packet.columns[1].data.push((1_i32, 2_i32))?;
+        packet.rows_count = 1;
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Unsigned {
+            id: u64,
+        }
+        let error = packet.rows::<Unsigned>().next().unwrap().unwrap_err();
+        assert!(matches!(
+            &error,
+            ClickHouseClientError::ColumnDeserializeError { column, .. }
+                if column == "id"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "cannot deserialize column `id`: invalid value: integer `-1`, \
+             expected u64"
+        );
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Named {
+            position: Point,
+        }
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Point {
+            x: String,
+            y: i32,
+        }
+        let error = packet.rows::<Named>().next().unwrap().unwrap_err();
+        assert!(
+            error.to_string().starts_with(
+                "cannot deserialize column `position.x`: invalid type"
+            ),
+            "{error}"
+        );
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Missing {
+            id: i64,
+            name: String,
+        }
+        let error = packet.rows::<Missing>().next().unwrap().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "deserialize error: missing field `name`"
+        );
+
+        assert!(packet.rows::<i64>().next().unwrap().is_err());
+        assert!(packet.rows::<(i64,)>().next().unwrap().is_err());
+        Ok(())
+    }
+}
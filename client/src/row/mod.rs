@@ -0,0 +1,6 @@
+mod de;
+mod value;
+
+pub(crate) use de::RowDeserializer;
+pub(crate) use value::value_rows;
+pub use value::ValueRow;
@@ -0,0 +1,76 @@
+use std::ops::Index;
+use std::sync::Arc;
+
+use clickhouse_datatypes::{DataType, Value};
+
+use crate::error::Result;
+use crate::protocol::client::DataPacket;
+
+/// A row read without knowing its schema, holding one [`Value`] per column
+/// along with the names and types of the columns.
+///
+/// The rows of a block share their column list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueRow {
+    columns: Arc<[(String, DataType)]>,
+    values: Vec<Value>,
+}
+
+impl ValueRow {
+    /// The names and types of the columns, in order.
+    pub fn columns(&self) -> &[(String, DataType)] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// The value of the column named `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|(x, _)| x == name)?;
+        self.values.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Index<usize> for ValueRow {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        &self.values[index]
+    }
+}
+
+/// The rows of `block`, see [`DataPacket::value_rows`].
+pub(crate) fn value_rows(
+    block: &DataPacket,
+) -> impl Iterator<Item = Result<ValueRow>> + '_ {
+    let columns: Arc<[_]> = block
+        .columns
+        .iter()
+        .map(|c| (c.name.clone(), c.column_type.clone()))
+        .collect();
+    (0..block.rows_count as usize).map(move |row| {
+        let values = block
+            .columns
+            .iter()
+            .map(|c| c.data.get(row))
+            .collect::<Result<_, _>>()?;
+        Ok(ValueRow {
+            columns: columns.clone(),
+            values,
+        })
+    })
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::num::NonZeroU64;
+use std::time::Duration;
+
+use crate::error::{ClickHouseClientError, Result};
+use crate::protocol::client::Settings;
+
+/// Settings for a query or insert, checked before they are sent.
+///
+/// The settings with methods of their own are marked important, so that a
+/// server which does not know one fails the query instead of ignoring it.
+/// Others are set with [`QuerySettings::set`], whose values are checked if
+/// the setting is one this client knows.
+#[derive(Debug, Clone, Default)]
+pub struct QuerySettings {
+    settings: Vec<Settings>,
+}
+
+/// What a query with `readonly` set may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readonly {
+    /// Anything.
+    Off = 0,
+    /// Only read data, without changing settings.
+    On = 1,
+    /// Only read data and change settings.
+    ChangeSettings = 2,
+}
+
+/// What a query does once it reaches a limit such as `max_result_rows`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Fail.
+    Throw,
+    /// Stop and return what it has.
+    Break,
+}
+
+/// The least severe server logs sent with a query, see
+/// [`crate::Query::logs_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    None,
+    Fatal,
+    Error,
+    Warning,
+    Information,
+    Debug,
+    Trace,
+    Test,
+}
+
+impl fmt::Display for OverflowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OverflowMode::Throw => "throw",
+            OverflowMode::Break => "break",
+        })
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::None => "none",
+            LogLevel::Fatal => "fatal",
+            LogLevel::Error => "error",
+            LogLevel::Warning => "warning",
+            LogLevel::Information => "information",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+            LogLevel::Test => "test",
+        })
+    }
+}
+
+/// The values a known setting takes.
+enum Kind {
+    UInt,
+    /// A count that cannot be 0.
+    Positive,
+    Bool,
+    /// Seconds, which may have a fraction.
+    Seconds,
+    /// A count of threads, or `auto` for as many as there are cores.
+    Threads,
+    OneOf(&'static [&'static str]),
+    String,
+}
+
+const OVERFLOW_MODES: &[&str] = &["throw", "break"];
+
+const KNOWN: &[(&str, Kind)] = &[
+    ("max_threads", Kind::Threads),
+    ("max_execution_time", Kind::Seconds),
+    ("max_memory_usage", Kind::UInt),
+    ("max_block_size", Kind::Positive),
+    ("max_insert_block_size", Kind::Positive),
+    ("max_result_rows", Kind::UInt),
+    ("max_result_bytes", Kind::UInt),
+    ("result_overflow_mode", Kind::OneOf(OVERFLOW_MODES)),
+    ("max_rows_to_read", Kind::UInt),
+    ("max_bytes_to_read", Kind::UInt),
+    ("read_overflow_mode", Kind::OneOf(OVERFLOW_MODES)),
+    ("readonly", Kind::OneOf(&["0", "1", "2"])),
+    ("priority", Kind::UInt),
+    ("async_insert", Kind::Bool),
+    ("wait_for_async_insert", Kind::Bool),
+    ("use_query_cache", Kind::Bool),
+    ("join_use_nulls", Kind::Bool),
+    (
+        "send_logs_level",
+        Kind::OneOf(&[
+            "none",
+            "fatal",
+            "error",
+            "warning",
+            "information",
+            "debug",
+            "trace",
+            "test",
+        ]),
+    ),
+    ("log_comment", Kind::String),
+];
+
+impl QuerySettings {
+    pub fn new() -> Self {
+        QuerySettings::default()
+    }
+
+    /// The most threads the query runs on, with 0 for one per core.
+    pub fn max_threads(self, threads: u64) -> Self {
+        self.known("max_threads", threads)
+    }
+
+    /// Fails the query once it has run for this long, to the millisecond.
+    pub fn max_execution_time(self, time: Duration) -> Self {
+        let millis = time.as_millis();
+        let seconds = if millis.is_multiple_of(1000) {
+            (millis / 1000).to_string()
+        } else {
+            format!("{}.{:03}", millis / 1000, millis % 1000)
+        };
+        self.known("max_execution_time", seconds)
+    }
+
+    /// The most memory the query uses on one server, with 0 for no limit.
+    pub fn max_memory_usage(self, bytes: u64) -> Self {
+        self.known("max_memory_usage", bytes)
+    }
+
+    /// How many rows the blocks of the query hold.
+    pub fn max_block_size(self, rows: NonZeroU64) -> Self {
+        self.known("max_block_size", rows)
+    }
+
+    /// How many rows the blocks written by an insert hold.
+    pub fn max_insert_block_size(self, rows: NonZeroU64) -> Self {
+        self.known("max_insert_block_size", rows)
+    }
+
+    /// Stops the query once its result has this many rows, as `mode` says.
+    pub fn max_result_rows(self, rows: u64, mode: OverflowMode) -> Self {
+        self.known("max_result_rows", rows)
+            .known("result_overflow_mode", mode)
+    }
+
+    /// Stops the query once it has read this many rows, as `mode` says.
+    pub fn max_rows_to_read(self, rows: u64, mode: OverflowMode) -> Self {
+        self.known("max_rows_to_read", rows)
+            .known("read_overflow_mode", mode)
+    }
+
+    pub fn readonly(self, readonly: Readonly) -> Self {
+        self.known("readonly", readonly as u8)
+    }
+
+    /// Has the server send its logs of the query from `level` up.
+    pub fn send_logs_level(self, level: LogLevel) -> Self {
+        self.known("send_logs_level", level)
+    }
+
+    /// A comment logged with the query in `system.query_log`.
+    pub fn log_comment(self, comment: impl Into<String>) -> Self {
+        self.known("log_comment", comment.into())
+    }
+
+    /// Sets any setting, which the server ignores if it does not know it.
+    ///
+    /// The name must be made of letters, digits and underscores, and the
+    /// value must fit the setting if this client knows it.
+    pub fn set(self, key: &str, value: impl fmt::Display) -> Result<Self> {
+        self.checked(Settings::new(key, value.to_string()))
+    }
+
+    /// Like [`QuerySettings::set`], but the query fails on a server which
+    /// does not know the setting.
+    pub fn set_important(
+        self,
+        key: &str,
+        value: impl fmt::Display,
+    ) -> Result<Self> {
+        self.checked(Settings {
+            important: true,
+            ..Settings::new(key, value.to_string())
+        })
+    }
+
+    /// Sets a custom setting, which the query reads with `getSetting`.
+    ///
+    /// The name must start with one of the server's
+    /// `custom_settings_prefixes`, `custom_` by default. The value is sent
+    /// as a string.
+    pub fn set_custom(
+        self,
+        key: &str,
+        value: impl fmt::Display,
+    ) -> Result<Self> {
+        self.checked(Settings {
+            custom: true,
+            ..Settings::new(key, value.to_string())
+        })
+    }
+
+    /// The value set for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|setting| setting.key == key)
+            .map(|setting| setting.value.as_str())
+    }
+
+    pub fn into_vec(self) -> Vec<Settings> {
+        self.settings
+    }
+
+    fn checked(self, setting: Settings) -> Result<Self> {
+        let key = setting.key.as_str();
+        let error = |message: &str| ClickHouseClientError::InvalidSetting {
+            key: key.to_owned(),
+            message: message.to_owned(),
+        };
+        if key.is_empty()
+            || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(error("not a setting name"));
+        }
+        if let Some((_, kind)) = KNOWN.iter().find(|(name, _)| *name == key) {
+            check(kind, &setting.value).map_err(|message| error(&message))?;
+        }
+        Ok(self.with(setting))
+    }
+
+    /// Sets a setting whose value was made to fit it.
+    fn known(self, key: &str, value: impl fmt::Display) -> Self {
+        self.with(Settings {
+            important: true,
+            ..Settings::new(key, value.to_string())
+        })
+    }
+
+    /// Sets a setting, replacing a value set before.
+    fn with(mut self, setting: Settings) -> Self {
+        self.settings.retain(|set| set.key != setting.key);
+        self.settings.push(setting);
+        self
+    }
+}
+
+fn check(kind: &Kind, value: &str) -> Result<(), String> {
+    let valid = match kind {
+        Kind::UInt => value.parse::<u64>().is_ok(),
+        Kind::Positive => value.parse::<u64>().is_ok_and(|n| n > 0),
+        Kind::Bool => ["0", "1", "true", "false"]
+            .iter()
+            .any(|b| value.eq_ignore_ascii_case(b)),
+        Kind::Seconds => value
+            .parse::<f64>()
+            .is_ok_and(|s| s.is_finite() && s >= 0.0),
+        Kind::Threads => {
+            let auto = value
+                .strip_prefix("auto(")
+                .and_then(|n| n.strip_suffix(')'))
+                .unwrap_or(value);
+            value == "auto" || auto.parse::<u64>().is_ok()
+        }
+        Kind::OneOf(values) => values.contains(&value),
+        Kind::String => true,
+    };
+    if valid {
+        return Ok(());
+    }
+    let expected = match kind {
+        Kind::UInt => "an unsigned integer".to_owned(),
+        Kind::Positive => "a positive integer".to_owned(),
+        Kind::Bool => "0, 1, true or false".to_owned(),
+        Kind::Seconds => "a non-negative number of seconds".to_owned(),
+        Kind::Threads => "a number of threads or auto".to_owned(),
+        Kind::OneOf(values) => format!("one of {}", values.join(", ")),
+        Kind::String => unreachable!("any string is valid"),
+    };
+    Err(format!("expected {expected}, got {value:?}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU64;
+    use std::time::Duration;
+
+    use anyhow::Result;
+
+    use crate::settings::{LogLevel, OverflowMode, QuerySettings, Readonly};
+
+    #[test]
+    fn test_settings() -> Result<()> {
+        let settings = QuerySettings::new()
+            .max_threads(4)
+            .max_execution_time(Duration::from_millis(1500))
+            .max_block_size(NonZeroU64::new(1024).unwrap())
+            .max_result_rows(10, OverflowMode::Break)
+            .readonly(Readonly::ChangeSettings)
+            .send_logs_level(LogLevel::Warning)
+            .set("custom_tag", "x")?
+            .set_important("max_threads", "auto(2)")?
+            .set_custom("custom_source", "batch")?;
+        assert_eq!(settings.get("max_execution_time"), Some("1.500"));
+        assert_eq!(settings.get("result_overflow_mode"), Some("break"));
+        assert_eq!(settings.get("readonly"), Some("2"));
+        // a setting set twice keeps the last value
+        assert_eq!(settings.get("max_threads"), Some("auto(2)"));
+
+        let settings = settings.into_vec();
+        let important = |key: &str| {
+            settings.iter().find(|s| s.key == key).map(|s| s.important)
+        };
+        assert_eq!(important("send_logs_level"), Some(true));
+        assert_eq!(important("custom_tag"), Some(false));
+        let custom = |key: &str| {
+            settings.iter().find(|s| s.key == key).map(|s| s.custom)
+        };
+        assert_eq!(custom("custom_source"), Some(true));
+        assert_eq!(custom("custom_tag"), Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let error = |key: &str, value: &str| {
+            QuerySettings::new()
+                .set(key, value)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("max_block_size", "0"),
+            "invalid setting `max_block_size`: expected a positive integer, \
+             got \"0\""
+        );
+        assert_eq!(
+            error("readonly", "3"),
+            "invalid setting `readonly`: expected one of 0, 1, 2, got \"3\""
+        );
+        assert!(QuerySettings::new().set("max_threads", "many").is_err());
+        assert!(QuerySettings::new()
+            .set("max_execution_time", "-1")
+            .is_err());
+        assert!(QuerySettings::new().set("async_insert", "yes").is_err());
+        assert!(QuerySettings::new().set("x = 1; DROP", "1").is_err());
+        assert!(QuerySettings::new().set("unknown_setting", "any").is_ok());
+    }
+}
@@ -0,0 +1,385 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use clickhouse_datatypes::Value;
+
+use crate::error::{ClickHouseClientError, Result};
+
+/// A query with `?` and `:name` placeholders, which are filled in on the
+/// client with quoted literals or identifiers.
+///
+/// This is for servers that cannot take the `{name:Type}` parameters of
+/// [`crate::Query::bind`]. Placeholders in string literals, quoted
+/// identifiers and comments are left alone, as are `::` casts and the
+/// `{name:Type}` parameters, so a `?` meant as the ternary operator has to
+/// be written with `if` instead.
+///
+/// ```
+/// use clickhouse_client::{Identifier, Sql};
+///
+/// let sql = Sql::new("SELECT * FROM ? WHERE id IN ? AND name = :name")
+///     .bind(Identifier("events"))
+///     .bind(vec![1u64, 2])
+///     .bind_named("name", "it's")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     sql,
+///     r"SELECT * FROM `events` WHERE id IN [1,2] AND name = 'it\'s'"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sql {
+    template: String,
+    args: Vec<SqlArg>,
+    named: Vec<(String, SqlArg)>,
+}
+
+/// What a placeholder of [`Sql`] is filled in with: a [`Value`] written as
+/// a literal, or an [`Identifier`].
+#[derive(Debug, Clone)]
+pub struct SqlArg(String);
+
+/// The name of a table, column or other object, quoted in backticks.
+///
+/// A dotted name such as `db.table` is quoted as a single name, so its
+/// parts are bound to placeholders of their own, as in `?.?`.
+#[derive(Debug, Clone, Copy)]
+pub struct Identifier<'a>(pub &'a str);
+
+impl<T: Into<Value>> From<T> for SqlArg {
+    fn from(value: T) -> Self {
+        SqlArg(value.into().to_string())
+    }
+}
+
+impl From<Identifier<'_>> for SqlArg {
+    fn from(identifier: Identifier<'_>) -> Self {
+        SqlArg(identifier.to_string())
+    }
+}
+
+impl Display for Identifier<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('`')?;
+        for c in self.0.chars() {
+            match c {
+                '`' => f.write_str("\\`")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                '\0' => f.write_str("\\0")?,
+                c if c.is_ascii_control() => write!(f, "\\x{:02X}", c as u8)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('`')
+    }
+}
+
+impl Sql {
+    pub fn new(template: impl Into<String>) -> Self {
+        Sql {
+            template: template.into(),
+            args: Vec::new(),
+            named: Vec::new(),
+        }
+    }
+
+    /// Fills in the next `?`.
+    pub fn bind(mut self, arg: impl Into<SqlArg>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Fills in every `:name`, replacing an argument bound to it before.
+    pub fn bind_named(
+        mut self,
+        name: impl Into<String>,
+        arg: impl Into<SqlArg>,
+    ) -> Self {
+        let name = name.into();
+        self.named.retain(|(bound, _)| *bound != name);
+        self.named.push((name, arg.into()));
+        self
+    }
+
+    /// The query with its placeholders filled in.
+    ///
+    /// Fails unless every placeholder has an argument and every argument
+    /// has a placeholder.
+    pub fn build(&self) -> Result<String> {
+        let error = |message: String| ClickHouseClientError::BindError(message);
+        let mut sql = String::with_capacity(self.template.len());
+        let mut args = self.args.iter();
+        let mut used = vec![false; self.named.len()];
+        let mut chars = self.template.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let arg = match c {
+                '\'' | '`' | '"' => {
+                    let end = quoted_end(&self.template, i, c);
+                    sql.push_str(&self.template[i..end]);
+                    skip_to(&mut chars, end);
+                    continue;
+                }
+                '-' if self.template[i..].starts_with("--") => {
+                    let end = self.template[i..]
+                        .find('\n')
+                        .map_or(self.template.len(), |n| i + n);
+                    sql.push_str(&self.template[i..end]);
+                    skip_to(&mut chars, end);
+                    continue;
+                }
+                '/' if self.template[i..].starts_with("/*") => {
+                    let end = self.template[i + 2..]
+                        .find("*/")
+                        .map_or(self.template.len(), |n| i + n + 4);
+                    sql.push_str(&self.template[i..end]);
+                    skip_to(&mut chars, end);
+                    continue;
+                }
+                '?' => args.next().ok_or_else(|| {
+                    error(format!(
+                        "no argument for placeholder {}",
+                        self.args.len() + 1
+                    ))
+                })?,
+                ':' if is_named(&self.template, i) => {
+                    let name_end = self.template[i + 1..]
+                        .find(|c: char| !is_identifier_char(c))
+                        .map_or(self.template.len(), |n| i + 1 + n);
+                    let name = &self.template[i + 1..name_end];
+                    let Some(n) =
+                        self.named.iter().position(|(bound, _)| bound == name)
+                    else {
+                        return Err(error(format!(
+                            "no argument for `:{name}`"
+                        )));
+                    };
+                    used[n] = true;
+                    skip_to(&mut chars, name_end);
+                    &self.named[n].1
+                }
+                c => {
+                    sql.push(c);
+                    continue;
+                }
+            };
+            // a negative number after a minus would start a comment
+            if sql.ends_with('-') && arg.0.starts_with('-') {
+                sql.push(' ');
+            }
+            sql.push_str(&arg.0);
+        }
+        if args.next().is_some() {
+            return Err(error(format!(
+                "{} arguments for {} placeholders",
+                self.args.len(),
+                self.args.len() - args.count() - 1
+            )));
+        }
+        if let Some(n) = used.iter().position(|used| !used) {
+            return Err(error(format!(
+                "no placeholder for `:{}`",
+                self.named[n].0
+            )));
+        }
+        Ok(sql)
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether the `:` at `i` begins a `:name` placeholder, rather than a `::`
+/// cast or the type of a `{name:Type}` parameter.
+fn is_named(template: &str, i: usize) -> bool {
+    let before = template[..i].chars().next_back();
+    let after = template[i + 1..].chars().next();
+    !before.is_some_and(|c| is_identifier_char(c) || c == ':')
+        && after.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// The end of the string literal or quoted identifier opened by `quote` at
+/// `start`, which may escape its quote with a backslash or by doubling it.
+fn quoted_end(template: &str, start: usize, quote: char) -> usize {
+    let bytes = template.as_bytes();
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote as u8 => {
+                if bytes.get(i + 1) != Some(&b) {
+                    return i + 1;
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    // an unclosed quote runs to the end, for the server to reject
+    template.len()
+}
+
+fn skip_to(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    end: usize,
+) {
+    while chars.next_if(|(i, _)| *i < end).is_some() {}
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bytes::Bytes;
+    use clickhouse_datatypes::Value;
+
+    use crate::sql::{Identifier, Sql};
+
+    #[test]
+    fn test_bind() -> Result<()> {
+        let sql = Sql::new(
+            "SELECT ?, :x, '?:x', `?`, \"?\" -- ?\n\
+             /* :x */ x::UInt8, {id:UInt64}, 1-?, :x",
+        )
+        .bind(Value::Tuple(vec![Value::Null, Value::Date(19723)]))
+        .bind_named("x", Identifier("a`b"))
+        .bind(-1i64)
+        .build()?;
+        assert_eq!(
+            sql,
+            "SELECT (NULL,'2024-01-01'), `a\\`b`, '?:x', `?`, \"?\" -- ?\n\
+             /* :x */ x::UInt8, {id:UInt64}, 1- -1, `a\\`b`"
+        );
+
+        let error = |sql: Sql| sql.build().unwrap_err().to_string();
+        assert_eq!(
+            error(Sql::new("SELECT ?, ?").bind(1)),
+            "bind error: no argument for placeholder 2"
+        );
+        assert_eq!(
+            error(Sql::new("SELECT ?").bind(1).bind(2)),
+            "bind error: 2 arguments for 1 placeholders"
+        );
+        assert_eq!(
+            error(Sql::new("SELECT :a")),
+            "bind error: no argument for `:a`"
+        );
+        assert_eq!(
+            error(Sql::new("SELECT 1").bind_named("a", 1)),
+            "bind error: no placeholder for `:a`"
+        );
+        Ok(())
+    }
+
+    /// A xorshift generator, so that failures can be reproduced.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn bytes(&mut self) -> Vec<u8> {
+            const TRICKY: &[&[u8]] = &[
+                b"'",
+                b"''",
+                b"\\",
+                b"`",
+                b"\"",
+                b"?",
+                b":x",
+                b"--",
+                b"/*",
+                b"*/",
+                b";",
+                b"\n",
+                b"\r",
+                b"\t",
+                b"\0",
+                b"\x1b",
+                b"a",
+                b" ",
+                b"\xc3\xa9",
+                b"\xf0\x9f\xa6\x80",
+                b"\xff",
+                b"\xe2\x82",
+            ];
+            let len = self.next() % 24;
+            (0..len)
+                .flat_map(|_| {
+                    let n = self.next() as usize % TRICKY.len();
+                    TRICKY[n].to_vec()
+                })
+                .collect()
+        }
+    }
+
+    /// Reads the literal quoted by `quote` at the start of `sql` the way
+    /// the server lexes it, returning its bytes and what follows it.
+    fn unquote(sql: &[u8], quote: u8) -> (Vec<u8>, &[u8]) {
+        assert_eq!(sql[0], quote);
+        let mut value = Vec::new();
+        let mut i = 1;
+        loop {
+            match sql[i] {
+                b'\\' => {
+                    let escaped = match sql[i + 1] {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'0' => b'\0',
+                        b'x' => {
+                            let hex = std::str::from_utf8(&sql[i + 2..i + 4])
+                                .unwrap();
+                            i += 2;
+                            u8::from_str_radix(hex, 16).unwrap()
+                        }
+                        c => c,
+                    };
+                    value.push(escaped);
+                    i += 2;
+                }
+                c if c == quote && sql.get(i + 1) == Some(&quote) => {
+                    value.push(quote);
+                    i += 2;
+                }
+                c if c == quote => return (value, &sql[i + 1..]),
+                c => {
+                    value.push(c);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bind_cannot_break_out() -> Result<()> {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..10_000 {
+            let string = rng.bytes();
+            let name = String::from_utf8_lossy(&rng.bytes()).into_owned();
+            let sql = Sql::new("SELECT ? FROM :t WHERE ?")
+                .bind(Value::String(Bytes::from(string.clone())))
+                .bind_named("t", Identifier(&name))
+                .bind(Value::Array(vec![Bytes::from(string.clone()).into()]))
+                .build()?;
+
+            let rest = sql.as_bytes().strip_prefix(b"SELECT ").unwrap();
+            let (value, rest) = unquote(rest, b'\'');
+            assert_eq!(value, string, "{sql}");
+            let rest = rest.strip_prefix(b" FROM ").unwrap();
+            let (identifier, rest) = unquote(rest, b'`');
+            assert_eq!(identifier, name.as_bytes(), "{sql}");
+            let rest = rest.strip_prefix(b" WHERE [").unwrap();
+            let (value, rest) = unquote(rest, b'\'');
+            assert_eq!(value, string, "{sql}");
+            assert_eq!(rest, b"]", "{sql}");
+        }
+        Ok(())
+    }
+}
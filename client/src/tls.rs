@@ -0,0 +1,379 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{
+    CertificateDer, PrivateKeyDer, ServerName, UnixTime,
+};
+use tokio_rustls::rustls::{
+    self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use tokio_rustls::TlsConnector;
+
+use crate::error::{ClickHouseClientError, Result};
+
+/// How a client speaks TLS, as it must to the secure native port, 9440 by
+/// default.
+///
+/// The server's certificate is verified against the Mozilla root
+/// certificates, or only against those added with
+/// [`TlsOptions::ca_pem`].
+#[derive(Clone, Default)]
+pub struct TlsOptions {
+    roots: Vec<CertificateDer<'static>>,
+    client_auth:
+        Option<(Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>)>,
+    server_name: Option<String>,
+    insecure_skip_verify: bool,
+}
+
+impl fmt::Debug for TlsOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsOptions")
+            .field("roots", &self.roots.len())
+            .field("client_auth", &self.client_auth.is_some())
+            .field("server_name", &self.server_name)
+            .field("insecure_skip_verify", &self.insecure_skip_verify)
+            .finish()
+    }
+}
+
+impl TlsOptions {
+    pub fn new() -> Self {
+        TlsOptions::default()
+    }
+
+    /// Trusts the CA certificates of a PEM bundle, instead of the Mozilla
+    /// root certificates.
+    pub fn ca_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certificates = certificates(pem)?;
+        if certificates.is_empty() {
+            return Err(tls_error("no certificate in the CA bundle"));
+        }
+        self.roots.extend(certificates);
+        Ok(self)
+    }
+
+    /// Like [`TlsOptions::ca_pem`], reading the bundle from a file.
+    pub fn ca_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        self.ca_pem(&std::fs::read(path)?)
+    }
+
+    /// Authenticates the client with the certificate chain and private key
+    /// of PEM files, for servers that ask for one.
+    pub fn client_auth_pem(
+        mut self,
+        certificate_chain: &[u8],
+        private_key: &[u8],
+    ) -> Result<Self> {
+        let chain = certificates(certificate_chain)?;
+        if chain.is_empty() {
+            return Err(tls_error("no certificate in the client chain"));
+        }
+        let key = PrivateKeyDer::from_pem_slice(private_key)
+            .map_err(|e| tls_error(format!("invalid private key: {e}")))?;
+        self.client_auth = Some((chain, Arc::new(key)));
+        Ok(self)
+    }
+
+    /// The name the server's certificate is checked for and which is sent
+    /// as SNI, instead of the host of the address.
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    /// Accepts any certificate the server presents. The connection is
+    /// still encrypted, but to whoever answers, so this is only for
+    /// development.
+    pub fn insecure_skip_verify(mut self, skip: bool) -> Self {
+        self.insecure_skip_verify = skip;
+        self
+    }
+
+    /// Performs the handshake over `stream`, connected to `addr`.
+    pub(crate) async fn connect(
+        &self,
+        addr: &str,
+        stream: TcpStream,
+    ) -> Result<TlsStream<TcpStream>> {
+        let name = match &self.server_name {
+            Some(name) => name.as_str(),
+            None => host(addr),
+        };
+        let name = ServerName::try_from(name.to_owned())
+            .map_err(|_| tls_error(format!("invalid server name `{name}`")))?;
+        let connector = TlsConnector::from(Arc::new(self.config()?));
+        Ok(connector.connect(name, stream).await?)
+    }
+
+    fn config(&self) -> Result<ClientConfig> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error(e.to_string()))?;
+        let builder = if self.insecure_skip_verify {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(SkipVerify(
+                    provider,
+                )))
+        } else {
+            let mut roots = RootCertStore::empty();
+            if self.roots.is_empty() {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+            for root in &self.roots {
+                roots
+                    .add(root.clone())
+                    .map_err(|e| tls_error(format!("invalid CA: {e}")))?;
+            }
+            builder.with_root_certificates(roots)
+        };
+        match &self.client_auth {
+            Some((chain, key)) => builder
+                .with_client_auth_cert(chain.clone(), key.clone_key())
+                .map_err(|e| tls_error(e.to_string())),
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+/// The host of a `host:port` address, without the brackets of an IPv6
+/// address.
+fn host(addr: &str) -> &str {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+fn certificates(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<_, _>>()
+        .map_err(|e| tls_error(format!("invalid certificate: {e}")))
+}
+
+fn tls_error(message: impl Into<String>) -> ClickHouseClientError {
+    ClickHouseClientError::TlsError(message.into())
+}
+
+/// Accepts any certificate, while still checking that the server holds
+/// its key.
+#[derive(Debug)]
+struct SkipVerify(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SkipVerify {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, CertifiedIssuer,
+        IsCa, KeyPair,
+    };
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+    use tokio_rustls::rustls::crypto::ring;
+    use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use tokio_rustls::rustls::server::WebPkiClientVerifier;
+    use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+    use tokio_rustls::TlsAcceptor;
+
+    use crate::binary::ClickHouseEncoder;
+    use crate::client::test::replay;
+    use crate::protocol::server::ServerPacketCode;
+    use crate::tls::{host, TlsOptions};
+    use crate::{ClickHouseClientError, Client, ClientOptions};
+
+    /// A CA with a certificate for the server and one for the client.
+    struct Pki {
+        ca: CertifiedIssuer<'static, KeyPair>,
+        server: (Certificate, KeyPair),
+        client: (Certificate, KeyPair),
+    }
+
+    impl Pki {
+        fn new() -> Result<Self> {
+            let mut params = CertificateParams::new(Vec::<String>::new())?;
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca =
+                CertifiedIssuer::self_signed(params, KeyPair::generate()?)?;
+            let issue = |name: &str| -> Result<_> {
+                let key = KeyPair::generate()?;
+                let params = CertificateParams::new(vec![name.to_owned()])?;
+                Ok((params.signed_by(&key, &ca)?, key))
+            };
+            Ok(Pki {
+                server: issue("clickhouse.test")?,
+                client: issue("reader")?,
+                ca,
+            })
+        }
+
+        /// Options that trust the CA and authenticate as the client.
+        fn options(&self) -> Result<TlsOptions> {
+            let (certificate, key) = &self.client;
+            Ok(TlsOptions::new()
+                .ca_pem(self.ca.pem().as_bytes())?
+                .client_auth_pem(
+                    certificate.pem().as_bytes(),
+                    key.serialize_pem().as_bytes(),
+                )?
+                .server_name("clickhouse.test"))
+        }
+
+        /// Accepts one connection over TLS, requiring a client certificate
+        /// issued by the CA, and answers a ping.
+        async fn serve(&self) -> Result<(String, JoinHandle<Result<Vec<u8>>>)> {
+            let provider = Arc::new(ring::default_provider());
+            let mut roots = RootCertStore::empty();
+            roots.add(self.ca.der().clone())?;
+            let verifier = WebPkiClientVerifier::builder_with_provider(
+                Arc::new(roots),
+                provider.clone(),
+            )
+            .build()?;
+            let (certificate, key) = &self.server;
+            let key = PrivatePkcs8KeyDer::from(key.serialize_der());
+            let config = ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()?
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(
+                    vec![certificate.der().clone()],
+                    PrivateKeyDer::Pkcs8(key),
+                )?;
+            let acceptor = TlsAcceptor::from(Arc::new(config));
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?.to_string();
+            let task = tokio::spawn(async move {
+                let (socket, _) = listener.accept().await?;
+                let socket = acceptor.accept(socket).await?;
+                let mut pong = Vec::new();
+                pong.encode_u8(ServerPacketCode::Pong as u8).await?;
+                replay(socket, &pong).await
+            });
+            Ok((addr, task))
+        }
+    }
+
+    async fn connect(addr: String, tls: TlsOptions) -> crate::Result<()> {
+        let options = ClientOptions::default().addr(addr).tls(tls);
+        let mut client = Client::connect(options).await?;
+        client.ping().await
+    }
+
+    #[tokio::test]
+    async fn test_tls() -> Result<()> {
+        let pki = Pki::new()?;
+        let (addr, _) = pki.serve().await?;
+        connect(addr, pki.options()?).await?;
+
+        // the certificate is for another name than the address
+        let (addr, _) = pki.serve().await?;
+        let mut options = pki.options()?;
+        options.server_name = None;
+        assert!(connect(addr, options).await.is_err());
+
+        // the server requires a client certificate
+        let (addr, _) = pki.serve().await?;
+        let mut options = pki.options()?;
+        options.client_auth = None;
+        assert!(connect(addr, options).await.is_err());
+
+        // the Mozilla roots do not include the CA, unless verifying is off
+        let (addr, _) = pki.serve().await?;
+        let mut options = pki.options()?;
+        options.roots.clear();
+        assert!(connect(addr, options.clone()).await.is_err());
+        let (addr, _) = pki.serve().await?;
+        connect(addr, options.insecure_skip_verify(true)).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() -> Result<()> {
+        // the server never answers the handshake
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let accepted = listener.accept().await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            drop(accepted);
+        });
+        let options = ClientOptions::default()
+            .addr(addr)
+            .connect_timeout(Duration::from_millis(100))
+            .tls(TlsOptions::new().insecure_skip_verify(true));
+        assert!(matches!(
+            Client::connect(options).await,
+            Err(ClickHouseClientError::ConnectTimeout)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert_eq!(
+            TlsOptions::new().ca_pem(b"").unwrap_err().to_string(),
+            "tls error: no certificate in the CA bundle"
+        );
+        assert!(TlsOptions::new().client_auth_pem(b"", b"").is_err());
+        assert_eq!(host("clickhouse.test:9440"), "clickhouse.test");
+        assert_eq!(host("[::1]:9440"), "::1");
+    }
+}
@@ -24,6 +24,7 @@ async fn hello() -> Result<()> {
         ServerPacketCode::Hello => {
             let result = reader.read_hello_packet().await?;
             info!("received packet: {:?}", result);
+            writer.write_hello_addendum(result.revision).await?;
         }
         ServerPacketCode::Exception => {
             let result = reader.read_exception_packet().await?;
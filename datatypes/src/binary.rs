@@ -0,0 +1,78 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{DataTypeError, Result};
+
+pub(crate) const MAX_STRING_SIZE: usize = 1 << 30;
+// see also: https://pkg.go.dev/encoding/binary#pkg-constants
+pub(crate) const MAX_VARINT_LEN64: usize = 10;
+
+pub(crate) async fn read_var_uint<R>(reader: &mut R) -> Result<u64>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let mut result = 0_u64;
+    for i in 0..MAX_VARINT_LEN64 {
+        let b = reader.read_u8().await?;
+        if (b & 0x80) == 0 {
+            if i == (MAX_VARINT_LEN64 - 1) && b > 1 {
+                break;
+            }
+            return Ok(result | (u64::from(b) << (7 * i)));
+        }
+        result |= u64::from(b & 0x7F) << (7 * i);
+    }
+    Err(DataTypeError::DecodeError(
+        "overflow when decoding var uint".into(),
+    ))
+}
+
+pub(crate) async fn read_string<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let len = read_var_uint(reader).await? as usize;
+    if len > MAX_STRING_SIZE {
+        return Err(DataTypeError::DecodeError(
+            "size is too long when decoding string".into(),
+        ));
+    }
+    let mut buf = vec![0_u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Reads `len` bytes at once, which is how fixed-width columns are laid out.
+pub(crate) async fn read_bytes<R>(reader: &mut R, len: usize) -> Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let mut buf = vec![0_u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+pub(crate) async fn write_var_uint<W>(writer: &mut W, x: u64) -> Result<usize>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let mut buf = [0_u8; MAX_VARINT_LEN64];
+    let mut i = 0;
+    let mut x = x;
+    while x >= 0x80 {
+        buf[i] = x as u8 | 0x80;
+        x >>= 7;
+        i += 1;
+    }
+    buf[i] = x as u8;
+    writer.write_all(&buf[..=i]).await?;
+    Ok(i + 1)
+}
+
+pub(crate) async fn write_string<W>(writer: &mut W, x: &[u8]) -> Result<usize>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let header_len = write_var_uint(writer, x.len() as u64).await?;
+    writer.write_all(x).await?;
+    Ok(header_len + x.len())
+}
@@ -0,0 +1,423 @@
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::binary::{MAX_STRING_SIZE, MAX_VARINT_LEN64};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// An `AggregateFunction(f, T, ...)` column of opaque per-row states.
+///
+/// The native format writes the states back to back without their sizes,
+/// so a column can only be split into rows when the layout of its states is
+/// known. That is the case for `count`, `sum`, `sumWithOverflow`, `min`,
+/// `max`, `any`, `anyLast`, `uniq`, `uniqExact` and `groupArray` of
+/// fixed-width types, and their `-If` variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateFunctionColumn {
+    function: String,
+    types: Vec<DataType>,
+    layout: Option<StateLayout>,
+    pub(crate) states: Vec<Bytes>,
+}
+
+impl AggregateFunctionColumn {
+    pub(crate) fn empty(function: &str, types: &[DataType]) -> Self {
+        AggregateFunctionColumn {
+            function: function.to_owned(),
+            types: types.to_vec(),
+            layout: StateLayout::new(function, types),
+            states: Vec::new(),
+        }
+    }
+
+    /// The aggregate function with its parameters.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn argument_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    /// Whether the states of this function can be split into rows.
+    pub fn is_framed(&self) -> bool {
+        self.layout.is_some()
+    }
+
+    /// The serialized state of every row.
+    pub fn states(&self) -> &[Bytes] {
+        &self.states
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        self.states.iter().map(|state| state.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::AggregateFunction(self.function.clone(), self.types.clone())
+    }
+
+    /// Appends a serialized state, which must be exactly one state of the
+    /// function.
+    pub(crate) fn push(&mut self, state: Bytes) -> Result<()> {
+        let layout = self.layout.ok_or_else(|| {
+            DataTypeError::EncodeError(self.unframed_message())
+        })?;
+        if layout.frame(&state) != Some(state.len()) {
+            return Err(DataTypeError::EncodeError(format!(
+                "{} bytes are not a state of {}",
+                state.len(),
+                self.data_type()
+            )));
+        }
+        self.states.push(state);
+        Ok(())
+    }
+
+    /// Appends the state of an aggregation over no rows.
+    pub(crate) fn push_default(&mut self) {
+        let state = self.layout.map(StateLayout::empty).unwrap_or_default();
+        self.states.push(state);
+    }
+
+    fn unframed_message(&self) -> String {
+        format!(
+            "cannot split {} states into rows, finalize them with \
+             finalizeAggregation or use a function with a known state layout",
+            self.data_type()
+        )
+    }
+
+    pub(crate) async fn read<R>(
+        function: &str,
+        types: &[DataType],
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<AggregateFunctionColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut column = AggregateFunctionColumn::empty(function, types);
+        if rows == 0 {
+            return Ok(column);
+        }
+        let Some(layout) = column.layout else {
+            return Err(DataTypeError::DecodeError(column.unframed_message()));
+        };
+        column.states.reserve(rows);
+        for _ in 0..rows {
+            column.states.push(layout.read(reader).await?.into());
+        }
+        Ok(column)
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut len = 0;
+        for state in &self.states {
+            writer.write_all(state).await?;
+            len += state.len();
+        }
+        Ok(len)
+    }
+}
+
+/// How the state of an aggregate function is serialized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StateLayout {
+    /// A single fixed-width value.
+    Fixed(usize),
+    /// A `has` flag byte, followed by a fixed-width value if it is set.
+    OptionalFixed(usize),
+    /// An `Int32` size that is negative without a value, followed by that
+    /// many bytes.
+    OptionalString,
+    /// A single var uint.
+    VarUInt,
+    /// The skip degree byte, a var uint count and that many `UInt32`
+    /// hashes, as written by `uniq`.
+    UniquesHashSet,
+    /// A var uint count followed by that many fixed-width values.
+    Array(usize),
+}
+
+impl StateLayout {
+    fn new(function: &str, types: &[DataType]) -> Option<StateLayout> {
+        // `-If` adds a `UInt8` condition but keeps the state
+        if let Some(function) = function.strip_suffix("If") {
+            return match types.split_last() {
+                Some((DataType::UInt8, types)) => {
+                    StateLayout::new(function, types)
+                }
+                _ => None,
+            };
+        }
+        let layout = match (function, types) {
+            ("count", _) => StateLayout::VarUInt,
+            ("sum", [data_type]) => StateLayout::Fixed(sum_size(data_type)?),
+            ("sumWithOverflow", [data_type]) => {
+                sum_size(data_type)?;
+                StateLayout::Fixed(data_type.fixed_size()?)
+            }
+            // a `has` flag, then the value
+            ("min" | "max" | "any" | "anyLast", [data_type]) => {
+                single_value_layout(data_type)?
+            }
+            ("uniq", types) if !types.is_empty() => StateLayout::UniquesHashSet,
+            // strings and several arguments are kept as 128-bit hashes
+            ("uniqExact", [DataType::String]) => StateLayout::Array(16),
+            ("uniqExact", [data_type]) => match data_type {
+                DataType::FixedString(_) => StateLayout::Array(16),
+                data_type => StateLayout::Array(data_type.fixed_size()?),
+            },
+            ("uniqExact", types) if types.len() > 1 => StateLayout::Array(16),
+            ("groupArray", [data_type]) => match data_type {
+                DataType::FixedString(_) => return None,
+                data_type => StateLayout::Array(data_type.fixed_size()?),
+            },
+            _ => return None,
+        };
+        Some(layout)
+    }
+
+    fn empty(self) -> Bytes {
+        match self {
+            StateLayout::Fixed(size) => vec![0; size].into(),
+            StateLayout::OptionalString => {
+                (-1_i32).to_le_bytes().to_vec().into()
+            }
+            StateLayout::UniquesHashSet => Bytes::from_static(&[0, 0]),
+            StateLayout::OptionalFixed(_)
+            | StateLayout::VarUInt
+            | StateLayout::Array(_) => Bytes::from_static(&[0]),
+        }
+    }
+
+    /// The size of the state at the start of `bytes`, `None` if `bytes`
+    /// ends before it does.
+    fn frame(self, bytes: &[u8]) -> Option<usize> {
+        let end = match self {
+            StateLayout::Fixed(size) => size,
+            StateLayout::OptionalFixed(size) => match bytes.first()? {
+                0 => 1,
+                _ => 1 + size,
+            },
+            StateLayout::OptionalString => {
+                let size = i32::from_le_bytes(bytes.get(..4)?.try_into().ok()?);
+                4 + usize::try_from(size).unwrap_or(0)
+            }
+            StateLayout::VarUInt => decode_var_uint(bytes)?.1,
+            StateLayout::UniquesHashSet => {
+                let (count, len) = decode_var_uint(bytes.get(1..)?)?;
+                (count as usize).checked_mul(4)?.checked_add(1 + len)?
+            }
+            StateLayout::Array(size) => {
+                let (count, len) = decode_var_uint(bytes)?;
+                (count as usize).checked_mul(size)?.checked_add(len)?
+            }
+        };
+        (end <= bytes.len()).then_some(end)
+    }
+
+    async fn read<R>(self, reader: &mut R) -> Result<Vec<u8>>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut state = Vec::new();
+        match self {
+            StateLayout::Fixed(size) => {
+                read_into(reader, &mut state, size).await?;
+            }
+            StateLayout::OptionalFixed(size) => {
+                read_into(reader, &mut state, 1).await?;
+                if state[0] != 0 {
+                    read_into(reader, &mut state, size).await?;
+                }
+            }
+            StateLayout::OptionalString => {
+                read_into(reader, &mut state, 4).await?;
+                let size = i32::from_le_bytes([
+                    state[0], state[1], state[2], state[3],
+                ]);
+                if let Ok(size) = usize::try_from(size) {
+                    read_into(reader, &mut state, size).await?;
+                }
+            }
+            StateLayout::VarUInt => {
+                read_var_uint_into(reader, &mut state).await?;
+            }
+            StateLayout::UniquesHashSet => {
+                read_into(reader, &mut state, 1).await?;
+                let count = read_var_uint_into(reader, &mut state).await?;
+                read_into(reader, &mut state, count.saturating_mul(4)).await?;
+            }
+            StateLayout::Array(size) => {
+                let count = read_var_uint_into(reader, &mut state).await?;
+                read_into(reader, &mut state, count.saturating_mul(size))
+                    .await?;
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn single_value_layout(data_type: &DataType) -> Option<StateLayout> {
+    match data_type {
+        DataType::String => Some(StateLayout::OptionalString),
+        DataType::FixedString(_) => None,
+        data_type => Some(StateLayout::OptionalFixed(data_type.fixed_size()?)),
+    }
+}
+
+/// The size of the `sum` of a numeric type, which widens integers to 64
+/// bits and floats to `Float64`.
+fn sum_size(data_type: &DataType) -> Option<usize> {
+    match data_type {
+        DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::Float32
+        | DataType::Float64 => Some(8),
+        DataType::UInt128 | DataType::Int128 => Some(16),
+        _ => None,
+    }
+}
+
+fn decode_var_uint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut result = 0_u64;
+    for (i, b) in bytes.iter().take(MAX_VARINT_LEN64).enumerate() {
+        result |= u64::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((result, i + 1));
+        }
+    }
+    None
+}
+
+async fn read_into<R>(
+    reader: &mut R,
+    state: &mut Vec<u8>,
+    len: usize,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    if state.len().saturating_add(len) > MAX_STRING_SIZE {
+        return Err(DataTypeError::DecodeError(
+            "aggregate function state is too large".into(),
+        ));
+    }
+    let start = state.len();
+    state.resize(start + len, 0);
+    reader.read_exact(&mut state[start..]).await?;
+    Ok(())
+}
+
+/// Reads a var uint, keeping its bytes in `state`.
+async fn read_var_uint_into<R>(
+    reader: &mut R,
+    state: &mut Vec<u8>,
+) -> Result<usize>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let start = state.len();
+    loop {
+        let b = reader.read_u8().await?;
+        state.push(b);
+        if b & 0x80 == 0 {
+            break;
+        }
+        if state.len() - start == MAX_VARINT_LEN64 {
+            return Err(DataTypeError::DecodeError(
+                "overflow when decoding var uint".into(),
+            ));
+        }
+    }
+    let (value, _) = decode_var_uint(&state[start..]).ok_or_else(|| {
+        DataTypeError::DecodeError("overflow when decoding var uint".into())
+    })?;
+    Ok(value as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_aggregate_function_states() -> Result<()> {
+        let data_type: DataType = "AggregateFunction(uniq, UInt64)".parse()?;
+        let bytes = [
+            0, 2, 1, 0, 0, 0, 2, 0, 0, 0, // two hashes
+            0, 0, // empty
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+        assert_eq!(column.get::<&[u8]>(0)?, &bytes[..10]);
+        assert_eq!(column.get::<Bytes>(1)?, &[0, 0][..]);
+
+        let mut built = Column::new(&data_type);
+        built.push(Bytes::copy_from_slice(&bytes[..10]))?;
+        built.push_default();
+        assert!(built.push(Bytes::from_static(&[0, 1, 0])).is_err());
+        assert!(built.push(Bytes::from_static(&[0, 0, 0])).is_err());
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+
+        let data_type: DataType =
+            "AggregateFunction(maxIf, String, UInt8)".parse()?;
+        let bytes = [2, 0, 0, 0, b'a', 0, 0xff, 0xff, 0xff, 0xff];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.get::<&[u8]>(1)?, [0xff; 4]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unframed_aggregate_function() -> Result<()> {
+        let data_type: DataType =
+            "AggregateFunction(quantiles(0.5), Float64)".parse()?;
+        let bytes = [0; 16];
+        let mut reader = &bytes[..];
+        let error = Column::read(&data_type, 1, &mut reader).await;
+        assert!(error
+            .unwrap_err()
+            .to_string()
+            .contains("finalizeAggregation"));
+        let column = Column::read(&data_type, 0, &mut reader).await?;
+        assert!(column.is_empty());
+        assert!(Column::new(&data_type).push(Bytes::new()).is_err());
+
+        let data_type: DataType =
+            "SimpleAggregateFunction(sum, UInt64)".parse()?;
+        let bytes = [7, 0, 0, 0, 0, 0, 0, 0];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 1, &mut reader).await?;
+        assert_eq!(column.get::<u64>(0)?, 7);
+        Ok(())
+    }
+}
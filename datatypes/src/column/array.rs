@@ -0,0 +1,285 @@
+use std::ops::Range;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::primitive::{read_primitive, write_primitive};
+use super::{Column, FromColumn, Prefix, Primitive};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// An `Array(T)` column.
+///
+/// On the wire it is one cumulative `u64` end offset per row followed by
+/// the inner column holding the elements of all rows back to back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayColumn {
+    pub(crate) offsets: Vec<u64>,
+    pub(crate) inner: Box<Column>,
+}
+
+impl ArrayColumn {
+    pub fn new(offsets: Vec<u64>, inner: Column) -> Result<ArrayColumn> {
+        check_offsets(&offsets, inner.len())
+            .map_err(DataTypeError::EncodeError)?;
+        Ok(ArrayColumn {
+            offsets,
+            inner: Box::new(inner),
+        })
+    }
+
+    pub(crate) fn empty(inner: &DataType) -> ArrayColumn {
+        ArrayColumn {
+            offsets: Vec::new(),
+            inner: Box::new(Column::new(inner)),
+        }
+    }
+
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// The flattened elements of all rows.
+    pub fn inner(&self) -> &Column {
+        &self.inner
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The range of `inner` holding the elements of `row`.
+    pub fn range(&self, row: usize) -> Result<Range<usize>> {
+        offsets_range(&self.offsets, row)
+    }
+
+    /// Borrows the elements of `row` without copying them.
+    pub fn slice(&self, row: usize) -> Result<ColumnSlice<'_>> {
+        Ok(ColumnSlice {
+            column: &self.inner,
+            range: self.range(row)?,
+        })
+    }
+
+    pub(crate) fn push_default(&mut self) {
+        self.offsets.push(self.offsets.last().copied().unwrap_or(0));
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.offsets.truncate(len);
+        let end = self.offsets.last().copied().unwrap_or(0);
+        self.inner.truncate(end as usize);
+    }
+
+    /// Appends one row holding the elements pushed by `push`.
+    ///
+    /// If `push` fails, the column is left unchanged.
+    pub(crate) fn push_with(
+        &mut self,
+        push: impl FnOnce(&mut Column) -> Result<()>,
+    ) -> Result<()> {
+        let start = self.inner.len();
+        if let Err(e) = push(&mut self.inner) {
+            self.inner.truncate(start);
+            return Err(e);
+        }
+        self.offsets.push(self.inner.len() as u64);
+        Ok(())
+    }
+
+    pub(crate) async fn read<R>(
+        inner: &DataType,
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<ArrayColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let offsets: Vec<u64> = read_primitive(reader, rows).await?;
+        let total = offsets.last().copied().unwrap_or(0) as usize;
+        check_offsets(&offsets, total).map_err(DataTypeError::DecodeError)?;
+        let inner =
+            Box::pin(Column::read_data(inner, prefix, total, reader)).await?;
+        Ok(ArrayColumn {
+            offsets,
+            inner: Box::new(inner),
+        })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let len = write_primitive(writer, &self.offsets).await?;
+        Ok(len + Box::pin(self.inner.write_data(writer)).await?)
+    }
+}
+
+pub(crate) fn check_offsets(
+    offsets: &[u64],
+    total: usize,
+) -> std::result::Result<(), String> {
+    if offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err("array offsets must not decrease".into());
+    }
+    let last = offsets.last().copied().unwrap_or(0);
+    if last != total as u64 {
+        return Err(format!(
+            "array offsets end at {last} but inner column has {total} rows"
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn offsets_range(
+    offsets: &[u64],
+    row: usize,
+) -> Result<Range<usize>> {
+    let end = *offsets.get(row).ok_or(DataTypeError::RowOutOfBounds {
+        row,
+        len: offsets.len(),
+    })?;
+    let start = if row == 0 { 0 } else { offsets[row - 1] };
+    Ok(start as usize..end as usize)
+}
+
+/// A borrowed run of rows of a column, such as the elements of one array.
+#[derive(Debug, Clone)]
+pub struct ColumnSlice<'a> {
+    column: &'a Column,
+    range: Range<usize>,
+}
+
+impl<'a> ColumnSlice<'a> {
+    pub fn column(&self) -> &'a Column {
+        self.column
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    pub fn get<T: FromColumn<'a>>(&self, index: usize) -> Result<T> {
+        if index >= self.len() {
+            return Err(DataTypeError::RowOutOfBounds {
+                row: index,
+                len: self.len(),
+            });
+        }
+        self.column.get(self.range.start + index)
+    }
+
+    /// Borrows fixed-width values directly from the underlying column.
+    pub fn as_slice<T: Primitive>(&self) -> Option<&'a [T]> {
+        T::as_slice(self.column).map(|data| &data[self.range.clone()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, ColumnSlice, DataType};
+
+    #[tokio::test]
+    async fn test_array_of_arrays() -> Result<()> {
+        let data_type: DataType = "Array(Array(UInt32))".parse()?;
+        // [[1, 2], []], [], [[3]]
+        let bytes = [
+            2, 0, 0, 0, 0, 0, 0, 0, // outer offsets
+            2, 0, 0, 0, 0, 0, 0, 0, //
+            3, 0, 0, 0, 0, 0, 0, 0, //
+            2, 0, 0, 0, 0, 0, 0, 0, // inner offsets
+            2, 0, 0, 0, 0, 0, 0, 0, //
+            3, 0, 0, 0, 0, 0, 0, 0, //
+            1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, // values
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+
+        assert_eq!(column.get::<Vec<Vec<u32>>>(0)?, vec![vec![1, 2], vec![]]);
+        assert!(column.get::<Vec<Vec<u32>>>(1)?.is_empty());
+        assert_eq!(column.get::<Vec<Vec<u32>>>(2)?, vec![vec![3]]);
+
+        let Column::Array(outer) = &column else {
+            panic!("expected array column");
+        };
+        let Column::Array(inner) = outer.inner() else {
+            panic!("expected array column");
+        };
+        assert_eq!(inner.slice(0)?.as_slice::<u32>(), Some(&[1, 2][..]));
+        assert_eq!(column.get::<ColumnSlice>(2)?.len(), 1);
+
+        let mut built = Column::new(&data_type);
+        built.push(vec![vec![1_u32, 2], vec![]])?;
+        built.push(Vec::<Vec<u32>>::new())?;
+        built.push(vec![vec![3_u32]])?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_array_of_nullable_strings() -> Result<()> {
+        let data_type: DataType = "Array(Nullable(String))".parse()?;
+        // ['a', NULL], [NULL]
+        let bytes = [
+            2, 0, 0, 0, 0, 0, 0, 0, // offsets
+            3, 0, 0, 0, 0, 0, 0, 0, //
+            0, 1, 1, // null map
+            1, b'a', 0, 0, // values
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+
+        assert_eq!(
+            column.get::<Vec<Option<String>>>(0)?,
+            vec![Some("a".to_owned()), None]
+        );
+        assert_eq!(column.get::<Vec<Option<&str>>>(1)?, vec![None]);
+
+        let mut built = Column::new(&data_type);
+        built.push(vec![Some("a"), None])?;
+        built.push(vec![None::<&str>])?;
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_push_leaves_array_unchanged() -> Result<()> {
+        let mut column = Column::new(&"Array(UInt8)".parse()?);
+        column.push(vec![1_u8])?;
+        assert!(column.push(vec![Some(2_u8), None]).is_err());
+        assert_eq!(column.len(), 1);
+        assert_eq!(column.get::<Vec<u8>>(0)?, vec![1]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_offsets() -> Result<()> {
+        let data_type: DataType = "Array(UInt8)".parse()?;
+        let bytes = [2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut reader = &bytes[..];
+        assert!(Column::read(&data_type, 2, &mut reader).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,890 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use arrow::array::{
+    make_array, Array, ArrayRef, AsArray, BinaryArray, BooleanArray,
+    Decimal128Array, DictionaryArray, FixedSizeBinaryArray, ListArray,
+    MapArray, PrimitiveArray, StringArray, StructArray,
+};
+use arrow::buffer::{Buffer, NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType as ArrowType, Date32Type, Decimal128Type,
+    Field, Fields, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, TimeUnit, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use bytes::Bytes;
+use uuid::Uuid;
+
+use super::{ArrayColumn, Column, IntoColumn, NestedColumn, Point};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+use crate::value::Value;
+
+/// Timestamps are read in UTC, which an offset names without needing a time
+/// zone database.
+const UTC: &str = "+00:00";
+
+impl Column {
+    /// Converts the column into an Arrow array.
+    ///
+    /// Fixed-width values that Arrow stores the same way, such as integers,
+    /// `Date32` and `DateTime64` with a precision of 0, 3, 6 or 9, are moved
+    /// without copying. The types map as follows:
+    ///
+    /// - `Nullable(T)` to `T` with a validity bitmap
+    /// - `LowCardinality(T)` to `Dictionary(UInt64, T)`
+    /// - `Array(T)` and the geo arrays to `List`, `Nested` to a `List` of
+    ///   `Struct`, `Tuple` to `Struct` and `Map` to `Map`
+    /// - `DateTime` and `DateTime64` to `Timestamp` with their time zone
+    /// - `String` to `Binary` whatever its values, or to `Utf8` with
+    ///   [`Column::into_arrow_utf8`]
+    /// - `UInt128`, `UUID` and `IPv6` to `FixedSizeBinary(16)`, `IPv4` to
+    ///   `UInt32`, `Int128` to `Decimal128(38, 0)`
+    /// - `Enum8`, `Enum16` and `JSON` to `Utf8`, `Point` to a `Struct` of
+    ///   `x` and `y`, `AggregateFunction` states to `Binary`
+    ///
+    /// `Variant` and `Dynamic` columns cannot be converted.
+    pub fn into_arrow(self) -> Result<ArrayRef> {
+        self.into_arrow_with(false)
+    }
+
+    /// Like [`Column::into_arrow`], but converts `String` to `Utf8`, failing
+    /// on values that are not UTF-8.
+    pub fn into_arrow_utf8(self) -> Result<ArrayRef> {
+        self.into_arrow_with(true)
+    }
+
+    /// Converts the column, with `utf8` asking for `String` as `Utf8`.
+    fn into_arrow_with(self, utf8: bool) -> Result<ArrayRef> {
+        let array: ArrayRef = match self {
+            Column::UInt8(data) => primitive::<UInt8Type>(data),
+            Column::UInt16(data) => primitive::<UInt16Type>(data),
+            Column::UInt32(data) => primitive::<UInt32Type>(data),
+            Column::UInt64(data) => primitive::<UInt64Type>(data),
+            Column::UInt128(data) => fixed_binary(
+                16,
+                data.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            )?,
+            Column::Int8(data) => primitive::<Int8Type>(data),
+            Column::Int16(data) => primitive::<Int16Type>(data),
+            Column::Int32(data) => primitive::<Int32Type>(data),
+            Column::Int64(data) => primitive::<Int64Type>(data),
+            Column::Int128(data) => Arc::new(
+                Decimal128Array::new(data.into(), None)
+                    .with_precision_and_scale(38, 0)?,
+            ),
+            Column::Float32(data) => primitive::<Float32Type>(data),
+            Column::Float64(data) => primitive::<Float64Type>(data),
+            Column::Bool(data) => Arc::new(BooleanArray::from(data)),
+            Column::String(data) => strings(&data, utf8)?,
+            Column::FixedString(column) => {
+                fixed_binary(column.size, column.data)?
+            }
+            Column::Date(data) => primitive::<Date32Type>(
+                data.into_iter().map(i32::from).collect(),
+            ),
+            Column::Date32(data) => primitive::<Date32Type>(data),
+            Column::DateTime { tz, data } => {
+                let data = data.into_iter().map(i64::from).collect();
+                timestamps(TimeUnit::Second, data, tz)
+            }
+            Column::DateTime64 {
+                precision,
+                tz,
+                mut data,
+            } => {
+                let (unit, factor) = time_unit(precision)?;
+                if factor > 1 {
+                    for value in &mut data {
+                        *value =
+                            value.checked_mul(factor).ok_or_else(|| {
+                                DataTypeError::EncodeError(format!(
+                                "DateTime64({precision}) value {value} is out \
+                                 of range for Arrow"
+                            ))
+                            })?;
+                    }
+                }
+                timestamps(unit, data, tz)
+            }
+            Column::Uuid(data) => fixed_binary(
+                16,
+                data.iter().flat_map(|x| *x.as_bytes()).collect(),
+            )?,
+            Column::IPv4(data) => primitive::<UInt32Type>(
+                data.into_iter().map(u32::from).collect(),
+            ),
+            Column::IPv6(data) => fixed_binary(
+                16,
+                data.iter().flat_map(|x| x.octets()).collect(),
+            )?,
+            Column::Enum8(column) => Arc::new(StringArray::from_iter_values(
+                column.data.iter().map(|code| {
+                    column.name(*code).expect("enum codes are validated")
+                }),
+            )),
+            Column::Enum16(column) => Arc::new(StringArray::from_iter_values(
+                column.data.iter().map(|code| {
+                    column.name(*code).expect("enum codes are validated")
+                }),
+            )),
+            Column::Nullable(column) => {
+                let nulls =
+                    NullBuffer::from_iter(column.nulls.iter().map(|x| *x == 0));
+                let data = column.inner.into_arrow_with(utf8)?.into_data();
+                make_array(data.into_builder().nulls(Some(nulls)).build()?)
+            }
+            Column::Array(array)
+            | Column::Ring(array)
+            | Column::LineString(array)
+            | Column::MultiLineString(array)
+            | Column::Polygon(array)
+            | Column::MultiPolygon(array) => {
+                let (field, values) = field("item", *array.inner, utf8)?;
+                let offsets = offsets(&array.offsets)?;
+                Arc::new(ListArray::try_new(
+                    field.into(),
+                    offsets,
+                    values,
+                    None,
+                )?)
+            }
+            Column::Nested(nested) => {
+                let offsets = offsets(&nested.offsets)?;
+                let values = structs(nested.fields, utf8)?;
+                let field =
+                    Field::new("item", values.data_type().clone(), false);
+                Arc::new(ListArray::try_new(
+                    field.into(),
+                    offsets,
+                    Arc::new(values),
+                    None,
+                )?)
+            }
+            Column::LowCardinality(column) => {
+                let (dictionary, keys) = column.into_parts();
+                Arc::new(DictionaryArray::try_new(
+                    PrimitiveArray::<UInt64Type>::new(keys.into(), None),
+                    dictionary.into_arrow_with(utf8)?,
+                )?)
+            }
+            Column::Tuple(tuple) => {
+                let elements = tuple.elements.into_iter().enumerate().map(
+                    |(i, (name, c))| {
+                        (name.unwrap_or_else(|| (i + 1).to_string()), c)
+                    },
+                );
+                Arc::new(structs(elements, utf8)?)
+            }
+            Column::Map(map) => {
+                let offsets = offsets(&map.entries.offsets)?;
+                let Column::Tuple(entries) = *map.entries.inner else {
+                    unreachable!("map entries are always tuples")
+                };
+                let names = ["keys", "values"];
+                let entries = structs(
+                    names.into_iter().map(String::from).zip(
+                        entries.elements.into_iter().map(|(_, column)| column),
+                    ),
+                    utf8,
+                )?;
+                let field =
+                    Field::new("entries", entries.data_type().clone(), false);
+                Arc::new(MapArray::try_new(
+                    field.into(),
+                    offsets,
+                    entries,
+                    None,
+                    false,
+                )?)
+            }
+            Column::Json { data, .. } => Arc::new(StringArray::from(data)),
+            Column::Point(data) => Arc::new(structs(
+                [
+                    (
+                        "x".to_owned(),
+                        Column::Float64(data.iter().map(|p| p.x).collect()),
+                    ),
+                    (
+                        "y".to_owned(),
+                        Column::Float64(data.iter().map(|p| p.y).collect()),
+                    ),
+                ],
+                utf8,
+            )?),
+            Column::AggregateFunction(column) => {
+                Arc::new(BinaryArray::from_iter_values(column.states.iter()))
+            }
+            column @ (Column::Variant(_) | Column::Dynamic(_)) => {
+                return Err(unsupported(&column.data_type()))
+            }
+        };
+        Ok(array)
+    }
+
+    /// Converts an Arrow array into a column of `data_type`, casting it
+    /// where the Arrow type differs from the one [`Column::into_arrow`]
+    /// produces. Integer casts fail rather than overflow.
+    ///
+    /// Arrays with nulls can only be converted to `Nullable` types.
+    pub fn from_arrow(
+        array: &dyn Array,
+        data_type: &DataType,
+    ) -> Result<Column> {
+        match data_type {
+            DataType::Nullable(inner) => {
+                // logical nulls include those in the values of dictionaries
+                let nulls = match array.logical_nulls() {
+                    Some(nulls) => nulls.iter().map(|x| u8::from(!x)).collect(),
+                    None => vec![0; array.len()],
+                };
+                let column = from_arrow_values(array, inner)?;
+                Ok(Column::Nullable(super::NullableColumn::new(nulls, column)?))
+            }
+            DataType::LowCardinality(inner) => {
+                let values = Column::from_arrow(array, inner)?;
+                let mut column = Column::new(data_type);
+                for row in 0..values.len() {
+                    column.push(values.get::<Value>(row)?)?;
+                }
+                Ok(column)
+            }
+            DataType::SimpleAggregateFunction(_, inner) => {
+                Column::from_arrow(array, inner)
+            }
+            _ if array.logical_null_count() > 0 => {
+                Err(DataTypeError::EncodeError(format!(
+                    "{data_type} cannot hold NULL values"
+                )))
+            }
+            _ => from_arrow_values(array, data_type),
+        }
+    }
+}
+
+fn unsupported(data_type: &DataType) -> DataTypeError {
+    DataTypeError::EncodeError(format!("{data_type} has no Arrow counterpart"))
+}
+
+fn primitive<T: ArrowPrimitiveType>(data: Vec<T::Native>) -> ArrayRef {
+    Arc::new(PrimitiveArray::<T>::new(ScalarBuffer::from(data), None))
+}
+
+fn fixed_binary(size: usize, data: Vec<u8>) -> Result<ArrayRef> {
+    let size = i32::try_from(size).map_err(|_| {
+        DataTypeError::EncodeError(format!("FixedString({size}) is too large"))
+    })?;
+    let array =
+        FixedSizeBinaryArray::try_new(size, Buffer::from_vec(data), None)?;
+    Ok(Arc::new(array))
+}
+
+/// `Binary`, as ClickHouse strings can hold any bytes, or `Utf8` when
+/// asked for, which fails on the first value that is not UTF-8.
+fn strings(data: &[Bytes], utf8: bool) -> Result<ArrayRef> {
+    if !utf8 {
+        return Ok(Arc::new(BinaryArray::from_iter_values(data.iter())));
+    }
+    let strings = data
+        .iter()
+        .map(|x| std::str::from_utf8(x))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Arc::new(StringArray::from(strings)))
+}
+
+fn timestamps(unit: TimeUnit, data: Vec<i64>, tz: Option<String>) -> ArrayRef {
+    match unit {
+        TimeUnit::Second => Arc::new(
+            PrimitiveArray::<TimestampSecondType>::new(data.into(), None)
+                .with_timezone_opt(tz),
+        ),
+        TimeUnit::Millisecond => Arc::new(
+            PrimitiveArray::<TimestampMillisecondType>::new(data.into(), None)
+                .with_timezone_opt(tz),
+        ),
+        TimeUnit::Microsecond => Arc::new(
+            PrimitiveArray::<TimestampMicrosecondType>::new(data.into(), None)
+                .with_timezone_opt(tz),
+        ),
+        TimeUnit::Nanosecond => Arc::new(
+            PrimitiveArray::<TimestampNanosecondType>::new(data.into(), None)
+                .with_timezone_opt(tz),
+        ),
+    }
+}
+
+/// The Arrow unit for a `DateTime64` precision, and how many ticks of that
+/// unit make one tick of the precision.
+fn time_unit(precision: u8) -> Result<(TimeUnit, i64)> {
+    let unit = match precision {
+        0 => TimeUnit::Second,
+        1..=3 => TimeUnit::Millisecond,
+        4..=6 => TimeUnit::Microsecond,
+        7..=9 => TimeUnit::Nanosecond,
+        _ => {
+            return Err(DataTypeError::EncodeError(format!(
+                "DateTime64({precision}) has no Arrow counterpart"
+            )))
+        }
+    };
+    let digits = precision.div_ceil(3) * 3;
+    Ok((unit, 10_i64.pow((digits - precision).into())))
+}
+
+fn field(name: &str, column: Column, utf8: bool) -> Result<(Field, ArrayRef)> {
+    let nullable = column.data_type().is_nullable();
+    let array = column.into_arrow_with(utf8)?;
+    Ok((Field::new(name, array.data_type().clone(), nullable), array))
+}
+
+fn structs(
+    columns: impl IntoIterator<Item = (String, Column)>,
+    utf8: bool,
+) -> Result<StructArray> {
+    let (fields, arrays): (Vec<_>, Vec<_>) = columns
+        .into_iter()
+        .map(|(name, column)| field(&name, column, utf8))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    Ok(StructArray::try_new(Fields::from(fields), arrays, None)?)
+}
+
+/// Arrow offsets start at zero and are 32-bit.
+fn offsets(ends: &[u64]) -> Result<OffsetBuffer<i32>> {
+    let offsets = std::iter::once(Ok(0))
+        .chain(ends.iter().map(|end| i32::try_from(*end)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            DataTypeError::EncodeError(
+                "arrays hold too many elements for Arrow".into(),
+            )
+        })?;
+    Ok(OffsetBuffer::new(offsets.into()))
+}
+
+fn cast(array: &dyn Array, to: &ArrowType) -> Result<ArrayRef> {
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    Ok(cast_with_options(array, to, &options)?)
+}
+
+/// The values of a primitive array, with the default under nulls.
+fn values<T: ArrowPrimitiveType>(array: &dyn Array) -> Result<Vec<T::Native>> {
+    Ok(primitive_values::<T>(&cast(array, &T::DATA_TYPE)?))
+}
+
+/// Like [`values`], for an array already of the type of `T`.
+fn primitive_values<T: ArrowPrimitiveType>(
+    array: &dyn Array,
+) -> Vec<T::Native> {
+    let array = array.as_primitive::<T>();
+    array.iter().map(Option::unwrap_or_default).collect()
+}
+
+fn timestamp_values(array: &dyn Array, unit: TimeUnit) -> Result<Vec<i64>> {
+    let array = cast(array, &ArrowType::Timestamp(unit, Some(UTC.into())))?;
+    Ok(match unit {
+        TimeUnit::Second => primitive_values::<TimestampSecondType>(&array),
+        TimeUnit::Millisecond => {
+            primitive_values::<TimestampMillisecondType>(&array)
+        }
+        TimeUnit::Microsecond => {
+            primitive_values::<TimestampMicrosecondType>(&array)
+        }
+        TimeUnit::Nanosecond => {
+            primitive_values::<TimestampNanosecondType>(&array)
+        }
+    })
+}
+
+/// The bytes of each value of a string or binary array.
+fn binary_values(array: &dyn Array) -> Result<Vec<Option<Bytes>>> {
+    let array = cast(array, &ArrowType::Binary)?;
+    Ok(array
+        .as_binary::<i32>()
+        .iter()
+        .map(|x| x.map(Bytes::copy_from_slice))
+        .collect())
+}
+
+fn string_values(array: &dyn Array) -> Result<Vec<Option<String>>> {
+    let array = cast(array, &ArrowType::Utf8)?;
+    Ok(array
+        .as_string::<i32>()
+        .iter()
+        .map(|x| x.map(str::to_owned))
+        .collect())
+}
+
+fn out_of_range(
+    value: impl std::fmt::Display,
+    data_type: &DataType,
+) -> DataTypeError {
+    DataTypeError::EncodeError(format!(
+        "{value} is out of range for {data_type}"
+    ))
+}
+
+/// An address from its 16 bytes, or the unspecified one under nulls.
+fn ipv6(bytes: Option<&[u8]>, data_type: &DataType) -> Result<Ipv6Addr> {
+    match bytes {
+        None => Ok(Ipv6Addr::UNSPECIFIED),
+        Some(bytes) => <[u8; 16]>::try_from(bytes)
+            .map(Ipv6Addr::from)
+            .map_err(|_| out_of_range(format!("{bytes:?}"), data_type)),
+    }
+}
+
+/// Builds a column of `data_type` by pushing each value, or the default
+/// under nulls.
+fn push_all<T: IntoColumn>(
+    data_type: &DataType,
+    values: impl IntoIterator<Item = Option<T>>,
+) -> Result<Column> {
+    let mut column = Column::new(data_type);
+    for value in values {
+        match value {
+            Some(value) => column.push(value)?,
+            None => column.push_default(),
+        }
+    }
+    Ok(column)
+}
+
+/// The offsets of a list array, relative to its values, and those values.
+fn list_parts(array: &dyn Array) -> Result<(Vec<u64>, ArrayRef)> {
+    let (offsets, values): (Vec<i64>, _) = match array.data_type() {
+        ArrowType::List(_) => {
+            let list = array.as_list::<i32>();
+            let offsets = list.value_offsets().iter().map(|x| *x as i64);
+            (offsets.collect(), list.values().clone())
+        }
+        ArrowType::LargeList(_) => {
+            let list = array.as_list::<i64>();
+            (list.value_offsets().to_vec(), list.values().clone())
+        }
+        ArrowType::Map(_, _) => {
+            let map = array.as_map();
+            let offsets = map.value_offsets().iter().map(|x| *x as i64);
+            (
+                offsets.collect(),
+                Arc::new(map.entries().clone()) as ArrayRef,
+            )
+        }
+        ArrowType::FixedSizeList(_, size) => {
+            let list = array.as_fixed_size_list();
+            let size = *size as i64;
+            let start = list.offset() as i64 * size;
+            let offsets = (0..=list.len() as i64).map(|i| start + i * size);
+            (offsets.collect(), list.values().clone())
+        }
+        other => {
+            return Err(DataTypeError::EncodeError(format!(
+                "cannot convert Arrow {other} to an array"
+            )))
+        }
+    };
+    let first = offsets.first().copied().unwrap_or(0);
+    let last = offsets.last().copied().unwrap_or(0);
+    let values = values.slice(first as usize, (last - first) as usize);
+    let ends = offsets.iter().skip(1).map(|x| (x - first) as u64).collect();
+    Ok((ends, values))
+}
+
+fn struct_columns<'a>(
+    array: &dyn Array,
+    types: impl ExactSizeIterator<Item = &'a DataType>,
+) -> Result<Vec<Column>> {
+    let Some(array) = array.as_struct_opt() else {
+        return Err(DataTypeError::EncodeError(format!(
+            "cannot convert Arrow {} to a tuple",
+            array.data_type()
+        )));
+    };
+    if array.num_columns() != types.len() {
+        return Err(DataTypeError::EncodeError(format!(
+            "Arrow struct has {} fields, expected {}",
+            array.num_columns(),
+            types.len()
+        )));
+    }
+    array
+        .columns()
+        .iter()
+        .zip(types)
+        .map(|(array, data_type)| Column::from_arrow(array, data_type))
+        .collect()
+}
+
+/// Converts an array to a column of a type other than `Nullable`, with the
+/// default value under any nulls.
+fn from_arrow_values(
+    array: &dyn Array,
+    data_type: &DataType,
+) -> Result<Column> {
+    let column = match data_type {
+        DataType::UInt8 => Column::UInt8(values::<UInt8Type>(array)?),
+        DataType::UInt16 => Column::UInt16(values::<UInt16Type>(array)?),
+        DataType::UInt32 => Column::UInt32(values::<UInt32Type>(array)?),
+        DataType::UInt64 => Column::UInt64(values::<UInt64Type>(array)?),
+        DataType::UInt128 => match array.data_type() {
+            ArrowType::FixedSizeBinary(16) => Column::UInt128(
+                binary_values(array)?
+                    .iter()
+                    .map(|x| {
+                        let bytes =
+                            x.as_deref().and_then(|x| x.try_into().ok());
+                        u128::from_le_bytes(bytes.unwrap_or_default())
+                    })
+                    .collect(),
+            ),
+            _ => Column::UInt128(
+                values::<UInt64Type>(array)?
+                    .into_iter()
+                    .map(u128::from)
+                    .collect(),
+            ),
+        },
+        DataType::Int8 => Column::Int8(values::<Int8Type>(array)?),
+        DataType::Int16 => Column::Int16(values::<Int16Type>(array)?),
+        DataType::Int32 => Column::Int32(values::<Int32Type>(array)?),
+        DataType::Int64 => Column::Int64(values::<Int64Type>(array)?),
+        DataType::Int128 => {
+            let array = cast(array, &ArrowType::Decimal128(38, 0))?;
+            Column::Int128(primitive_values::<Decimal128Type>(&array))
+        }
+        DataType::Float32 => Column::Float32(values::<Float32Type>(array)?),
+        DataType::Float64 => Column::Float64(values::<Float64Type>(array)?),
+        DataType::Bool => {
+            let array = cast(array, &ArrowType::Boolean)?;
+            let values = array.as_boolean().iter();
+            Column::Bool(values.map(Option::unwrap_or_default).collect())
+        }
+        DataType::String => Column::String(
+            binary_values(array)?
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+        ),
+        DataType::FixedString(_) | DataType::AggregateFunction(_, _) => {
+            push_all(data_type, binary_values(array)?)?
+        }
+        DataType::Enum8(_) | DataType::Enum16(_) | DataType::Json(_) => {
+            push_all(data_type, string_values(array)?)?
+        }
+        DataType::Date => Column::Date(
+            values::<Date32Type>(array)?
+                .into_iter()
+                .map(|x| {
+                    u16::try_from(x).map_err(|_| out_of_range(x, data_type))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        DataType::Date32 => Column::Date32(values::<Date32Type>(array)?),
+        DataType::DateTime(tz) => Column::DateTime {
+            tz: tz.clone(),
+            data: timestamp_values(array, TimeUnit::Second)?
+                .into_iter()
+                .map(|x| {
+                    u32::try_from(x).map_err(|_| out_of_range(x, data_type))
+                })
+                .collect::<Result<_>>()?,
+        },
+        DataType::DateTime64(precision, tz) => {
+            let (unit, factor) = time_unit(*precision)?;
+            let mut data = timestamp_values(array, unit)?;
+            if factor > 1 {
+                data.iter_mut().for_each(|x| *x = x.div_euclid(factor));
+            }
+            Column::DateTime64 {
+                precision: *precision,
+                tz: tz.clone(),
+                data,
+            }
+        }
+        DataType::Uuid => Column::Uuid(
+            binary_values(array)?
+                .iter()
+                .map(|x| match x {
+                    None => Ok(Uuid::nil()),
+                    Some(x) if x.len() == 16 => Uuid::from_slice(x),
+                    Some(x) => Uuid::try_parse_ascii(x),
+                })
+                .collect::<Result<_, _>>()
+                .map_err(|e| DataTypeError::EncodeError(e.to_string()))?,
+        ),
+        DataType::IPv4 => Column::IPv4(
+            values::<UInt32Type>(array)?
+                .into_iter()
+                .map(Ipv4Addr::from)
+                .collect(),
+        ),
+        DataType::IPv6 => Column::IPv6(
+            binary_values(array)?
+                .iter()
+                .map(|x| ipv6(x.as_deref(), data_type))
+                .collect::<Result<_>>()?,
+        ),
+        DataType::Array(inner) => {
+            let (offsets, values) = list_parts(array)?;
+            let inner = Column::from_arrow(&values, inner)?;
+            Column::Array(ArrayColumn::new(offsets, inner)?)
+        }
+        DataType::Ring
+        | DataType::LineString
+        | DataType::MultiLineString
+        | DataType::Polygon
+        | DataType::MultiPolygon => {
+            let (offsets, values) = list_parts(array)?;
+            let inner = match data_type {
+                DataType::Ring | DataType::LineString => DataType::Point,
+                DataType::MultiLineString => DataType::LineString,
+                DataType::Polygon => DataType::Ring,
+                _ => DataType::Polygon,
+            };
+            let inner = Column::from_arrow(&values, &inner)?;
+            let array = ArrayColumn::new(offsets, inner)?;
+            match data_type {
+                DataType::Ring => Column::Ring(array),
+                DataType::LineString => Column::LineString(array),
+                DataType::MultiLineString => Column::MultiLineString(array),
+                DataType::Polygon => Column::Polygon(array),
+                _ => Column::MultiPolygon(array),
+            }
+        }
+        DataType::Nested(fields) => {
+            let (offsets, values) = list_parts(array)?;
+            let columns =
+                struct_columns(&values, fields.iter().map(|(_, x)| x))?;
+            let names = fields.iter().map(|(name, _)| name.clone());
+            Column::Nested(NestedColumn::new(
+                offsets,
+                names.zip(columns).collect(),
+            )?)
+        }
+        DataType::Tuple(elements) => {
+            let columns =
+                struct_columns(array, elements.iter().map(|(_, x)| x))?;
+            let names = elements.iter().map(|(name, _)| name.clone());
+            Column::Tuple(super::TupleColumn::new(
+                names.zip(columns).collect(),
+            )?)
+        }
+        DataType::Map(key, value) => {
+            let (offsets, entries) = list_parts(array)?;
+            let mut columns =
+                struct_columns(&entries, [&**key, &**value].into_iter())?;
+            let values = columns.pop().expect("two columns");
+            let keys = columns.pop().expect("two columns");
+            Column::Map(super::MapColumn::new(offsets, keys, values)?)
+        }
+        DataType::Point => {
+            let types = [DataType::Float64, DataType::Float64];
+            let columns = struct_columns(array, types.iter())?;
+            let (Column::Float64(x), Column::Float64(y)) =
+                (&columns[0], &columns[1])
+            else {
+                unreachable!("converted to Float64")
+            };
+            Column::Point(
+                x.iter()
+                    .zip(y)
+                    .map(|(x, y)| Point { x: *x, y: *y })
+                    .collect(),
+            )
+        }
+        DataType::Nullable(_)
+        | DataType::LowCardinality(_)
+        | DataType::SimpleAggregateFunction(_, _) => {
+            Column::from_arrow(array, data_type)?
+        }
+        DataType::Variant(_) | DataType::Dynamic(_) => {
+            return Err(unsupported(data_type))
+        }
+    };
+    Ok(column)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use arrow::array::{Array, AsArray, Int64Array, ListArray, StringArray};
+    use arrow::datatypes::{
+        DataType as ArrowType, Int32Type, TimeUnit, TimestampMillisecondType,
+        UInt64Type,
+    };
+
+    use crate::{Column, DataType, Value};
+
+    fn column(data_type: &str, values: Vec<Value>) -> Result<Column> {
+        let mut column = Column::new(&data_type.parse()?);
+        for value in values {
+            column.push(value)?;
+        }
+        Ok(column)
+    }
+
+    /// Every column converts to Arrow and back unchanged.
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for (data_type, values) in
+            [
+                ("UInt128", vec![Value::UInt128(u128::MAX)]),
+                ("Int128", vec![Value::Int128(-1)]),
+                ("Nullable(String)", vec!["a".into(), Value::Null]),
+                ("FixedString(2)", vec!["ab".into()]),
+                ("Date", vec![Value::Date(1)]),
+                (
+                    "DateTime64(2, 'UTC')",
+                    vec![Value::DateTime64 {
+                        value: -150,
+                        precision: 2,
+                        tz: Some("UTC".into()),
+                    }],
+                ),
+                (
+                    "Enum8('a' = -1, 'b' = 5)",
+                    vec![Value::Enum8 {
+                        name: "b".into(),
+                        code: 5,
+                    }],
+                ),
+                (
+                    "LowCardinality(Nullable(String))",
+                    vec![Value::Null, "x".into()],
+                ),
+                ("Array(Nullable(UInt8))", vec![vec![Some(1u8), None].into()]),
+                (
+                    "Map(String, UInt64)",
+                    vec![Value::Map(vec![("k".into(), 1u64.into())])],
+                ),
+                (
+                    "Tuple(a Int32, String)",
+                    vec![Value::Tuple(vec![1i32.into(), "s".into()])],
+                ),
+                (
+                    "Nested(a UInt8, b String)",
+                    vec![Value::Array(vec![Value::Tuple(vec![
+                        1u8.into(),
+                        "x".into(),
+                    ])])],
+                ),
+                ("UUID", vec![Value::Uuid(uuid::Uuid::from_u128(7))]),
+                (
+                    "Nullable(Enum8('a' = -1, 'b' = 5))",
+                    vec![
+                        Value::Null,
+                        Value::Enum8 {
+                            name: "b".into(),
+                            code: 5,
+                        },
+                    ],
+                ),
+                ("Nullable(IPv6)", vec![Value::Null]),
+                ("IPv6", vec![Value::IPv6("::1".parse()?)]),
+                (
+                    "Polygon",
+                    vec![Value::Array(vec![Value::Array(vec![
+                        crate::Point { x: 1.0, y: 2.0 }.into(),
+                    ])])],
+                ),
+            ]
+        {
+            let column = column(data_type, values)?;
+            let array = column.clone().into_arrow()?;
+            assert_eq!(
+                Column::from_arrow(&array, &column.data_type())?,
+                column,
+                "{data_type}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrow_types() -> Result<()> {
+        let array = column("Nullable(UInt64)", vec![1u64.into(), Value::Null])?
+            .into_arrow()?;
+        assert_eq!(array.as_primitive::<UInt64Type>().values(), &[1, 0]);
+        assert!(array.is_null(1));
+
+        let array = column(
+            "DateTime64(3, 'Europe/Berlin')",
+            vec![Value::DateTime64 {
+                value: 1500,
+                precision: 3,
+                tz: None,
+            }],
+        )?
+        .into_arrow()?;
+        assert_eq!(
+            array.data_type(),
+            &ArrowType::Timestamp(
+                TimeUnit::Millisecond,
+                Some("Europe/Berlin".into())
+            )
+        );
+        assert_eq!(
+            array.as_primitive::<TimestampMillisecondType>().value(0),
+            1500
+        );
+
+        let array =
+            column("LowCardinality(String)", vec!["a".into(), "a".into()])?
+                .into_arrow()?;
+        let dictionary = array.as_dictionary::<UInt64Type>();
+        assert_eq!(dictionary.keys().values(), &[0, 0]);
+        assert_eq!(dictionary.values().len(), 1);
+
+        // strings are Binary whatever their values, unless Utf8 is asked for
+        let array = column("String", vec!["a".into()])?.into_arrow()?;
+        assert_eq!(array.data_type(), &ArrowType::Binary);
+        let array = column("String", vec!["a".into()])?.into_arrow_utf8()?;
+        assert_eq!(array.data_type(), &ArrowType::Utf8);
+        let strings = column("String", vec![Value::String(vec![0xff].into())])?;
+        assert_eq!(
+            strings.clone().into_arrow()?.data_type(),
+            &ArrowType::Binary
+        );
+        assert!(strings.into_arrow_utf8().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_arrow_casts() -> Result<()> {
+        let array = Int64Array::from(vec![1, 300]);
+        assert_eq!(
+            Column::from_arrow(&array, &DataType::UInt16)?,
+            Column::UInt16(vec![1, 300])
+        );
+        assert!(Column::from_arrow(&array, &DataType::UInt8).is_err());
+
+        let array = StringArray::from(vec![Some("a"), None]);
+        assert!(Column::from_arrow(&array, &DataType::String).is_err());
+        let column = Column::from_arrow(
+            &array,
+            &"LowCardinality(Nullable(String))".parse()?,
+        )?;
+        assert_eq!(column.get::<Option<&str>>(0)?, Some("a"));
+        assert_eq!(column.get::<Option<&str>>(1)?, None);
+
+        // sliced lists only convert their own elements
+        let lists = ListArray::from_iter_primitive::<Int32Type, _, _>([
+            Some(vec![Some(1)]),
+            Some(vec![Some(2), Some(3)]),
+        ]);
+        let lists: Arc<dyn Array> = Arc::new(lists.slice(1, 1));
+        let column = Column::from_arrow(&lists, &"Array(Int64)".parse()?)?;
+        assert_eq!(column.get::<Vec<i64>>(0)?, [2, 3]);
+        assert_eq!(column.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use super::{ArrayColumn, Column, ColumnSlice, Primitive};
+use crate::error::{DataTypeError, Result};
+use crate::types::quote;
+
+/// Reads a single row of a [`Column`] as a Rust value.
+pub trait FromColumn<'a>: Sized {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self>;
+}
+
+/// Appends a Rust value as a new row of a [`Column`].
+pub trait IntoColumn {
+    fn append_to(self, column: &mut Column) -> Result<()>;
+}
+
+pub(crate) fn mismatch<T>(column: &Column) -> DataTypeError {
+    DataTypeError::TypeMismatch {
+        data_type: column.data_type().to_string(),
+        rust_type: std::any::type_name::<T>(),
+    }
+}
+
+pub(crate) fn get<T>(data: &[T], row: usize) -> Result<&T> {
+    data.get(row).ok_or(DataTypeError::RowOutOfBounds {
+        row,
+        len: data.len(),
+    })
+}
+
+macro_rules! impl_convert_primitive {
+    ($($ty:ty),+) => {
+        $(
+            impl<'a> FromColumn<'a> for $ty {
+                fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+                    let data = <$ty>::as_slice(column)
+                        .ok_or_else(|| mismatch::<Self>(column))?;
+                    get(data, row).copied()
+                }
+            }
+
+            impl IntoColumn for $ty {
+                fn append_to(self, column: &mut Column) -> Result<()> {
+                    match <$ty>::as_vec_mut(column) {
+                        Some(data) => data.push(self),
+                        None => return Err(mismatch::<Self>(column)),
+                    }
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+impl_convert_primitive!(
+    u8, u16, u32, u64, u128, i32, i64, i128, f32, f64, Uuid, Ipv4Addr, Ipv6Addr
+);
+
+/// Like [`impl_convert_primitive`], but also reads and writes the codes of
+/// an enum column. Codes outside of the enum are rejected.
+macro_rules! impl_convert_enum_code {
+    ($($ty:ty => $variant:ident),+) => {
+        $(
+            impl<'a> FromColumn<'a> for $ty {
+                fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+                    let data = <$ty>::as_slice(column)
+                        .ok_or_else(|| mismatch::<Self>(column))?;
+                    get(data, row).copied()
+                }
+            }
+
+            impl IntoColumn for $ty {
+                fn append_to(self, column: &mut Column) -> Result<()> {
+                    if let Column::$variant(values) = column {
+                        if !values.contains(self) {
+                            return Err(unknown_enum_value(column, self));
+                        }
+                        values.data.push(self);
+                        return Ok(());
+                    }
+                    match <$ty>::as_vec_mut(column) {
+                        Some(data) => data.push(self),
+                        None => return Err(mismatch::<Self>(column)),
+                    }
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+impl_convert_enum_code!(i8 => Enum8, i16 => Enum16);
+
+fn unknown_enum_value(column: &Column, value: impl Display) -> DataTypeError {
+    DataTypeError::UnknownEnumValue {
+        value: value.to_string(),
+        data_type: column.data_type().to_string(),
+    }
+}
+
+/// Appends an enum value by name.
+fn push_enum_name(column: &mut Column, name: &str) -> Option<Result<()>> {
+    let code = match column {
+        Column::Enum8(values) => {
+            values.code(name).map(|code| values.data.push(code))
+        }
+        Column::Enum16(values) => {
+            values.code(name).map(|code| values.data.push(code))
+        }
+        _ => return None,
+    };
+    Some(code.ok_or_else(|| unknown_enum_value(column, quote(name))))
+}
+
+impl<'a> FromColumn<'a> for bool {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Bool(data) => get(data, row).copied(),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl IntoColumn for bool {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::Bool(data) => data.push(self),
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// Reads `String` values, checking that they are UTF-8, `FixedString`
+/// values without their zero padding, or the names of enum values.
+impl<'a> FromColumn<'a> for &'a str {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::String(data) => Ok(std::str::from_utf8(get(data, row)?)?),
+            Column::FixedString(values) => {
+                Ok(std::str::from_utf8(values.trimmed(row)?)?)
+            }
+            Column::Json { data, .. } => get(data, row).map(String::as_str),
+            Column::Enum8(values) => {
+                let code = *get(&values.data, row)?;
+                Ok(values.name(code).expect("enum codes are validated"))
+            }
+            Column::Enum16(values) => {
+                let code = *get(&values.data, row)?;
+                Ok(values.name(code).expect("enum codes are validated"))
+            }
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl<'a> FromColumn<'a> for String {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        <&str>::from_column(column, row).map(str::to_owned)
+    }
+}
+
+impl IntoColumn for String {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        if let Some(result) = push_enum_name(column, &self) {
+            return result;
+        }
+        match column {
+            Column::String(data) => data.push(Bytes::from(self.into_bytes())),
+            Column::FixedString(values) => values.push(self.as_bytes())?,
+            Column::Json { data, .. } => data.push(self),
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+impl IntoColumn for &str {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        if let Some(result) = push_enum_name(column, self) {
+            return result;
+        }
+        match column {
+            Column::String(data) => {
+                data.push(Bytes::copy_from_slice(self.as_bytes()))
+            }
+            Column::FixedString(values) => values.push(self.as_bytes())?,
+            Column::Json { data, .. } => data.push(self.to_owned()),
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// `JSON` maps to `serde_json::Value`.
+#[cfg(feature = "json")]
+impl<'a> FromColumn<'a> for serde_json::Value {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Json { data, .. } => serde_json::from_str(get(data, row)?)
+                .map_err(|e| {
+                    DataTypeError::DecodeError(format!("invalid JSON: {e}"))
+                }),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl IntoColumn for serde_json::Value {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::Json { data, .. } => data.push(self.to_string()),
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// Reads the raw bytes of `String` and `FixedString` values, and the
+/// serialized states of `AggregateFunction` columns.
+impl<'a> FromColumn<'a> for Bytes {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::String(data) => get(data, row).cloned(),
+            Column::AggregateFunction(states) => {
+                get(states.states(), row).cloned()
+            }
+            Column::FixedString(values) => {
+                values.value(row).map(Bytes::copy_from_slice)
+            }
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl IntoColumn for Bytes {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::String(data) => data.push(self),
+            Column::FixedString(values) => values.push(&self)?,
+            Column::AggregateFunction(states) => states.push(self)?,
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// `Nullable(T)` maps to `Option<T>`, as do the `NULL` rows of `Variant` and
+/// `Dynamic` columns; any other column always yields `Some`.
+impl<'a, T: FromColumn<'a>> FromColumn<'a> for Option<T> {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Nullable(nullable) if nullable.is_null(row) => Ok(None),
+            Column::Nullable(nullable) => {
+                T::from_column(nullable.inner(), row).map(Some)
+            }
+            Column::Variant(variant) if variant.is_null(row) => Ok(None),
+            Column::Dynamic(dynamic) if dynamic.variant().is_null(row) => {
+                Ok(None)
+            }
+            _ => T::from_column(column, row).map(Some),
+        }
+    }
+}
+
+impl<T: IntoColumn> IntoColumn for Option<T> {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match (column, self) {
+            (Column::Nullable(nullable), None) => nullable.push_null(),
+            (Column::Variant(variant), None) => variant.push_null(),
+            (Column::Dynamic(dynamic), None) => dynamic.push_null(),
+            (Column::Nullable(nullable), Some(value)) => {
+                value.append_to(&mut nullable.inner)?;
+                nullable.nulls.push(0);
+            }
+            (column, Some(value)) => value.append_to(column)?,
+            (column, None) => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// The array holding the rows of an `Array(T)` column or a geo type such as
+/// `Polygon`, or the `Array(Tuple(K, V))` entries of a `Map(K, V)` column.
+fn entries(column: &Column) -> Option<&ArrayColumn> {
+    match column {
+        Column::Array(array)
+        | Column::Ring(array)
+        | Column::LineString(array)
+        | Column::MultiLineString(array)
+        | Column::Polygon(array)
+        | Column::MultiPolygon(array) => Some(array),
+        Column::Map(map) => Some(&map.entries),
+        _ => None,
+    }
+}
+
+fn entries_mut(column: &mut Column) -> Option<&mut ArrayColumn> {
+    match column {
+        Column::Array(array)
+        | Column::Ring(array)
+        | Column::LineString(array)
+        | Column::MultiLineString(array)
+        | Column::Polygon(array)
+        | Column::MultiPolygon(array) => Some(array),
+        Column::Map(map) => Some(&mut map.entries),
+        _ => None,
+    }
+}
+
+fn collect_entries<'a, T, C>(column: &'a Column, row: usize) -> Result<C>
+where
+    T: FromColumn<'a>,
+    C: FromIterator<T>,
+{
+    let array = entries(column).ok_or_else(|| mismatch::<C>(column))?;
+    array.range(row)?.map(|i| array.inner().get(i)).collect()
+}
+
+/// `Array(T)` maps to `Vec<T>`, and `Map(K, V)` to `Vec<(K, V)>` in
+/// insertion order.
+impl<'a, T: FromColumn<'a>> FromColumn<'a> for Vec<T> {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        collect_entries(column, row)
+    }
+}
+
+impl<'a> FromColumn<'a> for ColumnSlice<'a> {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Array(array) => array.slice(row),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+/// Borrows an `Array(T)` row of fixed-width values without copying it.
+///
+/// `&[u8]` also borrows the bytes of `String` and `FixedString` values,
+/// and `AggregateFunction` states.
+impl<'a, T: Primitive> FromColumn<'a> for &'a [T] {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        let bytes = match column {
+            Column::String(data) => Some(&get(data, row)?[..]),
+            Column::FixedString(values) => Some(values.value(row)?),
+            Column::AggregateFunction(states) => {
+                Some(&get(states.states(), row)?[..])
+            }
+            _ => None,
+        };
+        match bytes {
+            Some(bytes) => T::from_bytes(bytes),
+            None => ColumnSlice::from_column(column, row)?.as_slice(),
+        }
+        .ok_or_else(|| mismatch::<Self>(column))
+    }
+}
+
+fn push_entries<T, I>(values: I, column: &mut Column) -> Result<()>
+where
+    T: IntoColumn,
+    I: IntoIterator<Item = T>,
+{
+    let Some(array) = entries_mut(column) else {
+        return Err(mismatch::<I>(column));
+    };
+    array.push_with(|inner| {
+        values.into_iter().try_for_each(|value| inner.push(value))
+    })
+}
+
+impl<T: IntoColumn> IntoColumn for Vec<T> {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        push_entries(self, column)
+    }
+}
+
+impl<T: IntoColumn + Clone> IntoColumn for &[T] {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        push_entries(self.iter().cloned(), column)
+    }
+}
+
+/// `Map(K, V)` maps to `HashMap<K, V>`; later duplicate keys win.
+impl<'a, K, V> FromColumn<'a> for HashMap<K, V>
+where
+    K: FromColumn<'a> + Eq + Hash,
+    V: FromColumn<'a>,
+{
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Map(_) => collect_entries(column, row),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl<K: IntoColumn, V: IntoColumn> IntoColumn for HashMap<K, V> {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::Map(_) => push_entries(self, column),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+/// `Map(K, V)` maps to `BTreeMap<K, V>`; later duplicate keys win.
+impl<'a, K, V> FromColumn<'a> for BTreeMap<K, V>
+where
+    K: FromColumn<'a> + Ord,
+    V: FromColumn<'a>,
+{
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Map(_) => collect_entries(column, row),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl<K: IntoColumn, V: IntoColumn> IntoColumn for BTreeMap<K, V> {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::Map(_) => push_entries(self, column),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+/// `Tuple(T, ...)` maps to a Rust tuple with the same number of elements.
+macro_rules! impl_convert_tuple {
+    ($(($len:expr; $($ty:ident $idx:tt),+))+) => {
+        $(
+            impl<'a, $($ty: FromColumn<'a>),+> FromColumn<'a> for ($($ty,)+) {
+                fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+                    match column {
+                        Column::Tuple(tuple) if tuple.elements.len() == $len => {
+                            Ok(($(tuple.elements[$idx].1.get::<$ty>(row)?,)+))
+                        }
+                        _ => Err(mismatch::<Self>(column)),
+                    }
+                }
+            }
+
+            impl<$($ty: IntoColumn),+> IntoColumn for ($($ty,)+) {
+                fn append_to(self, column: &mut Column) -> Result<()> {
+                    match column {
+                        Column::Tuple(tuple) if tuple.elements.len() == $len => {
+                            tuple.push_with(|elements| {
+                                $(elements[$idx].1.push(self.$idx)?;)+
+                                Ok(())
+                            })
+                        }
+                        _ => Err(mismatch::<Self>(column)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_convert_tuple! {
+    (1; A 0)
+    (2; A 0, B 1)
+    (3; A 0, B 1, C 2)
+    (4; A 0, B 1, C 2, D 3)
+    (5; A 0, B 1, C 2, D 3, E 4)
+    (6; A 0, B 1, C 2, D 3, E 4, F 5)
+    (7; A 0, B 1, C 2, D 3, E 4, F 5, G 6)
+    (8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7)
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::primitive::{read_primitive, write_primitive};
+use super::Primitive;
+use crate::error::{DataTypeError, Result};
+
+/// An `Enum8` or `Enum16` column.
+///
+/// Values are stored as their codes. Names and codes can be looked up in
+/// both directions.
+#[derive(Debug, Clone)]
+pub struct EnumColumn<T> {
+    pub(crate) variants: Vec<(String, T)>,
+    by_name: HashMap<String, T>,
+    by_code: HashMap<T, usize>,
+    pub(crate) data: Vec<T>,
+}
+
+impl<T> EnumColumn<T>
+where
+    T: Primitive + Eq + Hash + Display,
+{
+    pub fn new(variants: Vec<(String, T)>, data: Vec<T>) -> Result<Self> {
+        let mut column = EnumColumn::empty(&variants);
+        if let Some(code) = data.iter().find(|code| !column.contains(**code)) {
+            return Err(DataTypeError::EncodeError(format!(
+                "unknown enum code {code}"
+            )));
+        }
+        column.data = data;
+        Ok(column)
+    }
+
+    pub(crate) fn empty(variants: &[(String, T)]) -> Self {
+        EnumColumn {
+            by_name: variants.iter().cloned().collect(),
+            by_code: variants
+                .iter()
+                .enumerate()
+                .map(|(i, (_, code))| (*code, i))
+                .collect(),
+            variants: variants.to_vec(),
+            data: Vec::new(),
+        }
+    }
+
+    /// The `(name, code)` pairs of the enum type, sorted by code.
+    pub fn variants(&self) -> &[(String, T)] {
+        &self.variants
+    }
+
+    /// The code of every row.
+    pub fn codes(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn name(&self, code: T) -> Option<&str> {
+        let index = *self.by_code.get(&code)?;
+        Some(&self.variants[index].0)
+    }
+
+    pub fn code(&self, name: &str) -> Option<T> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn contains(&self, code: T) -> bool {
+        self.by_code.contains_key(&code)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Appends the smallest code, which is what the server uses as the
+    /// default value.
+    pub(crate) fn push_default(&mut self) {
+        let code = self.variants.first().map(|(_, code)| *code);
+        self.data.push(code.unwrap_or(T::ZERO));
+    }
+
+    pub(crate) async fn read<R>(
+        variants: &[(String, T)],
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<Self>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut column = EnumColumn::empty(variants);
+        let data: Vec<T> = read_primitive(reader, rows).await?;
+        if let Some(code) = data.iter().find(|code| !column.contains(**code)) {
+            return Err(DataTypeError::DecodeError(format!(
+                "unknown enum code {code}"
+            )));
+        }
+        column.data = data;
+        Ok(column)
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        write_primitive(writer, &self.data).await
+    }
+}
+
+impl<T: PartialEq> PartialEq for EnumColumn<T> {
+    fn eq(&self, other: &Self) -> bool {
+        // the lookup maps are derived from the variants
+        self.variants == other.variants && self.data == other.data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, DataType, DataTypeError};
+
+    #[tokio::test]
+    async fn test_enum8_round_trip() -> Result<()> {
+        let data_type: DataType = "Enum8('a' = 1, 'b' = -2)".parse()?;
+        let bytes = [1, 0xfe, 1];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+        assert_eq!(column.get::<&str>(0)?, "a");
+        assert_eq!(column.get::<String>(1)?, "b");
+        assert_eq!(column.get::<i8>(1)?, -2);
+        assert_eq!(column.as_slice::<i8>(), Some(&[1, -2, 1][..]));
+
+        let mut built = Column::new(&data_type);
+        built.push("a")?;
+        built.push(-2_i8)?;
+        built.push("a".to_owned())?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_enum16_unknown_values() -> Result<()> {
+        let data_type: DataType = "Nullable(Enum16('x' = 1000))".parse()?;
+        let mut column = Column::new(&data_type);
+        column.push(None::<&str>)?;
+        assert!(matches!(
+            column.push(Some("y")),
+            Err(DataTypeError::UnknownEnumValue { .. })
+        ));
+        assert!(column.push(Some(7_i16)).is_err());
+        assert!(column.push(1_u8).is_err());
+        assert_eq!(column.len(), 1);
+
+        let Column::Nullable(nullable) = &column else {
+            panic!("expected nullable column");
+        };
+        assert_eq!(nullable.inner().as_slice::<i16>(), Some(&[1000][..]));
+
+        let bytes = [0, 0xe9, 0x03];
+        let mut reader = &bytes[..];
+        assert!(Column::read(&data_type, 1, &mut reader).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use crate::binary::read_bytes;
+use crate::error::{DataTypeError, Result};
+
+/// A `FixedString(N)` column, stored as `N` bytes per row back to back.
+///
+/// Shorter values are padded with zero bytes on insert. Reading a row as
+/// `&[u8]` or `Bytes` returns all `N` bytes, while reading it as `&str` or
+/// `String` drops the trailing zero bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedStringColumn {
+    pub(crate) size: usize,
+    pub(crate) data: Vec<u8>,
+}
+
+impl FixedStringColumn {
+    pub fn new(size: usize, data: Vec<u8>) -> Result<FixedStringColumn> {
+        if size == 0 || !data.len().is_multiple_of(size) {
+            return Err(DataTypeError::EncodeError(format!(
+                "{} bytes do not make up rows of FixedString({size})",
+                data.len()
+            )));
+        }
+        Ok(FixedStringColumn { size, data })
+    }
+
+    pub(crate) fn empty(size: usize) -> FixedStringColumn {
+        FixedStringColumn {
+            size,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// All `N` bytes of `row`.
+    pub fn value(&self, row: usize) -> Result<&[u8]> {
+        if row >= self.len() {
+            return Err(DataTypeError::RowOutOfBounds {
+                row,
+                len: self.len(),
+            });
+        }
+        Ok(&self.data[row * self.size..(row + 1) * self.size])
+    }
+
+    /// The bytes of `row` without its trailing zero padding.
+    pub fn trimmed(&self, row: usize) -> Result<&[u8]> {
+        let value = self.value(row)?;
+        let end = value.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+        Ok(&value[..end])
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() / self.size.max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Appends `value`, padded with zero bytes to `N` bytes.
+    pub(crate) fn push(&mut self, value: &[u8]) -> Result<()> {
+        if value.len() > self.size {
+            return Err(DataTypeError::EncodeError(format!(
+                "value of {} bytes is too long for FixedString({})",
+                value.len(),
+                self.size
+            )));
+        }
+        self.data.extend_from_slice(value);
+        self.data
+            .resize(self.data.len() + self.size - value.len(), 0);
+        Ok(())
+    }
+
+    pub(crate) fn push_default(&mut self) {
+        self.data.resize(self.data.len() + self.size, 0);
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.data.truncate(len * self.size);
+    }
+
+    pub(crate) async fn read<R>(
+        size: usize,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<FixedStringColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let data = read_bytes(reader, rows * size).await?;
+        Ok(FixedStringColumn { size, data })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_all(&self.data).await?;
+        Ok(self.data.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_fixed_string_round_trip() -> Result<()> {
+        let data_type: DataType = "FixedString(3)".parse()?;
+        let bytes = [b'a', b'b', b'c', b'x', 0, 0, 0, 0xff, 0];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+        assert_eq!(column.get::<&str>(0)?, "abc");
+        assert_eq!(column.get::<String>(1)?, "x");
+        assert_eq!(column.get::<&[u8]>(1)?, b"x\0\0");
+        assert_eq!(column.get::<Bytes>(2)?, &[0, 0xff, 0][..]);
+        assert!(column.get::<&str>(2).is_err());
+
+        let mut built = Column::new(&data_type);
+        built.push("abc")?;
+        built.push("x".to_owned())?;
+        built.push(Bytes::from_static(&[0, 0xff]))?;
+        assert!(built.push("abcd").is_err());
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_string_low_cardinality() -> Result<()> {
+        let mut column =
+            Column::new(&"LowCardinality(FixedString(2))".parse()?);
+        column.push("a")?;
+        column.push(Bytes::from_static(b"a\0"))?;
+        let Column::LowCardinality(lc) = &column else {
+            panic!("expected low cardinality column");
+        };
+        assert_eq!(lc.keys(), lc.keys()[..1].repeat(2));
+        assert_eq!(column.get::<&str>(1)?, "a");
+        Ok(())
+    }
+}
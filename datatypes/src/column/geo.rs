@@ -0,0 +1,322 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::convert::{get, mismatch};
+use super::primitive::{read_primitive, write_primitive};
+use super::{Column, FromColumn, IntoColumn};
+use crate::error::Result;
+
+/// A `Point`, stored as `Tuple(Float64, Float64)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A `Ring`, a closed line whose last point connects back to the first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ring(pub Vec<Point>);
+
+/// A `LineString`, an open line through its points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineString(pub Vec<Point>);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultiLineString(pub Vec<LineString>);
+
+/// A `Polygon`, the outer ring followed by the rings of its holes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polygon(pub Vec<Ring>);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultiPolygon(pub Vec<Polygon>);
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for (f64, f64) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl<'a> FromColumn<'a> for Point {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        match column {
+            Column::Point(data) => get(data, row).copied(),
+            _ => Err(mismatch::<Self>(column)),
+        }
+    }
+}
+
+impl IntoColumn for Point {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::Point(data) => data.push(self),
+            _ => return Err(mismatch::<Self>(column)),
+        }
+        Ok(())
+    }
+}
+
+/// Converts the geo types that are arrays on the wire through `Vec` of
+/// their elements, which also works for the plain `Vec`.
+macro_rules! impl_convert_geo {
+    ($($ty:ident => $item:ty),+) => {
+        $(
+            impl<'a> FromColumn<'a> for $ty {
+                fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+                    match column {
+                        Column::$ty(_) => {
+                            Vec::<$item>::from_column(column, row).map($ty)
+                        }
+                        _ => Err(mismatch::<Self>(column)),
+                    }
+                }
+            }
+
+            impl IntoColumn for $ty {
+                fn append_to(self, column: &mut Column) -> Result<()> {
+                    match column {
+                        Column::$ty(_) => self.0.append_to(column),
+                        _ => Err(mismatch::<Self>(column)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_convert_geo!(
+    Ring => Point,
+    LineString => Point,
+    MultiLineString => LineString,
+    Polygon => Ring,
+    MultiPolygon => Polygon
+);
+
+/// Points are written as a tuple column: all `x` coordinates, then all `y`
+/// coordinates.
+pub(crate) async fn read_points<R>(
+    reader: &mut R,
+    rows: usize,
+) -> Result<Vec<Point>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let x: Vec<f64> = read_primitive(reader, rows).await?;
+    let y: Vec<f64> = read_primitive(reader, rows).await?;
+    Ok(x.into_iter().zip(y).map(|(x, y)| Point { x, y }).collect())
+}
+
+pub(crate) async fn write_points<W>(
+    writer: &mut W,
+    points: &[Point],
+) -> Result<usize>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let x: Vec<f64> = points.iter().map(|point| point.x).collect();
+    let y: Vec<f64> = points.iter().map(|point| point.y).collect();
+    let len = write_primitive(writer, &x).await?;
+    Ok(len + write_primitive(writer, &y).await?)
+}
+
+#[cfg(feature = "geo")]
+mod geo_types_impls {
+    use super::{
+        LineString, MultiLineString, MultiPolygon, Point, Polygon, Ring,
+    };
+
+    impl From<Point> for geo_types::Coord {
+        fn from(point: Point) -> Self {
+            geo_types::coord! { x: point.x, y: point.y }
+        }
+    }
+
+    impl From<geo_types::Coord> for Point {
+        fn from(coord: geo_types::Coord) -> Self {
+            Point {
+                x: coord.x,
+                y: coord.y,
+            }
+        }
+    }
+
+    impl From<Point> for geo_types::Point {
+        fn from(point: Point) -> Self {
+            geo_types::Point::new(point.x, point.y)
+        }
+    }
+
+    impl From<geo_types::Point> for Point {
+        fn from(point: geo_types::Point) -> Self {
+            point.0.into()
+        }
+    }
+
+    fn to_line(points: Vec<Point>) -> geo_types::LineString {
+        points.into_iter().map(geo_types::Coord::from).collect()
+    }
+
+    fn from_line(line: geo_types::LineString) -> Vec<Point> {
+        line.0.into_iter().map(Point::from).collect()
+    }
+
+    impl From<Ring> for geo_types::LineString {
+        fn from(ring: Ring) -> Self {
+            to_line(ring.0)
+        }
+    }
+
+    impl From<geo_types::LineString> for Ring {
+        fn from(line: geo_types::LineString) -> Self {
+            Ring(from_line(line))
+        }
+    }
+
+    impl From<LineString> for geo_types::LineString {
+        fn from(line: LineString) -> Self {
+            to_line(line.0)
+        }
+    }
+
+    impl From<geo_types::LineString> for LineString {
+        fn from(line: geo_types::LineString) -> Self {
+            LineString(from_line(line))
+        }
+    }
+
+    impl From<MultiLineString> for geo_types::MultiLineString {
+        fn from(lines: MultiLineString) -> Self {
+            geo_types::MultiLineString(
+                lines.0.into_iter().map(Into::into).collect(),
+            )
+        }
+    }
+
+    impl From<geo_types::MultiLineString> for MultiLineString {
+        fn from(lines: geo_types::MultiLineString) -> Self {
+            MultiLineString(lines.0.into_iter().map(Into::into).collect())
+        }
+    }
+
+    /// `geo_types` closes the rings by repeating their first point, if they
+    /// do not already.
+    impl From<Polygon> for geo_types::Polygon {
+        fn from(polygon: Polygon) -> Self {
+            let mut rings = polygon.0.into_iter().map(Into::into);
+            let exterior = rings
+                .next()
+                .unwrap_or_else(|| geo_types::LineString::new(Vec::new()));
+            geo_types::Polygon::new(exterior, rings.collect())
+        }
+    }
+
+    impl From<geo_types::Polygon> for Polygon {
+        fn from(polygon: geo_types::Polygon) -> Self {
+            let (exterior, interiors) = polygon.into_inner();
+            let mut rings = vec![Ring::from(exterior)];
+            rings.extend(interiors.into_iter().map(Ring::from));
+            Polygon(rings)
+        }
+    }
+
+    impl From<MultiPolygon> for geo_types::MultiPolygon {
+        fn from(polygons: MultiPolygon) -> Self {
+            geo_types::MultiPolygon(
+                polygons.0.into_iter().map(Into::into).collect(),
+            )
+        }
+    }
+
+    impl From<geo_types::MultiPolygon> for MultiPolygon {
+        fn from(polygons: geo_types::MultiPolygon) -> Self {
+            MultiPolygon(polygons.0.into_iter().map(Into::into).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use super::{Point, Polygon, Ring};
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_point_round_trip() -> Result<()> {
+        let mut bytes = Vec::new();
+        for coordinate in [1.0_f64, -2.5, 3.0, 4.0] {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        let mut reader = &bytes[..];
+        let column = Column::read(&DataType::Point, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.get::<Point>(1)?, Point { x: -2.5, y: 4.0 });
+        assert!(column.get::<(f64, f64)>(0).is_err());
+
+        let mut built = Column::new(&DataType::Point);
+        built.push(Point::from((1.0, 3.0)))?;
+        built.push(Point { x: -2.5, y: 4.0 })?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_polygon_round_trip() -> Result<()> {
+        let square = Ring(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 4.0, y: 0.0 },
+            Point { x: 4.0, y: 4.0 },
+            Point { x: 0.0, y: 4.0 },
+        ]);
+        let hole = Ring(vec![
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 2.0, y: 1.0 },
+            Point { x: 1.0, y: 2.0 },
+        ]);
+        let polygon = Polygon(vec![square.clone(), hole]);
+
+        let mut column = Column::new(&"Polygon".parse()?);
+        column.push(polygon.clone())?;
+        column.push(Polygon::default())?;
+        column.push(vec![square.clone()])?;
+        assert!(column.push(square.clone()).is_err());
+        assert_eq!(column.len(), 3);
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        let mut reader = &buf[..];
+        let read = Column::read(&DataType::Polygon, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(read, column);
+        assert_eq!(read.get::<Polygon>(0)?, polygon);
+        assert_eq!(read.get::<Polygon>(1)?, Polygon::default());
+        assert_eq!(read.get::<Vec<Ring>>(2)?, [square]);
+        assert!(read.get::<Ring>(0).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_geo_types() {
+        let polygon = Polygon(vec![Ring(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 0.0, y: 1.0 },
+        ])]);
+        let converted = geo_types::Polygon::from(polygon);
+        assert_eq!(converted.exterior().0.len(), 4);
+        assert!(converted.interiors().is_empty());
+        let back = Polygon::from(converted);
+        assert_eq!(back.0[0].0.len(), 4);
+        assert_eq!(back.0[0].0[3], Point { x: 0.0, y: 0.0 });
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use super::primitive::{read_primitive, write_primitive};
+use super::{Column, IntoColumn, NullableColumn, Prefix, Primitive};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// The only keys serialization version ClickHouse writes,
+/// `SharedDictionariesWithAdditionalKeys`.
+const KEYS_SERIALIZATION_VERSION: u64 = 1;
+
+const KEY_TYPE_MASK: u64 = 0xff;
+const NEED_GLOBAL_DICTIONARY_BIT: u64 = 1 << 8;
+const HAS_ADDITIONAL_KEYS_BIT: u64 = 1 << 9;
+const NEED_UPDATE_DICTIONARY_BIT: u64 = 1 << 10;
+
+/// A `LowCardinality(T)` column, kept in its dictionary encoded form.
+///
+/// Each row is a key into `dictionary`. For `LowCardinality(Nullable(T))`
+/// the dictionary is a `Nullable(T)` column whose first entry is `NULL`,
+/// which is how ClickHouse lays it out.
+///
+/// Reading a row through [`Column::get`] resolves the key, so callers that
+/// do not care about the encoding see plain values.
+#[derive(Debug, Clone)]
+pub struct LowCardinalityColumn {
+    dictionary: Box<Column>,
+    keys: Vec<u64>,
+    // dictionary row -> key, built lazily on the first push
+    index: HashMap<Vec<u8>, u64>,
+}
+
+impl PartialEq for LowCardinalityColumn {
+    fn eq(&self, other: &Self) -> bool {
+        self.dictionary == other.dictionary && self.keys == other.keys
+    }
+}
+
+impl LowCardinalityColumn {
+    pub fn new(dictionary: Column, keys: Vec<u64>) -> Result<Self> {
+        if !dictionary.data_type().can_be_inside_low_cardinality() {
+            return Err(DataTypeError::EncodeError(format!(
+                "{} cannot be inside LowCardinality",
+                dictionary.data_type()
+            )));
+        }
+        if let Some(key) = keys.iter().find(|k| **k >= dictionary.len() as u64)
+        {
+            return Err(DataTypeError::EncodeError(format!(
+                "key {key} is out of bounds for dictionary of {} rows",
+                dictionary.len()
+            )));
+        }
+        if let Column::Nullable(nullable) = &dictionary {
+            // only the first row can be NULL, the rest are written without
+            // their null map
+            if !nullable.is_null(0)
+                || (1..nullable.len()).any(|row| nullable.is_null(row))
+            {
+                return Err(DataTypeError::EncodeError(
+                    "a Nullable dictionary must hold NULL in its first row \
+                     and nowhere else"
+                        .into(),
+                ));
+            }
+        }
+        let mut probe = match dictionary.data_type() {
+            DataType::Nullable(inner) => Column::new(&inner),
+            data_type => Column::new(&data_type),
+        };
+        probe.push_default();
+        row_key(&probe, 0)?;
+        Ok(LowCardinalityColumn {
+            dictionary: Box::new(dictionary),
+            keys,
+            index: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn empty(inner: &DataType) -> LowCardinalityColumn {
+        let mut dictionary = Column::new(inner);
+        if let Column::Nullable(nullable) = &mut dictionary {
+            nullable.push_null();
+        }
+        LowCardinalityColumn {
+            dictionary: Box::new(dictionary),
+            keys: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// The distinct values referenced by [`keys`](Self::keys).
+    pub fn dictionary(&self) -> &Column {
+        &self.dictionary
+    }
+
+    pub fn keys(&self) -> &[u64] {
+        &self.keys
+    }
+
+    /// The dictionary and keys, without copying either.
+    #[cfg(any(feature = "arrow", feature = "polars"))]
+    pub(crate) fn into_parts(self) -> (Column, Vec<u64>) {
+        (*self.dictionary, self.keys)
+    }
+
+    /// The dictionary row holding the value of `row`.
+    pub fn key(&self, row: usize) -> Result<usize> {
+        self.keys.get(row).map(|key| *key as usize).ok_or(
+            DataTypeError::RowOutOfBounds {
+                row,
+                len: self.keys.len(),
+            },
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        self.dictionary.byte_size() + self.keys.len() * size_of::<u64>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn push<T: IntoColumn>(&mut self, value: T) -> Result<()> {
+        self.dictionary.push(value)?;
+        self.push_last_dictionary_row().inspect_err(|_| {
+            self.dictionary.truncate(self.dictionary.len() - 1);
+        })
+    }
+
+    pub(crate) fn push_default(&mut self) {
+        self.dictionary.push_default();
+        if self.push_last_dictionary_row().is_err() {
+            // a row without a key cannot be merged, but is still valid as
+            // a dictionary entry of its own
+            self.keys.push(self.dictionary.len() as u64 - 1);
+        }
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.keys.truncate(len);
+    }
+
+    /// Turns the row just appended to the dictionary into a key, dropping
+    /// it again if an equal value is already there.
+    fn push_last_dictionary_row(&mut self) -> Result<()> {
+        let last = self.dictionary.len() - 1;
+        if self.index.len() != last {
+            self.index.clear();
+            for row in 0..last {
+                self.index
+                    .entry(row_key(&self.dictionary, row)?)
+                    .or_insert(row as u64);
+            }
+        }
+        let key = row_key(&self.dictionary, last)?;
+        match self.index.get(&key) {
+            Some(existing) => {
+                self.keys.push(*existing);
+                self.dictionary.truncate(last);
+            }
+            None => {
+                self.index.insert(key, last as u64);
+                self.keys.push(last as u64);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn read_prefix<R>(reader: &mut R) -> Result<()>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let version = reader.read_u64_le().await?;
+        if version != KEYS_SERIALIZATION_VERSION {
+            return Err(DataTypeError::DecodeError(format!(
+                "unsupported LowCardinality keys serialization version {version}"
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn write_prefix<W>(writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_u64_le(KEYS_SERIALIZATION_VERSION).await?;
+        Ok(8)
+    }
+
+    pub(crate) async fn read<R>(
+        inner: &DataType,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<LowCardinalityColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        // empty columns, e.g. inside empty arrays, are not written at all
+        if rows == 0 {
+            return Ok(LowCardinalityColumn::empty(inner));
+        }
+
+        let flags = reader.read_u64_le().await?;
+        if flags & NEED_GLOBAL_DICTIONARY_BIT != 0 {
+            return Err(DataTypeError::DecodeError(
+                "LowCardinality global dictionaries are not supported".into(),
+            ));
+        }
+        if flags & HAS_ADDITIONAL_KEYS_BIT == 0 {
+            return Err(DataTypeError::DecodeError(
+                "LowCardinality column has no dictionary".into(),
+            ));
+        }
+
+        let dictionary_rows = reader.read_u64_le().await? as usize;
+        let dictionary = match inner {
+            DataType::Nullable(inner) => {
+                let inner = Box::pin(Column::read_data(
+                    inner,
+                    &Prefix::None,
+                    dictionary_rows,
+                    reader,
+                ))
+                .await?;
+                let mut nulls = vec![0; dictionary_rows];
+                if let Some(null) = nulls.first_mut() {
+                    *null = 1;
+                }
+                Column::Nullable(NullableColumn::new(nulls, inner)?)
+            }
+            _ => {
+                Box::pin(Column::read_data(
+                    inner,
+                    &Prefix::None,
+                    dictionary_rows,
+                    reader,
+                ))
+                .await?
+            }
+        };
+
+        let key_rows = reader.read_u64_le().await? as usize;
+        if key_rows != rows {
+            return Err(DataTypeError::DecodeError(format!(
+                "expected {rows} LowCardinality keys, got {key_rows}"
+            )));
+        }
+        let keys = match flags & KEY_TYPE_MASK {
+            0 => widen::<u8, R>(reader, rows).await?,
+            1 => widen::<u16, R>(reader, rows).await?,
+            2 => widen::<u32, R>(reader, rows).await?,
+            3 => read_primitive(reader, rows).await?,
+            key_type => {
+                return Err(DataTypeError::DecodeError(format!(
+                    "unknown LowCardinality key type {key_type}"
+                )))
+            }
+        };
+
+        LowCardinalityColumn::new(dictionary, keys)
+            .map_err(|e| DataTypeError::DecodeError(e.to_string()))
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        if self.keys.is_empty() {
+            return Ok(0);
+        }
+
+        let key_type = match self.dictionary.len() {
+            n if n <= 1 << 8 => 0,
+            n if n <= 1 << 16 => 1,
+            n if n as u64 <= 1 << 32 => 2,
+            _ => 3,
+        };
+        let flags =
+            key_type | HAS_ADDITIONAL_KEYS_BIT | NEED_UPDATE_DICTIONARY_BIT;
+        writer.write_u64_le(flags).await?;
+        writer.write_u64_le(self.dictionary.len() as u64).await?;
+        let mut len = 16;
+        len += match self.dictionary.as_ref() {
+            Column::Nullable(nullable) => {
+                Box::pin(nullable.inner().write_data(writer)).await?
+            }
+            dictionary => Box::pin(dictionary.write_data(writer)).await?,
+        };
+
+        writer.write_u64_le(self.keys.len() as u64).await?;
+        len += 8;
+        len += match key_type {
+            0 => narrow::<u8, W>(writer, &self.keys).await?,
+            1 => narrow::<u16, W>(writer, &self.keys).await?,
+            2 => narrow::<u32, W>(writer, &self.keys).await?,
+            _ => write_primitive(writer, &self.keys).await?,
+        };
+        Ok(len)
+    }
+}
+
+async fn widen<T, R>(reader: &mut R, rows: usize) -> Result<Vec<u64>>
+where
+    T: Primitive + Into<u64>,
+    R: AsyncRead + Unpin + Send,
+{
+    let keys: Vec<T> = read_primitive(reader, rows).await?;
+    Ok(keys.into_iter().map(Into::into).collect())
+}
+
+async fn narrow<T, W>(writer: &mut W, keys: &[u64]) -> Result<usize>
+where
+    T: Primitive + TryFrom<u64>,
+    W: AsyncWrite + Unpin + Send,
+{
+    let keys: Vec<T> = keys
+        .iter()
+        .map(|key| T::try_from(*key).unwrap_or(T::ZERO))
+        .collect();
+    write_primitive(writer, &keys).await
+}
+
+/// A byte representation of a dictionary row, equal for equal values.
+fn row_key(column: &Column, row: usize) -> Result<Vec<u8>> {
+    fn primitive<T: Primitive>(column: &Column, row: usize) -> Option<Vec<u8>> {
+        let data = T::as_slice(column)?;
+        let mut buf = vec![0_u8; T::SIZE];
+        T::encode_into(&data[row..=row], &mut buf);
+        Some(buf)
+    }
+
+    Ok(match column {
+        Column::String(data) => data[row].to_vec(),
+        Column::FixedString(column) => {
+            column.data[row * column.size..(row + 1) * column.size].to_vec()
+        }
+        Column::Bool(data) => vec![data[row] as u8],
+        Column::Nullable(nullable) if nullable.is_null(row) => vec![0],
+        Column::Nullable(nullable) => {
+            let mut key = vec![1];
+            key.extend(row_key(nullable.inner(), row)?);
+            key
+        }
+        _ => primitive::<u8>(column, row)
+            .or_else(|| primitive::<u16>(column, row))
+            .or_else(|| primitive::<u32>(column, row))
+            .or_else(|| primitive::<u64>(column, row))
+            .or_else(|| primitive::<u128>(column, row))
+            .or_else(|| primitive::<i8>(column, row))
+            .or_else(|| primitive::<i16>(column, row))
+            .or_else(|| primitive::<i32>(column, row))
+            .or_else(|| primitive::<i64>(column, row))
+            .or_else(|| primitive::<i128>(column, row))
+            .or_else(|| primitive::<f32>(column, row))
+            .or_else(|| primitive::<f64>(column, row))
+            .or_else(|| primitive::<Uuid>(column, row))
+            .or_else(|| primitive::<Ipv4Addr>(column, row))
+            .or_else(|| primitive::<Ipv6Addr>(column, row))
+            // a key shared by different values would merge them
+            .ok_or_else(|| {
+                DataTypeError::EncodeError(format!(
+                    "{} values cannot be dictionary keys",
+                    column.data_type()
+                ))
+            })?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, DataType, LowCardinalityColumn, NullableColumn};
+
+    #[tokio::test]
+    async fn test_low_cardinality_string() -> Result<()> {
+        let data_type: DataType = "LowCardinality(String)".parse()?;
+        let bytes = [
+            1, 0, 0, 0, 0, 0, 0, 0, // keys serialization version
+            0, 6, 0, 0, 0, 0, 0, 0, // UInt8 keys with additional keys
+            2, 0, 0, 0, 0, 0, 0, 0, // dictionary size
+            1, b'a', 1, b'b', // dictionary
+            3, 0, 0, 0, 0, 0, 0, 0, // rows
+            0, 1, 0, // keys
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+
+        assert_eq!(column.get::<&str>(0)?, "a");
+        assert_eq!(column.get::<String>(1)?, "b");
+        assert_eq!(column.get::<Option<&str>>(2)?, Some("a"));
+        let Column::LowCardinality(lc) = &column else {
+            panic!("expected low cardinality column");
+        };
+        assert_eq!(lc.keys(), &[0, 1, 0]);
+        assert_eq!(
+            lc.dictionary(),
+            &Column::String(vec!["a".into(), "b".into()])
+        );
+
+        let mut built = Column::new(&data_type);
+        for value in ["a", "b", "a"] {
+            built.push(value)?;
+        }
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_low_cardinality_nullable() -> Result<()> {
+        let data_type: DataType = "LowCardinality(Nullable(String))".parse()?;
+        let mut column = Column::new(&data_type);
+        column.push(Some("x"))?;
+        column.push(None::<&str>)?;
+        column.push(Some("x"))?;
+        column.push_default();
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        assert_eq!(
+            buf[16..24],
+            [2, 0, 0, 0, 0, 0, 0, 0],
+            "dictionary holds NULL and 'x'"
+        );
+
+        let mut reader = buf.as_slice();
+        let decoded = Column::read(&data_type, 4, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(decoded, column);
+        let values: Vec<Option<String>> =
+            (0..4).map(|i| decoded.get(i)).collect::<Result<_, _>>()?;
+        assert_eq!(values, [Some("x".into()), None, Some("x".into()), None]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_low_cardinality_bool() -> Result<()> {
+        for data_type in
+            ["LowCardinality(Bool)", "LowCardinality(Nullable(Bool))"]
+        {
+            let data_type: DataType = data_type.parse()?;
+            let mut column = Column::new(&data_type);
+            for value in [true, false, true] {
+                column.push(Some(value))?;
+            }
+
+            let mut buf = Vec::new();
+            column.write(&mut buf).await?;
+            let mut reader = buf.as_slice();
+            let decoded = Column::read(&data_type, 3, &mut reader).await?;
+            assert!(reader.is_empty());
+            let values: Vec<Option<bool>> =
+                (0..3).map(|i| decoded.get(i)).collect::<Result<_, _>>()?;
+            assert_eq!(values, [Some(true), Some(false), Some(true)]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_array_of_low_cardinality() -> Result<()> {
+        let data_type: DataType = "Array(LowCardinality(String))".parse()?;
+        let mut column = Column::new(&data_type);
+        column.push(vec!["a", "b"])?;
+        column.push(Vec::<&str>::new())?;
+        column.push(vec!["b"])?;
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        // the keys version comes before the array offsets
+        assert_eq!(buf[..8], [1, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut reader = buf.as_slice();
+        let decoded = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(decoded.get::<Vec<String>>(0)?, ["a", "b"]);
+        assert_eq!(decoded.get::<Vec<String>>(2)?, ["b"]);
+
+        let mut empty = Column::new(&data_type);
+        empty.push(Vec::<&str>::new())?;
+        let mut buf = Vec::new();
+        empty.write(&mut buf).await?;
+        assert_eq!(buf.len(), 16, "only the keys version and one offset");
+        Ok(())
+    }
+
+    #[test]
+    fn test_low_cardinality_rejects_composite_types() {
+        assert!("LowCardinality(Array(String))".parse::<DataType>().is_err());
+        assert!("Nullable(LowCardinality(String))"
+            .parse::<DataType>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_low_cardinality_rejects_misplaced_nulls() -> Result<()> {
+        let inner = Column::String(vec!["a".into(), "b".into()]);
+        for nulls in [vec![0, 0], vec![0, 1], vec![1, 1]] {
+            let dictionary =
+                Column::Nullable(NullableColumn::new(nulls, inner.clone())?);
+            assert!(LowCardinalityColumn::new(dictionary, vec![0]).is_err());
+        }
+        let empty = Column::Nullable(NullableColumn::new(
+            Vec::new(),
+            Column::String(Vec::new()),
+        )?);
+        assert!(LowCardinalityColumn::new(empty, Vec::new()).is_err());
+
+        let dictionary =
+            Column::Nullable(NullableColumn::new(vec![1, 0], inner)?);
+        let mut column = LowCardinalityColumn::new(dictionary, vec![1, 0])?;
+        column.push(Some("b"))?;
+        assert_eq!(column.keys(), [1, 0, 1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{ArrayColumn, Column, Prefix, TupleColumn};
+use crate::error::Result;
+use crate::types::DataType;
+
+/// A `Map(K, V)` column.
+///
+/// It is stored and serialized exactly like `Array(Tuple(K, V))`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapColumn {
+    pub(crate) entries: ArrayColumn,
+}
+
+impl MapColumn {
+    pub fn new(
+        offsets: Vec<u64>,
+        keys: Column,
+        values: Column,
+    ) -> Result<Self> {
+        let entries = TupleColumn::new(vec![(None, keys), (None, values)])?;
+        Ok(MapColumn {
+            entries: ArrayColumn::new(offsets, Column::Tuple(entries))?,
+        })
+    }
+
+    pub(crate) fn empty(key: &DataType, value: &DataType) -> MapColumn {
+        MapColumn {
+            entries: ArrayColumn::empty(&entry_type(key, value)),
+        }
+    }
+
+    /// The entries of all rows as an `Array(Tuple(K, V))` column.
+    pub fn entries(&self) -> &ArrayColumn {
+        &self.entries
+    }
+
+    pub fn keys(&self) -> &Column {
+        self.element(0)
+    }
+
+    pub fn values(&self) -> &Column {
+        self.element(1)
+    }
+
+    fn element(&self, index: usize) -> &Column {
+        match self.entries.inner() {
+            Column::Tuple(tuple) => &tuple.elements[index].1,
+            _ => unreachable!("map entries are always tuples"),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::Map(
+            Box::new(self.keys().data_type()),
+            Box::new(self.values().data_type()),
+        )
+    }
+
+    pub(crate) async fn read<R>(
+        key: &DataType,
+        value: &DataType,
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<MapColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let entry_type = entry_type(key, value);
+        let entries =
+            ArrayColumn::read(&entry_type, prefix, rows, reader).await?;
+        Ok(MapColumn { entries })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.entries.write(writer).await
+    }
+}
+
+fn entry_type(key: &DataType, value: &DataType) -> DataType {
+    DataType::Tuple(vec![(None, key.clone()), (None, value.clone())])
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use anyhow::Result;
+
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_map_with_low_cardinality_keys() -> Result<()> {
+        let data_type: DataType =
+            "Map(LowCardinality(String), String)".parse()?;
+        let bytes = [
+            1, 0, 0, 0, 0, 0, 0, 0, // keys serialization version
+            2, 0, 0, 0, 0, 0, 0, 0, // offsets
+            3, 0, 0, 0, 0, 0, 0, 0, //
+            0, 6, 0, 0, 0, 0, 0, 0, // UInt8 keys with additional keys
+            2, 0, 0, 0, 0, 0, 0, 0, // dictionary size
+            1, b'k', 1, b'v', // dictionary
+            3, 0, 0, 0, 0, 0, 0, 0, // keys rows
+            0, 1, 0, // keys
+            1, b'1', 1, b'2', 1, b'3', // values
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+
+        let first: BTreeMap<String, String> = column.get(0)?;
+        assert_eq!(
+            first,
+            BTreeMap::from([
+                ("k".into(), "1".into()),
+                ("v".into(), "2".into())
+            ])
+        );
+        let second: HashMap<&str, &str> = column.get(1)?;
+        assert_eq!(second, HashMap::from([("k", "3")]));
+        let pairs: Vec<(&str, &str)> = column.get(0)?;
+        assert_eq!(pairs, [("k", "1"), ("v", "2")]);
+
+        let mut built = Column::new(&data_type);
+        built.push(vec![("k", "1"), ("v", "2")])?;
+        built.push(BTreeMap::from([("k", "3")]))?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_push_type_mismatch() -> Result<()> {
+        let mut column = Column::new(&"Map(String, UInt64)".parse()?);
+        column.push(HashMap::from([("a", 1_u64)]))?;
+        assert!(column.push(HashMap::from([("b", "x")])).is_err());
+        assert_eq!(column.len(), 1);
+        let Column::Map(map) = &column else {
+            panic!("expected map column");
+        };
+        assert_eq!(map.keys().len(), 1);
+        assert_eq!(map.values().len(), 1);
+        Ok(())
+    }
+}
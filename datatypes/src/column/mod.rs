@@ -0,0 +1,1105 @@
+mod aggregate;
+mod array;
+#[cfg(feature = "arrow")]
+mod arrow;
+pub(crate) mod convert;
+mod enums;
+mod fixed_string;
+mod geo;
+mod low_cardinality;
+mod map;
+mod nested;
+mod nullable;
+#[cfg(feature = "polars")]
+mod polars;
+mod primitive;
+mod tuple;
+mod variant;
+
+pub use aggregate::AggregateFunctionColumn;
+pub use array::{ArrayColumn, ColumnSlice};
+pub use convert::{FromColumn, IntoColumn};
+pub use enums::EnumColumn;
+pub use fixed_string::FixedStringColumn;
+pub use geo::{
+    LineString, MultiLineString, MultiPolygon, Point, Polygon, Ring,
+};
+pub use low_cardinality::LowCardinalityColumn;
+pub use map::MapColumn;
+pub use nested::NestedColumn;
+pub use nullable::NullableColumn;
+pub use primitive::Primitive;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use geo::{read_points, write_points};
+use primitive::{read_primitive, write_primitive};
+use tokio::io::{AsyncRead, AsyncWrite};
+pub use tuple::TupleColumn;
+use uuid::Uuid;
+use variant::DynamicStructure;
+pub use variant::{DynamicColumn, VariantColumn, VariantValue};
+
+use crate::binary::{read_string, write_string};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// The decoded values of a single column in a native block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    UInt8(Vec<u8>),
+    UInt16(Vec<u16>),
+    UInt32(Vec<u32>),
+    UInt64(Vec<u64>),
+    UInt128(Vec<u128>),
+    Int8(Vec<i8>),
+    Int16(Vec<i16>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Int128(Vec<i128>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+    Bool(Vec<bool>),
+    /// Arbitrary bytes, which are only checked to be UTF-8 when read as
+    /// `&str` or `String`.
+    String(Vec<Bytes>),
+    FixedString(FixedStringColumn),
+    /// Days since 1970-01-01.
+    Date(Vec<u16>),
+    /// Days since 1970-01-01, may be negative.
+    Date32(Vec<i32>),
+    /// Seconds since the unix epoch.
+    DateTime {
+        tz: Option<String>,
+        data: Vec<u32>,
+    },
+    /// Ticks of `10^-precision` seconds since the unix epoch.
+    DateTime64 {
+        precision: u8,
+        tz: Option<String>,
+        data: Vec<i64>,
+    },
+    Uuid(Vec<Uuid>),
+    IPv4(Vec<Ipv4Addr>),
+    IPv6(Vec<Ipv6Addr>),
+    Enum8(EnumColumn<i8>),
+    Enum16(EnumColumn<i16>),
+    Nullable(NullableColumn),
+    Array(ArrayColumn),
+    Nested(NestedColumn),
+    LowCardinality(LowCardinalityColumn),
+    Tuple(TupleColumn),
+    Map(MapColumn),
+    /// JSON documents as text.
+    Json {
+        params: Vec<String>,
+        data: Vec<String>,
+    },
+    Variant(VariantColumn),
+    Dynamic(DynamicColumn),
+    Point(Vec<Point>),
+    Ring(ArrayColumn),
+    LineString(ArrayColumn),
+    MultiLineString(ArrayColumn),
+    Polygon(ArrayColumn),
+    MultiPolygon(ArrayColumn),
+    AggregateFunction(AggregateFunctionColumn),
+}
+
+/// Serialization state read from the prefix of a column, which decoding its
+/// data may depend on.
+#[derive(Debug, Default)]
+pub(crate) enum Prefix {
+    #[default]
+    None,
+    /// The prefixes of the inner columns, in order.
+    Inner(Vec<Prefix>),
+    /// The types held by a `Dynamic` column.
+    Dynamic(DynamicStructure),
+}
+
+static NO_PREFIX: Prefix = Prefix::None;
+
+impl Prefix {
+    /// The prefix of the inner column at `index`.
+    pub(crate) fn inner(&self, index: usize) -> &Prefix {
+        match self {
+            Prefix::Inner(inner) => inner.get(index).unwrap_or(&NO_PREFIX),
+            _ => &NO_PREFIX,
+        }
+    }
+}
+
+impl Column {
+    /// Creates an empty column of the given type.
+    pub fn new(data_type: &DataType) -> Column {
+        match data_type {
+            DataType::UInt8 => Column::UInt8(Vec::new()),
+            DataType::UInt16 => Column::UInt16(Vec::new()),
+            DataType::UInt32 => Column::UInt32(Vec::new()),
+            DataType::UInt64 => Column::UInt64(Vec::new()),
+            DataType::UInt128 => Column::UInt128(Vec::new()),
+            DataType::Int8 => Column::Int8(Vec::new()),
+            DataType::Int16 => Column::Int16(Vec::new()),
+            DataType::Int32 => Column::Int32(Vec::new()),
+            DataType::Int64 => Column::Int64(Vec::new()),
+            DataType::Int128 => Column::Int128(Vec::new()),
+            DataType::Float32 => Column::Float32(Vec::new()),
+            DataType::Float64 => Column::Float64(Vec::new()),
+            DataType::Bool => Column::Bool(Vec::new()),
+            DataType::String => Column::String(Vec::new()),
+            DataType::FixedString(size) => {
+                Column::FixedString(FixedStringColumn::empty(*size))
+            }
+            DataType::Date => Column::Date(Vec::new()),
+            DataType::Date32 => Column::Date32(Vec::new()),
+            DataType::DateTime(tz) => Column::DateTime {
+                tz: tz.clone(),
+                data: Vec::new(),
+            },
+            DataType::DateTime64(precision, tz) => Column::DateTime64 {
+                precision: *precision,
+                tz: tz.clone(),
+                data: Vec::new(),
+            },
+            DataType::Uuid => Column::Uuid(Vec::new()),
+            DataType::IPv4 => Column::IPv4(Vec::new()),
+            DataType::IPv6 => Column::IPv6(Vec::new()),
+            DataType::Enum8(variants) => {
+                Column::Enum8(EnumColumn::empty(variants))
+            }
+            DataType::Enum16(variants) => {
+                Column::Enum16(EnumColumn::empty(variants))
+            }
+            DataType::Nullable(inner) => {
+                Column::Nullable(NullableColumn::empty(inner))
+            }
+            DataType::Array(inner) => Column::Array(ArrayColumn::empty(inner)),
+            DataType::Nested(fields) => {
+                Column::Nested(NestedColumn::empty(fields))
+            }
+            DataType::LowCardinality(inner) => {
+                Column::LowCardinality(LowCardinalityColumn::empty(inner))
+            }
+            DataType::Tuple(elements) => {
+                Column::Tuple(TupleColumn::empty(elements))
+            }
+            DataType::Map(key, value) => {
+                Column::Map(MapColumn::empty(key, value))
+            }
+            DataType::Json(params) => Column::Json {
+                params: params.clone(),
+                data: Vec::new(),
+            },
+            DataType::Variant(types) => {
+                Column::Variant(VariantColumn::empty(types))
+            }
+            DataType::Dynamic(max_types) => {
+                Column::Dynamic(DynamicColumn::empty(*max_types))
+            }
+            DataType::Point => Column::Point(Vec::new()),
+            DataType::AggregateFunction(function, types) => {
+                Column::AggregateFunction(AggregateFunctionColumn::empty(
+                    function, types,
+                ))
+            }
+            // the values are plain values of the inner type
+            DataType::SimpleAggregateFunction(_, inner) => Column::new(inner),
+            DataType::Ring => {
+                Column::Ring(ArrayColumn::empty(&DataType::Point))
+            }
+            DataType::LineString => {
+                Column::LineString(ArrayColumn::empty(&DataType::Point))
+            }
+            DataType::MultiLineString => Column::MultiLineString(
+                ArrayColumn::empty(&DataType::LineString),
+            ),
+            DataType::Polygon => {
+                Column::Polygon(ArrayColumn::empty(&DataType::Ring))
+            }
+            DataType::MultiPolygon => {
+                Column::MultiPolygon(ArrayColumn::empty(&DataType::Polygon))
+            }
+        }
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Column::UInt8(_) => DataType::UInt8,
+            Column::UInt16(_) => DataType::UInt16,
+            Column::UInt32(_) => DataType::UInt32,
+            Column::UInt64(_) => DataType::UInt64,
+            Column::UInt128(_) => DataType::UInt128,
+            Column::Int8(_) => DataType::Int8,
+            Column::Int16(_) => DataType::Int16,
+            Column::Int32(_) => DataType::Int32,
+            Column::Int64(_) => DataType::Int64,
+            Column::Int128(_) => DataType::Int128,
+            Column::Float32(_) => DataType::Float32,
+            Column::Float64(_) => DataType::Float64,
+            Column::Bool(_) => DataType::Bool,
+            Column::String(_) => DataType::String,
+            Column::FixedString(column) => DataType::FixedString(column.size()),
+            Column::Date(_) => DataType::Date,
+            Column::Date32(_) => DataType::Date32,
+            Column::DateTime { tz, .. } => DataType::DateTime(tz.clone()),
+            Column::DateTime64 { precision, tz, .. } => {
+                DataType::DateTime64(*precision, tz.clone())
+            }
+            Column::Uuid(_) => DataType::Uuid,
+            Column::IPv4(_) => DataType::IPv4,
+            Column::IPv6(_) => DataType::IPv6,
+            Column::Enum8(column) => {
+                DataType::Enum8(column.variants().to_vec())
+            }
+            Column::Enum16(column) => {
+                DataType::Enum16(column.variants().to_vec())
+            }
+            Column::Nullable(column) => {
+                DataType::Nullable(Box::new(column.inner().data_type()))
+            }
+            Column::Array(column) => {
+                DataType::Array(Box::new(column.inner().data_type()))
+            }
+            Column::Nested(column) => column.data_type(),
+            Column::LowCardinality(column) => DataType::LowCardinality(
+                Box::new(column.dictionary().data_type()),
+            ),
+            Column::Tuple(column) => column.data_type(),
+            Column::Map(column) => column.data_type(),
+            Column::Json { params, .. } => DataType::Json(params.clone()),
+            Column::Variant(column) => column.data_type(),
+            Column::Dynamic(column) => column.data_type(),
+            Column::Point(_) => DataType::Point,
+            Column::AggregateFunction(column) => column.data_type(),
+            Column::Ring(_) => DataType::Ring,
+            Column::LineString(_) => DataType::LineString,
+            Column::MultiLineString(_) => DataType::MultiLineString,
+            Column::Polygon(_) => DataType::Polygon,
+            Column::MultiPolygon(_) => DataType::MultiPolygon,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::UInt8(data) => data.len(),
+            Column::UInt16(data) => data.len(),
+            Column::UInt32(data) => data.len(),
+            Column::UInt64(data) => data.len(),
+            Column::UInt128(data) => data.len(),
+            Column::Int8(data) => data.len(),
+            Column::Int16(data) => data.len(),
+            Column::Int32(data) => data.len(),
+            Column::Int64(data) => data.len(),
+            Column::Int128(data) => data.len(),
+            Column::Float32(data) => data.len(),
+            Column::Float64(data) => data.len(),
+            Column::Bool(data) => data.len(),
+            Column::String(data) => data.len(),
+            Column::FixedString(column) => column.len(),
+            Column::Date(data) => data.len(),
+            Column::Date32(data) => data.len(),
+            Column::DateTime { data, .. } => data.len(),
+            Column::DateTime64 { data, .. } => data.len(),
+            Column::Uuid(data) => data.len(),
+            Column::IPv4(data) => data.len(),
+            Column::IPv6(data) => data.len(),
+            Column::Enum8(column) => column.len(),
+            Column::Enum16(column) => column.len(),
+            Column::Nullable(column) => column.len(),
+            Column::Array(column) => column.len(),
+            Column::Nested(column) => column.len(),
+            Column::LowCardinality(column) => column.len(),
+            Column::Tuple(column) => column.len(),
+            Column::Map(column) => column.len(),
+            Column::Json { data, .. } => data.len(),
+            Column::Variant(column) => column.len(),
+            Column::Dynamic(column) => column.len(),
+            Column::Point(data) => data.len(),
+            Column::AggregateFunction(column) => column.len(),
+            Column::Ring(column) => column.len(),
+            Column::LineString(column) => column.len(),
+            Column::MultiLineString(column) => column.len(),
+            Column::Polygon(column) => column.len(),
+            Column::MultiPolygon(column) => column.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// About how many bytes the column takes when sent, counting values and
+    /// offsets but not the small prefixes some types have.
+    pub fn byte_size(&self) -> usize {
+        fn fixed<T>(data: &[T]) -> usize {
+            size_of_val(data)
+        }
+        fn array(column: &ArrayColumn) -> usize {
+            fixed(&column.offsets) + column.inner.byte_size()
+        }
+        match self {
+            Column::UInt8(data) => fixed(data),
+            Column::UInt16(data) => fixed(data),
+            Column::UInt32(data) => fixed(data),
+            Column::UInt64(data) => fixed(data),
+            Column::UInt128(data) => fixed(data),
+            Column::Int8(data) => fixed(data),
+            Column::Int16(data) => fixed(data),
+            Column::Int32(data) => fixed(data),
+            Column::Int64(data) => fixed(data),
+            Column::Int128(data) => fixed(data),
+            Column::Float32(data) => fixed(data),
+            Column::Float64(data) => fixed(data),
+            Column::Bool(data) => fixed(data),
+            // a length of one byte covers strings of up to 127 bytes
+            Column::String(data) => data.iter().map(|s| s.len() + 1).sum(),
+            Column::FixedString(column) => column.data.len(),
+            Column::Date(data) => fixed(data),
+            Column::Date32(data) => fixed(data),
+            Column::DateTime { data, .. } => fixed(data),
+            Column::DateTime64 { data, .. } => fixed(data),
+            Column::Uuid(data) => data.len() * 16,
+            Column::IPv4(data) => data.len() * 4,
+            Column::IPv6(data) => data.len() * 16,
+            Column::Enum8(column) => fixed(&column.data),
+            Column::Enum16(column) => fixed(&column.data),
+            Column::Nullable(column) => {
+                column.nulls.len() + column.inner.byte_size()
+            }
+            Column::Array(column) => array(column),
+            Column::Nested(column) => {
+                fixed(&column.offsets)
+                    + column
+                        .fields
+                        .iter()
+                        .map(|(_, field)| field.byte_size())
+                        .sum::<usize>()
+            }
+            Column::LowCardinality(column) => column.byte_size(),
+            Column::Tuple(column) => column
+                .elements
+                .iter()
+                .map(|(_, element)| element.byte_size())
+                .sum(),
+            Column::Map(column) => array(&column.entries),
+            Column::Json { data, .. } => data.iter().map(|s| s.len() + 1).sum(),
+            Column::Variant(column) => column.byte_size(),
+            Column::Dynamic(column) => column.byte_size(),
+            Column::Point(data) => data.len() * 16,
+            Column::AggregateFunction(column) => column.byte_size(),
+            Column::Ring(column)
+            | Column::LineString(column)
+            | Column::MultiLineString(column)
+            | Column::Polygon(column)
+            | Column::MultiPolygon(column) => array(column),
+        }
+    }
+
+    /// Converts the value at `row` into a Rust value.
+    ///
+    /// `LowCardinality` keys are resolved against their dictionary, so
+    /// values read the same as from a plain column. Likewise `Variant` and
+    /// `Dynamic` rows read as the value of whichever type they hold, see
+    /// [`VariantValue`] to find out which one that is.
+    pub fn get<'a, T: FromColumn<'a>>(&'a self, row: usize) -> Result<T> {
+        if row >= self.len() {
+            return Err(DataTypeError::RowOutOfBounds {
+                row,
+                len: self.len(),
+            });
+        }
+        match self {
+            Column::LowCardinality(column) => {
+                column.dictionary().get(column.key(row)?)
+            }
+            Column::Variant(column) => match column.value(row) {
+                Some((inner, row)) => inner.get(row),
+                None => T::from_column(self, row),
+            },
+            Column::Dynamic(column) => match column.value(row) {
+                Some((inner, row)) => inner.get(row),
+                None => T::from_column(self, row),
+            },
+            _ => T::from_column(self, row),
+        }
+    }
+
+    /// Borrows the values of a fixed-width column.
+    pub fn as_slice<T: Primitive>(&self) -> Option<&[T]> {
+        T::as_slice(self)
+    }
+
+    /// Appends a Rust value to the end of the column.
+    ///
+    /// `LowCardinality` columns add the value to their dictionary unless it
+    /// is already there. If the value does not fit the column type, the
+    /// column is left unchanged.
+    pub fn push<T: IntoColumn>(&mut self, value: T) -> Result<()> {
+        match self {
+            Column::LowCardinality(column) => column.push(value),
+            _ => value.append_to(self),
+        }
+    }
+
+    /// Appends the default value of the column type, which is what
+    /// ClickHouse stores under a `NULL`.
+    pub fn push_default(&mut self) {
+        match self {
+            Column::UInt8(data) => data.push(0),
+            Column::UInt16(data) => data.push(0),
+            Column::UInt32(data) => data.push(0),
+            Column::UInt64(data) => data.push(0),
+            Column::UInt128(data) => data.push(0),
+            Column::Int8(data) => data.push(0),
+            Column::Int16(data) => data.push(0),
+            Column::Int32(data) => data.push(0),
+            Column::Int64(data) => data.push(0),
+            Column::Int128(data) => data.push(0),
+            Column::Float32(data) => data.push(0.0),
+            Column::Float64(data) => data.push(0.0),
+            Column::Bool(data) => data.push(false),
+            Column::String(data) => data.push(Bytes::new()),
+            Column::FixedString(column) => column.push_default(),
+            Column::Date(data) => data.push(0),
+            Column::Date32(data) => data.push(0),
+            Column::DateTime { data, .. } => data.push(0),
+            Column::DateTime64 { data, .. } => data.push(0),
+            Column::Uuid(data) => data.push(Uuid::nil()),
+            Column::IPv4(data) => data.push(Ipv4Addr::UNSPECIFIED),
+            Column::IPv6(data) => data.push(Ipv6Addr::UNSPECIFIED),
+            Column::Enum8(column) => column.push_default(),
+            Column::Enum16(column) => column.push_default(),
+            Column::Nullable(column) => column.push_null(),
+            Column::Array(column) => column.push_default(),
+            Column::Nested(column) => column.push_default(),
+            Column::LowCardinality(column) => column.push_default(),
+            Column::Tuple(column) => column.push_default(),
+            Column::Map(column) => column.entries.push_default(),
+            Column::Json { data, .. } => data.push("{}".to_owned()),
+            Column::Variant(column) => column.push_null(),
+            Column::Dynamic(column) => column.push_null(),
+            Column::Point(data) => data.push(Point::default()),
+            Column::AggregateFunction(column) => column.push_default(),
+            Column::Ring(column) => column.push_default(),
+            Column::LineString(column) => column.push_default(),
+            Column::MultiLineString(column) => column.push_default(),
+            Column::Polygon(column) => column.push_default(),
+            Column::MultiPolygon(column) => column.push_default(),
+        }
+    }
+
+    /// Shortens the column to its first `len` rows.
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            Column::UInt8(data) => data.truncate(len),
+            Column::UInt16(data) => data.truncate(len),
+            Column::UInt32(data) => data.truncate(len),
+            Column::UInt64(data) => data.truncate(len),
+            Column::UInt128(data) => data.truncate(len),
+            Column::Int8(data) => data.truncate(len),
+            Column::Int16(data) => data.truncate(len),
+            Column::Int32(data) => data.truncate(len),
+            Column::Int64(data) => data.truncate(len),
+            Column::Int128(data) => data.truncate(len),
+            Column::Float32(data) => data.truncate(len),
+            Column::Float64(data) => data.truncate(len),
+            Column::Bool(data) => data.truncate(len),
+            Column::String(data) => data.truncate(len),
+            Column::FixedString(column) => column.truncate(len),
+            Column::Date(data) => data.truncate(len),
+            Column::Date32(data) => data.truncate(len),
+            Column::DateTime { data, .. } => data.truncate(len),
+            Column::DateTime64 { data, .. } => data.truncate(len),
+            Column::Uuid(data) => data.truncate(len),
+            Column::IPv4(data) => data.truncate(len),
+            Column::IPv6(data) => data.truncate(len),
+            Column::Enum8(column) => column.data.truncate(len),
+            Column::Enum16(column) => column.data.truncate(len),
+            Column::Nullable(column) => column.truncate(len),
+            Column::Array(column) => column.truncate(len),
+            Column::Nested(column) => column.truncate(len),
+            Column::LowCardinality(column) => column.truncate(len),
+            Column::Tuple(column) => column.truncate(len),
+            Column::Map(column) => column.entries.truncate(len),
+            Column::Json { data, .. } => data.truncate(len),
+            Column::Variant(column) => column.truncate(len),
+            Column::Dynamic(column) => column.truncate(len),
+            Column::Point(data) => data.truncate(len),
+            Column::AggregateFunction(column) => column.states.truncate(len),
+            Column::Ring(column) => column.truncate(len),
+            Column::LineString(column) => column.truncate(len),
+            Column::MultiLineString(column) => column.truncate(len),
+            Column::Polygon(column) => column.truncate(len),
+            Column::MultiPolygon(column) => column.truncate(len),
+        }
+    }
+
+    /// Decodes `rows` values of `data_type` in the native format.
+    pub async fn read<R>(
+        data_type: &DataType,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<Column>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let prefix = Column::read_prefix(data_type, reader).await?;
+        Column::read_data(data_type, &prefix, rows, reader).await
+    }
+
+    /// Encodes all values of the column in the native format.
+    pub async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let len = self.write_prefix(writer).await?;
+        Ok(len + self.write_data(writer).await?)
+    }
+
+    /// Reads the serialization state that precedes the data of a column,
+    /// such as the `LowCardinality` keys version or the types of a
+    /// `Dynamic` column.
+    ///
+    /// Nested types carry the prefixes of their inner types, in order.
+    pub(crate) async fn read_prefix<R>(
+        data_type: &DataType,
+        reader: &mut R,
+    ) -> Result<Prefix>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let prefix = match data_type {
+            DataType::Nullable(inner) | DataType::Array(inner) => {
+                let inner =
+                    Box::pin(Column::read_prefix(inner, reader)).await?;
+                Prefix::Inner(vec![inner])
+            }
+            DataType::Nested(fields) => {
+                let mut prefixes = Vec::with_capacity(fields.len());
+                for (_, data_type) in fields {
+                    prefixes.push(
+                        Box::pin(Column::read_prefix(data_type, reader))
+                            .await?,
+                    );
+                }
+                Prefix::Inner(prefixes)
+            }
+            DataType::Tuple(elements) => {
+                let mut prefixes = Vec::with_capacity(elements.len());
+                for (_, data_type) in elements {
+                    prefixes.push(
+                        Box::pin(Column::read_prefix(data_type, reader))
+                            .await?,
+                    );
+                }
+                Prefix::Inner(prefixes)
+            }
+            DataType::Map(key, value) => {
+                let key = Box::pin(Column::read_prefix(key, reader)).await?;
+                let value =
+                    Box::pin(Column::read_prefix(value, reader)).await?;
+                Prefix::Inner(vec![key, value])
+            }
+            DataType::LowCardinality(_) => {
+                LowCardinalityColumn::read_prefix(reader).await?;
+                Prefix::None
+            }
+            DataType::Variant(types) => {
+                VariantColumn::read_prefix(types, reader).await?
+            }
+            DataType::Dynamic(_) => {
+                Prefix::Dynamic(DynamicColumn::read_prefix(reader).await?)
+            }
+            DataType::SimpleAggregateFunction(_, inner) => {
+                Box::pin(Column::read_prefix(inner, reader)).await?
+            }
+            _ => Prefix::None,
+        };
+        Ok(prefix)
+    }
+
+    pub(crate) async fn write_prefix<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        match self {
+            Column::Nullable(column) => {
+                Box::pin(column.inner().write_prefix(writer)).await
+            }
+            Column::Array(column) => {
+                Box::pin(column.inner().write_prefix(writer)).await
+            }
+            Column::Nested(column) => {
+                let mut len = 0;
+                for (_, field) in column.fields() {
+                    len += Box::pin(field.write_prefix(writer)).await?;
+                }
+                Ok(len)
+            }
+            Column::Tuple(column) => {
+                let mut len = 0;
+                for (_, element) in column.elements() {
+                    len += Box::pin(element.write_prefix(writer)).await?;
+                }
+                Ok(len)
+            }
+            Column::Map(column) => {
+                Box::pin(column.entries.inner().write_prefix(writer)).await
+            }
+            Column::LowCardinality(_) => {
+                LowCardinalityColumn::write_prefix(writer).await
+            }
+            Column::Variant(column) => column.write_prefix(writer).await,
+            Column::Dynamic(column) => column.write_prefix(writer).await,
+            _ => Ok(0),
+        }
+    }
+
+    pub(crate) async fn read_data<R>(
+        data_type: &DataType,
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<Column>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let column = match data_type {
+            DataType::UInt8 => {
+                Column::UInt8(read_primitive(reader, rows).await?)
+            }
+            DataType::UInt16 => {
+                Column::UInt16(read_primitive(reader, rows).await?)
+            }
+            DataType::UInt32 => {
+                Column::UInt32(read_primitive(reader, rows).await?)
+            }
+            DataType::UInt64 => {
+                Column::UInt64(read_primitive(reader, rows).await?)
+            }
+            DataType::UInt128 => {
+                Column::UInt128(read_primitive(reader, rows).await?)
+            }
+            DataType::Int8 => Column::Int8(read_primitive(reader, rows).await?),
+            DataType::Int16 => {
+                Column::Int16(read_primitive(reader, rows).await?)
+            }
+            DataType::Int32 => {
+                Column::Int32(read_primitive(reader, rows).await?)
+            }
+            DataType::Int64 => {
+                Column::Int64(read_primitive(reader, rows).await?)
+            }
+            DataType::Int128 => {
+                Column::Int128(read_primitive(reader, rows).await?)
+            }
+            DataType::Float32 => {
+                Column::Float32(read_primitive(reader, rows).await?)
+            }
+            DataType::Float64 => {
+                Column::Float64(read_primitive(reader, rows).await?)
+            }
+            DataType::Bool => {
+                let data: Vec<u8> = read_primitive(reader, rows).await?;
+                Column::Bool(data.into_iter().map(|x| x != 0).collect())
+            }
+            DataType::String => {
+                let mut data = Vec::with_capacity(rows);
+                for _ in 0..rows {
+                    data.push(Bytes::from(read_string(reader).await?));
+                }
+                Column::String(data)
+            }
+            DataType::FixedString(size) => Column::FixedString(
+                FixedStringColumn::read(*size, rows, reader).await?,
+            ),
+            DataType::Date => Column::Date(read_primitive(reader, rows).await?),
+            DataType::Date32 => {
+                Column::Date32(read_primitive(reader, rows).await?)
+            }
+            DataType::DateTime(tz) => Column::DateTime {
+                tz: tz.clone(),
+                data: read_primitive(reader, rows).await?,
+            },
+            DataType::DateTime64(precision, tz) => Column::DateTime64 {
+                precision: *precision,
+                tz: tz.clone(),
+                data: read_primitive(reader, rows).await?,
+            },
+            DataType::Uuid => Column::Uuid(read_primitive(reader, rows).await?),
+            DataType::IPv4 => Column::IPv4(read_primitive(reader, rows).await?),
+            DataType::IPv6 => Column::IPv6(read_primitive(reader, rows).await?),
+            DataType::Enum8(variants) => {
+                Column::Enum8(EnumColumn::read(variants, rows, reader).await?)
+            }
+            DataType::Enum16(variants) => {
+                Column::Enum16(EnumColumn::read(variants, rows, reader).await?)
+            }
+            DataType::Nullable(inner) => Column::Nullable(
+                NullableColumn::read(inner, prefix.inner(0), rows, reader)
+                    .await?,
+            ),
+            DataType::Array(inner) => Column::Array(
+                ArrayColumn::read(inner, prefix.inner(0), rows, reader).await?,
+            ),
+            DataType::Nested(fields) => Column::Nested(
+                NestedColumn::read(fields, prefix, rows, reader).await?,
+            ),
+            DataType::LowCardinality(inner) => Column::LowCardinality(
+                LowCardinalityColumn::read(inner, rows, reader).await?,
+            ),
+            DataType::Tuple(elements) => Column::Tuple(
+                TupleColumn::read(elements, prefix, rows, reader).await?,
+            ),
+            DataType::Map(key, value) => Column::Map(
+                MapColumn::read(key, value, prefix, rows, reader).await?,
+            ),
+            DataType::Json(params) => {
+                let mut data = Vec::with_capacity(rows);
+                for _ in 0..rows {
+                    let value = read_string(reader).await?;
+                    data.push(
+                        String::from_utf8(value).map_err(|e| e.utf8_error())?,
+                    );
+                }
+                Column::Json {
+                    params: params.clone(),
+                    data,
+                }
+            }
+            DataType::Variant(types) => Column::Variant(
+                VariantColumn::read(types, prefix, rows, reader).await?,
+            ),
+            DataType::Dynamic(max_types) => Column::Dynamic(
+                DynamicColumn::read(*max_types, prefix, rows, reader).await?,
+            ),
+            DataType::Point => Column::Point(read_points(reader, rows).await?),
+            DataType::AggregateFunction(function, types) => {
+                Column::AggregateFunction(
+                    AggregateFunctionColumn::read(
+                        function, types, rows, reader,
+                    )
+                    .await?,
+                )
+            }
+            DataType::SimpleAggregateFunction(_, inner) => {
+                Box::pin(Column::read_data(inner, prefix, rows, reader)).await?
+            }
+            DataType::Ring => Column::Ring(
+                ArrayColumn::read(
+                    &DataType::Point,
+                    prefix.inner(0),
+                    rows,
+                    reader,
+                )
+                .await?,
+            ),
+            DataType::LineString => Column::LineString(
+                ArrayColumn::read(
+                    &DataType::Point,
+                    prefix.inner(0),
+                    rows,
+                    reader,
+                )
+                .await?,
+            ),
+            DataType::MultiLineString => Column::MultiLineString(
+                ArrayColumn::read(
+                    &DataType::LineString,
+                    prefix.inner(0),
+                    rows,
+                    reader,
+                )
+                .await?,
+            ),
+            DataType::Polygon => Column::Polygon(
+                ArrayColumn::read(
+                    &DataType::Ring,
+                    prefix.inner(0),
+                    rows,
+                    reader,
+                )
+                .await?,
+            ),
+            DataType::MultiPolygon => Column::MultiPolygon(
+                ArrayColumn::read(
+                    &DataType::Polygon,
+                    prefix.inner(0),
+                    rows,
+                    reader,
+                )
+                .await?,
+            ),
+        };
+        Ok(column)
+    }
+
+    pub(crate) async fn write_data<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        match self {
+            Column::UInt8(data) => write_primitive(writer, data).await,
+            Column::UInt16(data) => write_primitive(writer, data).await,
+            Column::UInt32(data) => write_primitive(writer, data).await,
+            Column::UInt64(data) => write_primitive(writer, data).await,
+            Column::UInt128(data) => write_primitive(writer, data).await,
+            Column::Int8(data) => write_primitive(writer, data).await,
+            Column::Int16(data) => write_primitive(writer, data).await,
+            Column::Int32(data) => write_primitive(writer, data).await,
+            Column::Int64(data) => write_primitive(writer, data).await,
+            Column::Int128(data) => write_primitive(writer, data).await,
+            Column::Float32(data) => write_primitive(writer, data).await,
+            Column::Float64(data) => write_primitive(writer, data).await,
+            Column::Bool(data) => {
+                let data: Vec<u8> = data.iter().map(|x| *x as u8).collect();
+                write_primitive(writer, &data).await
+            }
+            Column::String(data) => {
+                let mut len = 0;
+                for x in data {
+                    len += write_string(writer, x).await?;
+                }
+                Ok(len)
+            }
+            Column::FixedString(column) => column.write(writer).await,
+            Column::Date(data) => write_primitive(writer, data).await,
+            Column::Date32(data) => write_primitive(writer, data).await,
+            Column::DateTime { data, .. } => {
+                write_primitive(writer, data).await
+            }
+            Column::DateTime64 { data, .. } => {
+                write_primitive(writer, data).await
+            }
+            Column::Uuid(data) => write_primitive(writer, data).await,
+            Column::IPv4(data) => write_primitive(writer, data).await,
+            Column::IPv6(data) => write_primitive(writer, data).await,
+            Column::Enum8(column) => column.write(writer).await,
+            Column::Enum16(column) => column.write(writer).await,
+            Column::Nullable(column) => column.write(writer).await,
+            Column::Array(column) => column.write(writer).await,
+            Column::Nested(column) => column.write(writer).await,
+            Column::LowCardinality(column) => column.write(writer).await,
+            Column::Tuple(column) => column.write(writer).await,
+            Column::Map(column) => column.write(writer).await,
+            Column::Json { data, .. } => {
+                let mut len = 0;
+                for x in data {
+                    len += write_string(writer, x.as_bytes()).await?;
+                }
+                Ok(len)
+            }
+            Column::Variant(column) => column.write(writer).await,
+            Column::Dynamic(column) => column.write(writer).await,
+            Column::Point(data) => write_points(writer, data).await,
+            Column::AggregateFunction(column) => column.write(writer).await,
+            Column::Ring(column) => column.write(writer).await,
+            Column::LineString(column) => column.write(writer).await,
+            Column::MultiLineString(column) => column.write(writer).await,
+            Column::Polygon(column) => column.write(writer).await,
+            Column::MultiPolygon(column) => column.write(writer).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use anyhow::Result;
+    use bytes::Bytes;
+    use uuid::Uuid;
+
+    use crate::{Column, DataType, DataTypeError};
+
+    async fn round_trip(data_type: &str, bytes: &[u8]) -> Result<Column> {
+        let data_type: DataType = data_type.parse()?;
+        let mut reader = bytes;
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty(), "{} bytes left", reader.len());
+        assert_eq!(column.data_type(), data_type);
+
+        let mut buf = Vec::new();
+        let len = column.write(&mut buf).await?;
+        assert_eq!(len, bytes.len());
+        assert_eq!(buf, bytes);
+        Ok(column)
+    }
+
+    #[tokio::test]
+    async fn test_uint32_column() -> Result<()> {
+        let column = round_trip(
+            "UInt32",
+            &[0x01, 0, 0, 0, 0xff, 0, 0, 0, 0x00, 0x01, 0, 0],
+        )
+        .await?;
+        assert_eq!(column, Column::UInt32(vec![1, 255, 256]));
+        assert_eq!(column.get::<u32>(2)?, 256);
+        assert!(column.get::<u32>(3).is_err());
+        assert!(column.get::<i64>(0).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_string_column() -> Result<()> {
+        let column =
+            round_trip("String", &[0x02, 0x48, 0x69, 0x00, 0x01, 0x21]).await?;
+        assert_eq!(column.get::<&str>(0)?, "Hi");
+        assert_eq!(column.get::<String>(1)?, "");
+        assert_eq!(column.get::<Option<String>>(2)?, Some("!".to_owned()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_binary_string_column() -> Result<()> {
+        let column =
+            round_trip("String", &[0x02, 0xc3, 0x28, 0x00, 0x01, 0x00]).await?;
+        assert_eq!(column.get::<&[u8]>(0)?, &[0xc3, 0x28]);
+        assert_eq!(column.get::<Bytes>(2)?, &[0][..]);
+        assert!(matches!(
+            column.get::<&str>(0),
+            Err(DataTypeError::Utf8Error(_))
+        ));
+        assert_eq!(column.get::<&str>(1)?, "");
+
+        let mut built = Column::new(&DataType::String);
+        built.push(Bytes::from_static(&[0xc3, 0x28]))?;
+        assert_eq!(built.get::<&[u8]>(0)?, &[0xc3, 0x28]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_column() -> Result<()> {
+        let column = round_trip(
+            "JSON",
+            &[
+                7, b'{', b'"', b'a', b'"', b':', b'1', b'}', //
+                2, b'{', b'}', //
+                2, b'{', b'}',
+            ],
+        )
+        .await?;
+        assert_eq!(column.get::<&str>(0)?, r#"{"a":1}"#);
+        assert!(column.get::<u8>(1).is_err());
+
+        let mut built = Column::new(&DataType::Json(Vec::new()));
+        built.push(r#"{"a":1}"#)?;
+        built.push_default();
+        built.push("{}".to_owned())?;
+        assert_eq!(built, column);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_values() -> Result<()> {
+        use serde_json::{json, Value};
+
+        let mut column = Column::new(&"Array(JSON)".parse()?);
+        column.push(vec![json!({"a": [1, 2]}), json!(null)])?;
+        assert_eq!(
+            column.get::<Vec<Value>>(0)?,
+            vec![json!({"a": [1, 2]}), json!(null)]
+        );
+        assert_eq!(column.get::<Vec<&str>>(0)?, vec![r#"{"a":[1,2]}"#, "null"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_datetime64_column() -> Result<()> {
+        let column = round_trip(
+            "DateTime64(3, 'UTC')",
+            &[
+                0xe8, 0x03, 0, 0, 0, 0, 0, 0, // 1000
+                0, 0, 0, 0, 0, 0, 0, 0, // 0
+                0x18, 0xfc, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // -1000
+            ],
+        )
+        .await?;
+        assert_eq!(column.get::<i64>(2)?, -1000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uuid_column() -> Result<()> {
+        // toUUID('61f0c404-5cb3-11e7-907b-a6006ad3dba0'), the nil UUID and
+        // toUUID('00000000-0000-0001-0000-000000000002')
+        let column = round_trip(
+            "UUID",
+            &[
+                0xe7, 0x11, 0xb3, 0x5c, 0x04, 0xc4, 0xf0, 0x61, //
+                0xa0, 0xdb, 0xd3, 0x6a, 0x00, 0xa6, 0x7b, 0x90, //
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+                1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, //
+            ],
+        )
+        .await?;
+        assert_eq!(
+            column.get::<Uuid>(0)?,
+            "61f0c404-5cb3-11e7-907b-a6006ad3dba0".parse::<Uuid>()?
+        );
+        assert!(column.get::<Uuid>(1)?.is_nil());
+        assert_eq!(
+            column.get::<Uuid>(2)?,
+            "00000000-0000-0001-0000-000000000002".parse::<Uuid>()?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ip_columns() -> Result<()> {
+        // toIPv4('116.106.34.242'), '0.0.0.0', '1.2.3.4'
+        let column = round_trip(
+            "IPv4",
+            &[0xf2, 0x22, 0x6a, 0x74, 0, 0, 0, 0, 4, 3, 2, 1],
+        )
+        .await?;
+        assert_eq!(
+            column.as_slice::<Ipv4Addr>(),
+            Some(
+                &[
+                    Ipv4Addr::new(116, 106, 34, 242),
+                    Ipv4Addr::UNSPECIFIED,
+                    Ipv4Addr::new(1, 2, 3, 4),
+                ][..]
+            )
+        );
+
+        // toIPv6('2001:44c8:129:2632:33:0:252:2'), '::', '::1'
+        let column = round_trip(
+            "IPv6",
+            &[
+                0x20, 0x01, 0x44, 0xc8, 0x01, 0x29, 0x26, 0x32, //
+                0x00, 0x33, 0x00, 0x00, 0x02, 0x52, 0x00, 0x02, //
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, //
+            ],
+        )
+        .await?;
+        assert_eq!(
+            column.get::<Ipv6Addr>(0)?,
+            "2001:44c8:129:2632:33:0:252:2".parse::<Ipv6Addr>()?
+        );
+        assert_eq!(column.get::<Ipv6Addr>(2)?, Ipv6Addr::LOCALHOST);
+
+        let mut built = Column::new(&DataType::IPv6);
+        built.push(Ipv6Addr::LOCALHOST)?;
+        assert!(built.push(Ipv4Addr::LOCALHOST).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_size() -> Result<()> {
+        // the size of the values as sent, for types without a prefix
+        let column = Column::UInt32(vec![1, 2, 3]);
+        assert_eq!(column.byte_size(), 12);
+        let column = Column::String(vec!["a".into(), "bc".into(), "".into()]);
+        assert_eq!(column.byte_size(), 6);
+
+        let mut column = Column::new(&"Array(Nullable(UInt8))".parse()?);
+        column.push(vec![Some(1_u8), None])?;
+        assert_eq!(column.byte_size(), 8 + 2 + 2);
+        Ok(())
+    }
+}
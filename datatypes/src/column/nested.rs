@@ -0,0 +1,228 @@
+use std::ops::Range;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::array::{check_offsets, offsets_range};
+use super::primitive::{read_primitive, write_primitive};
+use super::{ArrayColumn, Column, Prefix};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// A `Nested(name T, ...)` column: arrays of equal length per row that share
+/// their offsets.
+///
+/// It is serialized like `Array(Tuple(T, ...))`. Tables store each field as
+/// its own `name.field Array(T)` column, which is what [`to_arrays`]
+/// produces for inserts.
+///
+/// [`to_arrays`]: NestedColumn::to_arrays
+#[derive(Debug, Clone, PartialEq)]
+pub struct NestedColumn {
+    pub(crate) offsets: Vec<u64>,
+    pub(crate) fields: Vec<(String, Column)>,
+}
+
+impl NestedColumn {
+    pub fn new(
+        offsets: Vec<u64>,
+        fields: Vec<(String, Column)>,
+    ) -> Result<NestedColumn> {
+        if fields.is_empty() {
+            return Err(DataTypeError::EncodeError(
+                "nested column must have at least one field".into(),
+            ));
+        }
+        for (name, column) in &fields {
+            check_offsets(&offsets, column.len()).map_err(|e| {
+                DataTypeError::EncodeError(format!("nested field {name}: {e}"))
+            })?;
+        }
+        Ok(NestedColumn { offsets, fields })
+    }
+
+    /// Groups `name.field` array columns with identical offsets.
+    pub fn from_arrays(
+        arrays: Vec<(String, ArrayColumn)>,
+    ) -> Result<NestedColumn> {
+        let offsets = match arrays.first() {
+            Some((_, array)) => array.offsets.clone(),
+            None => Vec::new(),
+        };
+        let mut fields = Vec::with_capacity(arrays.len());
+        for (name, array) in arrays {
+            if array.offsets != offsets {
+                return Err(DataTypeError::EncodeError(format!(
+                    "nested field {name} has different array sizes"
+                )));
+            }
+            fields.push((name, *array.inner));
+        }
+        NestedColumn::new(offsets, fields)
+    }
+
+    pub(crate) fn empty(fields: &[(String, DataType)]) -> NestedColumn {
+        NestedColumn {
+            offsets: Vec::new(),
+            fields: fields
+                .iter()
+                .map(|(name, data_type)| (name.clone(), Column::new(data_type)))
+                .collect(),
+        }
+    }
+
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    pub fn fields(&self) -> &[(String, Column)] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Column> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, column)| column)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The range of every field holding the elements of `row`.
+    pub fn range(&self, row: usize) -> Result<Range<usize>> {
+        offsets_range(&self.offsets, row)
+    }
+
+    /// Splits the column into one array column per field.
+    pub fn to_arrays(&self) -> Vec<(String, ArrayColumn)> {
+        self.fields
+            .iter()
+            .map(|(name, column)| {
+                let array = ArrayColumn {
+                    offsets: self.offsets.clone(),
+                    inner: Box::new(column.clone()),
+                };
+                (name.clone(), array)
+            })
+            .collect()
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::Nested(
+            self.fields
+                .iter()
+                .map(|(name, column)| (name.clone(), column.data_type()))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn push_default(&mut self) {
+        self.offsets.push(self.offsets.last().copied().unwrap_or(0));
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.offsets.truncate(len);
+        let end = self.offsets.last().copied().unwrap_or(0) as usize;
+        for (_, column) in &mut self.fields {
+            column.truncate(end);
+        }
+    }
+
+    pub(crate) async fn read<R>(
+        fields: &[(String, DataType)],
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<NestedColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let offsets: Vec<u64> = read_primitive(reader, rows).await?;
+        let total = offsets.last().copied().unwrap_or(0) as usize;
+        check_offsets(&offsets, total).map_err(DataTypeError::DecodeError)?;
+        let mut columns = Vec::with_capacity(fields.len());
+        for (i, (name, data_type)) in fields.iter().enumerate() {
+            let prefix = prefix.inner(i);
+            let column =
+                Box::pin(Column::read_data(data_type, prefix, total, reader))
+                    .await?;
+            columns.push((name.clone(), column));
+        }
+        Ok(NestedColumn {
+            offsets,
+            fields: columns,
+        })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut len = write_primitive(writer, &self.offsets).await?;
+        for (_, column) in &self.fields {
+            len += Box::pin(column.write_data(writer)).await?;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{ArrayColumn, Column, DataType, NestedColumn};
+
+    #[tokio::test]
+    async fn test_nested_round_trip() -> Result<()> {
+        let data_type: DataType = "Nested(id UInt8, name String)".parse()?;
+        // [(1, 'a'), (2, 'b')], []
+        let bytes = [
+            2, 0, 0, 0, 0, 0, 0, 0, // offsets
+            2, 0, 0, 0, 0, 0, 0, 0, //
+            1, 2, // id
+            1, b'a', 1, b'b', // name
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+
+        let Column::Nested(nested) = &column else {
+            panic!("expected nested column");
+        };
+        assert_eq!(nested.range(0)?, 0..2);
+        assert_eq!(nested.field("name").unwrap().get::<&str>(1)?, "b");
+
+        let arrays = nested.to_arrays();
+        assert_eq!(arrays[0].0, "id");
+        assert_eq!(arrays[0].1.offsets(), &[2, 2]);
+        assert_eq!(
+            Column::Array(arrays[1].1.clone()).get::<Vec<String>>(0)?,
+            vec!["a", "b"]
+        );
+        assert_eq!(&NestedColumn::from_arrays(arrays)?, nested);
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_from_mismatched_arrays() -> Result<()> {
+        let ids = ArrayColumn::new(vec![1], Column::UInt8(vec![1]))?;
+        let names =
+            ArrayColumn::new(vec![2], Column::String(vec!["a".into(); 2]))?;
+        assert!(NestedColumn::from_arrays(vec![
+            ("id".into(), ids),
+            ("name".into(), names)
+        ])
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::primitive::{read_primitive, write_primitive};
+use super::{Column, Prefix};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// A `Nullable(T)` column.
+///
+/// On the wire it is a null map of one byte per row (`1` means `NULL`)
+/// followed by the full inner column, where `NULL` rows hold the default
+/// value of `T`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NullableColumn {
+    pub(crate) nulls: Vec<u8>,
+    pub(crate) inner: Box<Column>,
+}
+
+impl NullableColumn {
+    pub fn new(nulls: Vec<u8>, inner: Column) -> Result<NullableColumn> {
+        if nulls.len() != inner.len() {
+            return Err(DataTypeError::EncodeError(format!(
+                "null map has {} rows but inner column has {}",
+                nulls.len(),
+                inner.len()
+            )));
+        }
+        Ok(NullableColumn {
+            nulls,
+            inner: Box::new(inner),
+        })
+    }
+
+    pub(crate) fn empty(inner: &DataType) -> NullableColumn {
+        NullableColumn {
+            nulls: Vec::new(),
+            inner: Box::new(Column::new(inner)),
+        }
+    }
+
+    pub fn nulls(&self) -> &[u8] {
+        &self.nulls
+    }
+
+    pub fn inner(&self) -> &Column {
+        &self.inner
+    }
+
+    pub fn is_null(&self, row: usize) -> bool {
+        self.nulls.get(row).is_some_and(|x| *x != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nulls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nulls.is_empty()
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.nulls.truncate(len);
+        self.inner.truncate(len);
+    }
+
+    pub(crate) fn push_null(&mut self) {
+        self.inner.push_default();
+        self.nulls.push(1);
+    }
+
+    pub(crate) async fn read<R>(
+        inner: &DataType,
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<NullableColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let nulls = read_primitive(reader, rows).await?;
+        let inner =
+            Box::pin(Column::read_data(inner, prefix, rows, reader)).await?;
+        Ok(NullableColumn {
+            nulls,
+            inner: Box::new(inner),
+        })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let len = write_primitive(writer, &self.nulls).await?;
+        Ok(len + Box::pin(self.inner.write_data(writer)).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_nullable_string() -> Result<()> {
+        let data_type: DataType = "Nullable(String)".parse()?;
+        let bytes = [
+            0x00, 0x01, 0x00, // null map
+            0x01, 0x61, 0x00, 0x02, 0x62, 0x63, // "a", "", "bc"
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+
+        assert_eq!(column.get::<Option<&str>>(0)?, Some("a"));
+        assert_eq!(column.get::<Option<String>>(1)?, None);
+        assert_eq!(column.get::<Option<&str>>(2)?, Some("bc"));
+        assert!(column.get::<String>(1).is_err());
+
+        let mut built = Column::new(&data_type);
+        built.push(Some("a"))?;
+        built.push(None::<String>)?;
+        built.push(Some("bc".to_owned()))?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nullable_uint64() -> Result<()> {
+        let data_type: DataType = "Nullable(UInt64)".parse()?;
+        let mut column = Column::new(&data_type);
+        column.push(Some(42_u64))?;
+        column.push(None::<u64>)?;
+        assert!(column.push(Some("42")).is_err());
+        assert_eq!(column.len(), 2);
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        let mut reader = buf.as_slice();
+        let decoded = Column::read(&data_type, 2, &mut reader).await?;
+        assert_eq!(decoded.get::<Option<u64>>(0)?, Some(42));
+        assert_eq!(decoded.get::<Option<u64>>(1)?, None);
+        Ok(())
+    }
+}
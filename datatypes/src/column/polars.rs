@@ -0,0 +1,866 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use polars::chunked_array::builder::get_list_builder;
+use polars::prelude::{
+    BinaryChunked, BooleanChunked, Categories, ChunkedArray,
+    DataType as PolarsType, Float32Type, Float64Type, FrozenCategories,
+    IdxSize, Int128Type, Int16Type, Int32Chunked, Int32Type, Int64Chunked,
+    Int64Type, Int8Type, IntoSeries, NewChunkedArray, PlSmallStr,
+    PolarsNumericType, Series, StringChunked, StructChunked, TimeUnit,
+    TimeZone, UInt128Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use polars_arrow::bitmap::Bitmap;
+use uuid::Uuid;
+
+use super::{
+    ArrayColumn, Column, IntoColumn, MapColumn, NestedColumn, NullableColumn,
+    Point, TupleColumn,
+};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+use crate::value::Value;
+
+impl Column {
+    /// Converts the column into a Polars series named `name`.
+    ///
+    /// Numbers, `Date32` and `DateTime64` with a precision of 3, 6 or 9 are
+    /// moved without copying. The types map as follows:
+    ///
+    /// - `Nullable(T)` to `T` with nulls
+    /// - `LowCardinality(T)` to the values of `T`
+    /// - `Enum8` and `Enum16` to `Enum` with the same names
+    /// - `Array(T)` and the geo arrays to `List`, `Nested` to a `List` of
+    ///   `Struct`, `Map` to a `List` of `Struct` of `key` and `value`,
+    ///   `Tuple` to `Struct` and `Point` to a `Struct` of `x` and `y`
+    /// - `DateTime` and `DateTime64` to `Datetime` with their time zone, in
+    ///   milliseconds for precisions up to 3
+    /// - `String` to `Binary` whatever its values, or to `String` with
+    ///   [`Column::into_series_utf8`]
+    /// - `UUID`, `IPv4`, `IPv6` and `JSON` to `String`, `FixedString` and
+    ///   `AggregateFunction` states to `Binary`
+    ///
+    /// `Variant` and `Dynamic` columns cannot be converted.
+    pub fn into_series(self, name: &str) -> Result<Series> {
+        into_series(self, name.into(), None, false)
+    }
+
+    /// Like [`Column::into_series`], but converts `String` to `String`, and
+    /// `LowCardinality(String)` to `Categorical`, failing on values that are
+    /// not UTF-8.
+    pub fn into_series_utf8(self, name: &str) -> Result<Series> {
+        into_series(self, name.into(), None, true)
+    }
+
+    /// Converts a Polars series into a column of `data_type`, casting it
+    /// where its type differs from the one [`Column::into_series`]
+    /// produces. Integer casts fail rather than overflow.
+    ///
+    /// Series with nulls can only be converted to `Nullable` types.
+    pub fn from_series(
+        series: &Series,
+        data_type: &DataType,
+    ) -> Result<Column> {
+        match data_type {
+            DataType::Nullable(inner) => {
+                let nulls = series
+                    .is_null()
+                    .iter()
+                    .map(|x| u8::from(x == Some(true)))
+                    .collect();
+                let column = from_series_values(series, inner)?;
+                Ok(Column::Nullable(NullableColumn::new(nulls, column)?))
+            }
+            DataType::LowCardinality(inner) => {
+                let values = Column::from_series(series, inner)?;
+                let mut column = Column::new(data_type);
+                for row in 0..values.len() {
+                    column.push(values.get::<Value>(row)?)?;
+                }
+                Ok(column)
+            }
+            DataType::SimpleAggregateFunction(_, inner) => {
+                Column::from_series(series, inner)
+            }
+            _ if series.null_count() > 0 => Err(DataTypeError::EncodeError(
+                format!("{data_type} cannot hold NULL values"),
+            )),
+            _ => from_series_values(series, data_type),
+        }
+    }
+}
+
+fn unsupported(data_type: &DataType) -> DataTypeError {
+    DataTypeError::EncodeError(format!("{data_type} has no Polars counterpart"))
+}
+
+/// Converts a column, with `validity` marking the rows that are not null
+/// for the inner column of a `Nullable` and `utf8` asking for `String` as
+/// `String`.
+fn into_series(
+    column: Column,
+    name: PlSmallStr,
+    validity: Option<Bitmap>,
+    utf8: bool,
+) -> Result<Series> {
+    let len = column.len();
+    let series = match column {
+        Column::UInt8(data) => numeric::<UInt8Type>(name, data, validity),
+        Column::UInt16(data) => numeric::<UInt16Type>(name, data, validity),
+        Column::UInt32(data) => numeric::<UInt32Type>(name, data, validity),
+        Column::UInt64(data) => numeric::<UInt64Type>(name, data, validity),
+        Column::UInt128(data) => numeric::<UInt128Type>(name, data, validity),
+        Column::Int8(data) => numeric::<Int8Type>(name, data, validity),
+        Column::Int16(data) => numeric::<Int16Type>(name, data, validity),
+        Column::Int32(data) => numeric::<Int32Type>(name, data, validity),
+        Column::Int64(data) => numeric::<Int64Type>(name, data, validity),
+        Column::Int128(data) => numeric::<Int128Type>(name, data, validity),
+        Column::Float32(data) => numeric::<Float32Type>(name, data, validity),
+        Column::Float64(data) => numeric::<Float64Type>(name, data, validity),
+        Column::Bool(data) => BooleanChunked::from_slice(name, &data)
+            .with_validity(validity)
+            .into_series(),
+        Column::String(data) => strings(name, &data, validity, utf8)?,
+        Column::FixedString(column) => {
+            let values = column.data.chunks_exact(column.size.max(1));
+            BinaryChunked::from_iter_values(name, values)
+                .with_validity(validity)
+                .into_series()
+        }
+        Column::Date(data) => {
+            let data = data.into_iter().map(i32::from).collect();
+            Int32Chunked::from_vec_validity(name, data, validity)
+                .into_date()
+                .into_series()
+        }
+        Column::Date32(data) => {
+            Int32Chunked::from_vec_validity(name, data, validity)
+                .into_date()
+                .into_series()
+        }
+        Column::DateTime { tz, data } => {
+            let data = data.into_iter().map(|x| i64::from(x) * 1000).collect();
+            datetime(name, data, TimeUnit::Milliseconds, tz, validity)?
+        }
+        Column::DateTime64 {
+            precision,
+            tz,
+            mut data,
+        } => {
+            let (unit, factor) = time_unit(precision)?;
+            if factor > 1 {
+                for value in &mut data {
+                    *value = value.checked_mul(factor).ok_or_else(|| {
+                        DataTypeError::EncodeError(format!(
+                            "DateTime64({precision}) value {value} is out of \
+                             range for Polars"
+                        ))
+                    })?;
+                }
+            }
+            datetime(name, data, unit, tz, validity)?
+        }
+        Column::Uuid(data) => formatted(name, &data, validity),
+        Column::IPv4(data) => formatted(name, &data, validity),
+        Column::IPv6(data) => formatted(name, &data, validity),
+        Column::Enum8(column) => enums(
+            name,
+            column.variants().iter().map(|(name, _)| name.as_str()),
+            column.data.iter().map(|code| column.name(*code)),
+            validity,
+        )?,
+        Column::Enum16(column) => enums(
+            name,
+            column.variants().iter().map(|(name, _)| name.as_str()),
+            column.data.iter().map(|code| column.name(*code)),
+            validity,
+        )?,
+        Column::Nullable(column) => {
+            let validity = column.nulls.iter().map(|x| *x == 0).collect();
+            into_series(*column.inner, name, Some(validity), utf8)?
+        }
+        Column::Array(array)
+        | Column::Ring(array)
+        | Column::LineString(array)
+        | Column::MultiLineString(array)
+        | Column::Polygon(array)
+        | Column::MultiPolygon(array) => {
+            let inner =
+                into_series(*array.inner, PlSmallStr::EMPTY, None, utf8)?;
+            list(name, &array.offsets, &inner)?
+        }
+        Column::Nested(nested) => {
+            let len = nested.fields.first().map_or(0, |(_, c)| c.len());
+            let inner = structs(PlSmallStr::EMPTY, len, nested.fields, utf8)?;
+            list(name, &nested.offsets, &inner)?
+        }
+        Column::LowCardinality(column) => {
+            let (dictionary, keys) = column.into_parts();
+            let values = into_series(dictionary, name, None, utf8)?;
+            let keys = keys
+                .into_iter()
+                .map(IdxSize::try_from)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| {
+                    DataTypeError::EncodeError(
+                        "dictionary is too large for Polars".into(),
+                    )
+                })?;
+            let series = values.take_slice(&keys)?;
+            if series.dtype() == &PolarsType::String {
+                let categorical =
+                    PolarsType::from_categories(Categories::global());
+                series.strict_cast(&categorical)?
+            } else {
+                series
+            }
+        }
+        Column::Tuple(tuple) => structs(
+            name,
+            len,
+            tuple
+                .elements
+                .into_iter()
+                .enumerate()
+                .map(|(i, (name, c))| {
+                    (name.unwrap_or_else(|| (i + 1).to_string()), c)
+                }),
+            utf8,
+        )?,
+        Column::Map(map) => {
+            let len = map.entries.inner.len();
+            let Column::Tuple(entries) = *map.entries.inner else {
+                unreachable!("map entries are always tuples")
+            };
+            let names = ["key", "value"].map(String::from);
+            let entries = structs(
+                PlSmallStr::EMPTY,
+                len,
+                names
+                    .into_iter()
+                    .zip(entries.elements.into_iter().map(|(_, c)| c)),
+                utf8,
+            )?;
+            list(name, &map.entries.offsets, &entries)?
+        }
+        Column::Json { data, .. } => StringChunked::from_iter_values(
+            name,
+            data.iter().map(String::as_str),
+        )
+        .into_series(),
+        Column::Point(data) => structs(
+            name,
+            len,
+            [
+                (
+                    "x".to_owned(),
+                    Column::Float64(data.iter().map(|p| p.x).collect()),
+                ),
+                (
+                    "y".to_owned(),
+                    Column::Float64(data.iter().map(|p| p.y).collect()),
+                ),
+            ],
+            utf8,
+        )?,
+        Column::AggregateFunction(column) => BinaryChunked::from_iter_values(
+            name,
+            column.states.iter().map(|x| &x[..]),
+        )
+        .into_series(),
+        column @ (Column::Variant(_) | Column::Dynamic(_)) => {
+            return Err(unsupported(&column.data_type()))
+        }
+    };
+    Ok(series)
+}
+
+fn numeric<T: PolarsNumericType>(
+    name: PlSmallStr,
+    data: Vec<T::Native>,
+    validity: Option<Bitmap>,
+) -> Series {
+    ChunkedArray::<T>::from_vec_validity(name, data, validity).into_series()
+}
+
+/// `Binary`, as ClickHouse strings can hold any bytes, or `String` when
+/// asked for, which fails on the first value that is not UTF-8.
+fn strings(
+    name: PlSmallStr,
+    data: &[Bytes],
+    validity: Option<Bitmap>,
+    utf8: bool,
+) -> Result<Series> {
+    if !utf8 {
+        return Ok(BinaryChunked::from_iter_values(
+            name,
+            data.iter().map(|x| &x[..]),
+        )
+        .with_validity(validity)
+        .into_series());
+    }
+    let strings = data
+        .iter()
+        .map(|x| std::str::from_utf8(x))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(StringChunked::from_iter_values(name, strings.into_iter())
+        .with_validity(validity)
+        .into_series())
+}
+
+fn formatted<T: ToString>(
+    name: PlSmallStr,
+    data: &[T],
+    validity: Option<Bitmap>,
+) -> Series {
+    let strings = data.iter().map(ToString::to_string).collect::<Vec<_>>();
+    StringChunked::from_iter_values(name, strings.iter().map(String::as_str))
+        .with_validity(validity)
+        .into_series()
+}
+
+fn enums<'a>(
+    name: PlSmallStr,
+    variants: impl IntoIterator<Item = &'a str>,
+    names: impl Iterator<Item = Option<&'a str>>,
+    validity: Option<Bitmap>,
+) -> Result<Series> {
+    let categories = FrozenCategories::new(variants)?;
+    let names = names.map(|x| x.expect("enum codes are validated"));
+    let series = StringChunked::from_iter_values(name, names)
+        .with_validity(validity)
+        .into_series();
+    Ok(series.strict_cast(&PolarsType::from_frozen_categories(categories))?)
+}
+
+fn datetime(
+    name: PlSmallStr,
+    data: Vec<i64>,
+    unit: TimeUnit,
+    tz: Option<String>,
+    validity: Option<Bitmap>,
+) -> Result<Series> {
+    let tz = TimeZone::opt_try_new(tz)?;
+    Ok(Int64Chunked::from_vec_validity(name, data, validity)
+        .into_datetime(unit, tz)
+        .into_series())
+}
+
+/// The Polars unit for a `DateTime64` precision, and how many ticks of that
+/// unit make one tick of the precision. Polars has no unit of seconds.
+fn time_unit(precision: u8) -> Result<(TimeUnit, i64)> {
+    let (unit, digits) = match precision {
+        0..=3 => (TimeUnit::Milliseconds, 3),
+        4..=6 => (TimeUnit::Microseconds, 6),
+        7..=9 => (TimeUnit::Nanoseconds, 9),
+        _ => {
+            return Err(DataTypeError::EncodeError(format!(
+                "DateTime64({precision}) has no Polars counterpart"
+            )))
+        }
+    };
+    Ok((unit, 10_i64.pow((digits - precision).into())))
+}
+
+/// A list series of the rows of `inner` that `offsets` delimit.
+fn list(name: PlSmallStr, offsets: &[u64], inner: &Series) -> Result<Series> {
+    let mut builder =
+        get_list_builder(inner.dtype(), inner.len(), offsets.len(), name);
+    let mut start = 0;
+    for end in offsets {
+        builder.append_series(
+            &inner.slice(start as i64, (end - start) as usize),
+        )?;
+        start = *end;
+    }
+    Ok(builder.finish().into_series())
+}
+
+fn structs(
+    name: PlSmallStr,
+    len: usize,
+    columns: impl IntoIterator<Item = (String, Column)>,
+    utf8: bool,
+) -> Result<Series> {
+    let fields = columns
+        .into_iter()
+        .map(|(name, column)| into_series(column, name.into(), None, utf8))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(StructChunked::from_series(name, len, fields.iter())?.into_series())
+}
+
+/// The values of a numeric series, with the default under nulls.
+fn values<T: PolarsNumericType>(series: &Series) -> Result<Vec<T::Native>> {
+    let series = series.strict_cast(&T::get_static_dtype())?;
+    let values = series.unpack::<T>()?.iter();
+    Ok(values.map(Option::unwrap_or_default).collect())
+}
+
+/// The ticks of `unit` since the epoch, in UTC.
+fn timestamps(series: &Series, unit: TimeUnit) -> Result<Vec<i64>> {
+    let series = series.strict_cast(&PolarsType::Datetime(unit, None))?;
+    values::<Int64Type>(&series.to_physical_repr())
+}
+
+/// The bytes of each value of a string or binary series.
+fn binary_values(series: &Series) -> Result<Vec<Option<Bytes>>> {
+    let series = match series.dtype() {
+        PolarsType::Binary => series.clone(),
+        PolarsType::BinaryOffset => series.strict_cast(&PolarsType::Binary)?,
+        _ => series
+            .strict_cast(&PolarsType::String)?
+            .strict_cast(&PolarsType::Binary)?,
+    };
+    Ok(series
+        .binary()?
+        .iter()
+        .map(|x| x.map(Bytes::copy_from_slice))
+        .collect())
+}
+
+fn string_values(series: &Series) -> Result<Vec<Option<String>>> {
+    let series = series.strict_cast(&PolarsType::String)?;
+    Ok(series.str()?.iter().map(|x| x.map(str::to_owned)).collect())
+}
+
+fn out_of_range(
+    value: impl std::fmt::Display,
+    data_type: &DataType,
+) -> DataTypeError {
+    DataTypeError::EncodeError(format!(
+        "{value} is out of range for {data_type}"
+    ))
+}
+
+/// Parses each value of a string series, with `default` under nulls.
+fn parsed<T>(
+    series: &Series,
+    data_type: &DataType,
+    default: T,
+) -> Result<Vec<T>>
+where
+    T: std::str::FromStr + Copy,
+{
+    let series = series.strict_cast(&PolarsType::String)?;
+    series
+        .str()?
+        .iter()
+        .map(|x| match x {
+            Some(x) => x.parse().map_err(|_| out_of_range(x, data_type)),
+            None => Ok(default),
+        })
+        .collect()
+}
+
+/// An address from its 16 bytes, or the unspecified one under nulls.
+fn ipv6(bytes: Option<&[u8]>, data_type: &DataType) -> Result<Ipv6Addr> {
+    match bytes {
+        None => Ok(Ipv6Addr::UNSPECIFIED),
+        Some(bytes) => <[u8; 16]>::try_from(bytes)
+            .map(Ipv6Addr::from)
+            .map_err(|_| out_of_range(format!("{bytes:?}"), data_type)),
+    }
+}
+
+/// Builds a column of `data_type` by pushing each value, or the default
+/// under nulls.
+fn push_all<T: IntoColumn>(
+    data_type: &DataType,
+    values: impl IntoIterator<Item = Option<T>>,
+) -> Result<Column> {
+    let mut column = Column::new(data_type);
+    for value in values {
+        match value {
+            Some(value) => column.push(value)?,
+            None => column.push_default(),
+        }
+    }
+    Ok(column)
+}
+
+/// The offsets of a list series, relative to its values, and those values.
+fn list_parts(series: &Series) -> Result<(Vec<u64>, Series)> {
+    let series = match series.dtype() {
+        PolarsType::Array(inner, _) => {
+            series.strict_cast(&PolarsType::List(inner.clone()))?
+        }
+        _ => series.clone(),
+    };
+    let list = series.list()?.rechunk();
+    let offsets = list
+        .downcast_iter()
+        .next()
+        .map_or_else(|| vec![0], |array| array.offsets().to_vec());
+    let first = offsets.first().copied().unwrap_or(0);
+    let last = offsets.last().copied().unwrap_or(0);
+    let values = list.get_inner().slice(first, (last - first) as usize);
+    let ends = offsets.iter().skip(1).map(|x| (x - first) as u64).collect();
+    Ok((ends, values))
+}
+
+fn struct_columns<'a>(
+    series: &Series,
+    types: impl ExactSizeIterator<Item = &'a DataType>,
+) -> Result<Vec<Column>> {
+    let fields = series.struct_()?.fields_as_series();
+    if fields.len() != types.len() {
+        return Err(DataTypeError::EncodeError(format!(
+            "Polars struct has {} fields, expected {}",
+            fields.len(),
+            types.len()
+        )));
+    }
+    fields
+        .iter()
+        .zip(types)
+        .map(|(series, data_type)| Column::from_series(series, data_type))
+        .collect()
+}
+
+/// Converts a series to a column of a type other than `Nullable`, with the
+/// default value under any nulls.
+fn from_series_values(series: &Series, data_type: &DataType) -> Result<Column> {
+    let column = match data_type {
+        DataType::UInt8 => Column::UInt8(values::<UInt8Type>(series)?),
+        DataType::UInt16 => Column::UInt16(values::<UInt16Type>(series)?),
+        DataType::UInt32 => Column::UInt32(values::<UInt32Type>(series)?),
+        DataType::UInt64 => Column::UInt64(values::<UInt64Type>(series)?),
+        DataType::UInt128 => Column::UInt128(values::<UInt128Type>(series)?),
+        DataType::Int8 => Column::Int8(values::<Int8Type>(series)?),
+        DataType::Int16 => Column::Int16(values::<Int16Type>(series)?),
+        DataType::Int32 => Column::Int32(values::<Int32Type>(series)?),
+        DataType::Int64 => Column::Int64(values::<Int64Type>(series)?),
+        DataType::Int128 => Column::Int128(values::<Int128Type>(series)?),
+        DataType::Float32 => Column::Float32(values::<Float32Type>(series)?),
+        DataType::Float64 => Column::Float64(values::<Float64Type>(series)?),
+        DataType::Bool => {
+            let series = series.strict_cast(&PolarsType::Boolean)?;
+            let values = series.bool()?.iter();
+            Column::Bool(values.map(Option::unwrap_or_default).collect())
+        }
+        DataType::String => Column::String(
+            binary_values(series)?
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+        ),
+        DataType::FixedString(_) | DataType::AggregateFunction(_, _) => {
+            push_all(data_type, binary_values(series)?)?
+        }
+        DataType::Enum8(_) | DataType::Enum16(_) | DataType::Json(_) => {
+            push_all(data_type, string_values(series)?)?
+        }
+        DataType::Date => {
+            let series = series.strict_cast(&PolarsType::Date)?;
+            Column::Date(
+                values::<Int32Type>(&series.to_physical_repr())?
+                    .into_iter()
+                    .map(|x| {
+                        u16::try_from(x).map_err(|_| out_of_range(x, data_type))
+                    })
+                    .collect::<Result<_>>()?,
+            )
+        }
+        DataType::Date32 => {
+            let series = series.strict_cast(&PolarsType::Date)?;
+            Column::Date32(values::<Int32Type>(&series.to_physical_repr())?)
+        }
+        DataType::DateTime(tz) => Column::DateTime {
+            tz: tz.clone(),
+            data: timestamps(series, TimeUnit::Milliseconds)?
+                .into_iter()
+                .map(|x| {
+                    let x = x.div_euclid(1000);
+                    u32::try_from(x).map_err(|_| out_of_range(x, data_type))
+                })
+                .collect::<Result<_>>()?,
+        },
+        DataType::DateTime64(precision, tz) => {
+            let (unit, factor) = time_unit(*precision)?;
+            let mut data = timestamps(series, unit)?;
+            if factor > 1 {
+                data.iter_mut().for_each(|x| *x = x.div_euclid(factor));
+            }
+            Column::DateTime64 {
+                precision: *precision,
+                tz: tz.clone(),
+                data,
+            }
+        }
+        DataType::Uuid => match series.dtype() {
+            PolarsType::Binary => Column::Uuid(
+                binary_values(series)?
+                    .iter()
+                    .map(|x| {
+                        x.as_deref().map_or(Ok(Uuid::nil()), |x| {
+                            Uuid::from_slice(x).map_err(|e| {
+                                DataTypeError::EncodeError(e.to_string())
+                            })
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            _ => Column::Uuid(parsed(series, data_type, Uuid::nil())?),
+        },
+        DataType::IPv4 if series.dtype().is_integer() => Column::IPv4(
+            values::<UInt32Type>(series)?
+                .into_iter()
+                .map(Ipv4Addr::from)
+                .collect(),
+        ),
+        DataType::IPv4 => {
+            Column::IPv4(parsed(series, data_type, Ipv4Addr::UNSPECIFIED)?)
+        }
+        DataType::IPv6 => match series.dtype() {
+            PolarsType::Binary => Column::IPv6(
+                binary_values(series)?
+                    .iter()
+                    .map(|x| ipv6(x.as_deref(), data_type))
+                    .collect::<Result<_>>()?,
+            ),
+            _ => {
+                Column::IPv6(parsed(series, data_type, Ipv6Addr::UNSPECIFIED)?)
+            }
+        },
+        DataType::Array(inner) => {
+            let (offsets, values) = list_parts(series)?;
+            let inner = Column::from_series(&values, inner)?;
+            Column::Array(ArrayColumn::new(offsets, inner)?)
+        }
+        DataType::Ring
+        | DataType::LineString
+        | DataType::MultiLineString
+        | DataType::Polygon
+        | DataType::MultiPolygon => {
+            let (offsets, values) = list_parts(series)?;
+            let inner = match data_type {
+                DataType::Ring | DataType::LineString => DataType::Point,
+                DataType::MultiLineString => DataType::LineString,
+                DataType::Polygon => DataType::Ring,
+                _ => DataType::Polygon,
+            };
+            let inner = Column::from_series(&values, &inner)?;
+            let array = ArrayColumn::new(offsets, inner)?;
+            match data_type {
+                DataType::Ring => Column::Ring(array),
+                DataType::LineString => Column::LineString(array),
+                DataType::MultiLineString => Column::MultiLineString(array),
+                DataType::Polygon => Column::Polygon(array),
+                _ => Column::MultiPolygon(array),
+            }
+        }
+        DataType::Nested(fields) => {
+            let (offsets, values) = list_parts(series)?;
+            let columns =
+                struct_columns(&values, fields.iter().map(|(_, x)| x))?;
+            let names = fields.iter().map(|(name, _)| name.clone());
+            Column::Nested(NestedColumn::new(
+                offsets,
+                names.zip(columns).collect(),
+            )?)
+        }
+        DataType::Tuple(elements) => {
+            let columns =
+                struct_columns(series, elements.iter().map(|(_, x)| x))?;
+            let names = elements.iter().map(|(name, _)| name.clone());
+            Column::Tuple(TupleColumn::new(names.zip(columns).collect())?)
+        }
+        DataType::Map(key, value) => {
+            let (offsets, entries) = list_parts(series)?;
+            let mut columns =
+                struct_columns(&entries, [&**key, &**value].into_iter())?;
+            let values = columns.pop().expect("two columns");
+            let keys = columns.pop().expect("two columns");
+            Column::Map(MapColumn::new(offsets, keys, values)?)
+        }
+        DataType::Point => {
+            let types = [DataType::Float64, DataType::Float64];
+            let columns = struct_columns(series, types.iter())?;
+            let (Column::Float64(x), Column::Float64(y)) =
+                (&columns[0], &columns[1])
+            else {
+                unreachable!("converted to Float64")
+            };
+            Column::Point(
+                x.iter()
+                    .zip(y)
+                    .map(|(x, y)| Point { x: *x, y: *y })
+                    .collect(),
+            )
+        }
+        DataType::Nullable(_)
+        | DataType::LowCardinality(_)
+        | DataType::SimpleAggregateFunction(_, _) => {
+            Column::from_series(series, data_type)?
+        }
+        DataType::Variant(_) | DataType::Dynamic(_) => {
+            return Err(unsupported(data_type))
+        }
+    };
+    Ok(column)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use polars::prelude::{
+        DataType as PolarsType, NamedFrom, Series, TimeUnit,
+    };
+
+    use crate::{Column, DataType, Value};
+
+    fn column(data_type: &str, values: Vec<Value>) -> Result<Column> {
+        let mut column = Column::new(&data_type.parse()?);
+        for value in values {
+            column.push(value)?;
+        }
+        Ok(column)
+    }
+
+    /// Every column converts to a series and back unchanged.
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for (data_type, values) in
+            [
+                ("UInt128", vec![Value::UInt128(u128::MAX)]),
+                ("Int128", vec![Value::Int128(-1)]),
+                ("Nullable(String)", vec!["a".into(), Value::Null]),
+                ("Nullable(Int32)", vec![Value::Null, 7i32.into()]),
+                ("FixedString(2)", vec!["ab".into()]),
+                ("Date", vec![Value::Date(1)]),
+                (
+                    "DateTime('UTC')",
+                    vec![Value::DateTime {
+                        value: 10,
+                        tz: Some("UTC".into()),
+                    }],
+                ),
+                (
+                    "DateTime64(2, 'UTC')",
+                    vec![Value::DateTime64 {
+                        value: -150,
+                        precision: 2,
+                        tz: Some("UTC".into()),
+                    }],
+                ),
+                (
+                    "Nullable(Enum8('a' = -1, 'b' = 5))",
+                    vec![
+                        Value::Null,
+                        Value::Enum8 {
+                            name: "b".into(),
+                            code: 5,
+                        },
+                    ],
+                ),
+                (
+                    "LowCardinality(Nullable(String))",
+                    vec![Value::Null, "x".into()],
+                ),
+                ("LowCardinality(UInt64)", vec![3u64.into(), 3u64.into()]),
+                ("Array(Nullable(UInt8))", vec![vec![Some(1u8), None].into()]),
+                (
+                    "Map(String, UInt64)",
+                    vec![Value::Map(vec![("k".into(), 1u64.into())])],
+                ),
+                (
+                    "Tuple(a Int32, String)",
+                    vec![Value::Tuple(vec![1i32.into(), "s".into()])],
+                ),
+                (
+                    "Nested(a UInt8, b String)",
+                    vec![Value::Array(vec![Value::Tuple(vec![
+                        1u8.into(),
+                        "x".into(),
+                    ])])],
+                ),
+                ("UUID", vec![Value::Uuid(uuid::Uuid::from_u128(7))]),
+                ("IPv4", vec![Value::IPv4("10.0.0.1".parse()?)]),
+                ("IPv6", vec![Value::IPv6("::1".parse()?)]),
+                ("Nullable(UUID)", vec![Value::Null]),
+                (
+                    "Polygon",
+                    vec![Value::Array(vec![Value::Array(vec![
+                        crate::Point { x: 1.0, y: 2.0 }.into(),
+                    ])])],
+                ),
+            ]
+        {
+            let column = column(data_type, values)?;
+            let series = column.clone().into_series("c")?;
+            assert_eq!(
+                Column::from_series(&series, &column.data_type())?,
+                column,
+                "{data_type}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_polars_types() -> Result<()> {
+        let series =
+            column("Nullable(UInt64)", vec![1u64.into(), Value::Null])?
+                .into_series("n")?;
+        assert_eq!(series.name().as_str(), "n");
+        assert_eq!(series.u64()?.get(0), Some(1));
+        assert_eq!(series.null_count(), 1);
+
+        let series = column(
+            "DateTime64(3, 'Europe/Berlin')",
+            vec![Value::DateTime64 {
+                value: 1500,
+                precision: 3,
+                tz: None,
+            }],
+        )?
+        .into_series("t")?;
+        assert!(matches!(
+            series.dtype(),
+            PolarsType::Datetime(TimeUnit::Milliseconds, Some(tz))
+                if tz.as_str() == "Europe/Berlin"
+        ));
+        let ticks = Column::from_series(&series, &"DateTime64(1)".parse()?)?;
+        assert_eq!(ticks.get::<i64>(0)?, 15);
+
+        let series = column("Enum8('a' = 1, 'b' = 2)", vec!["b".into()])?
+            .into_series("e")?;
+        assert!(matches!(series.dtype(), PolarsType::Enum(..)));
+        assert_eq!(
+            series.strict_cast(&PolarsType::String)?.str()?.get(0),
+            Some("b")
+        );
+
+        // strings are Binary whatever their values, unless UTF-8 is asked for
+        let strings = column("LowCardinality(String)", vec!["a".into()])?;
+        let series = strings.clone().into_series("c")?;
+        assert_eq!(series.dtype(), &PolarsType::Binary);
+        let series = strings.into_series_utf8("c")?;
+        assert!(matches!(series.dtype(), PolarsType::Categorical(..)));
+        let strings = column("String", vec![Value::String(vec![0xff].into())])?;
+        let series = strings.clone().into_series("s")?;
+        assert_eq!(series.dtype(), &PolarsType::Binary);
+        assert!(strings.into_series_utf8("s").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_series_casts() -> Result<()> {
+        let series = Series::new("i".into(), [1_i64, 300]);
+        assert_eq!(
+            Column::from_series(&series, &DataType::UInt16)?,
+            Column::UInt16(vec![1, 300])
+        );
+        assert!(Column::from_series(&series, &DataType::UInt8).is_err());
+
+        let series = Series::new("s".into(), [Some("a"), None]);
+        assert!(Column::from_series(&series, &DataType::String).is_err());
+        let column = Column::from_series(
+            &series,
+            &"LowCardinality(Nullable(String))".parse()?,
+        )?;
+        assert_eq!(column.get::<Option<&str>>(0)?, Some("a"));
+        assert_eq!(column.get::<Option<&str>>(1)?, None);
+        Ok(())
+    }
+}
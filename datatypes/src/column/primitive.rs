@@ -0,0 +1,261 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use byteorder::{ByteOrder, LittleEndian};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use super::{Column, EnumColumn};
+use crate::binary::read_bytes;
+use crate::error::Result;
+
+/// A fixed-width value stored as little-endian bytes on the wire.
+pub trait Primitive: Copy + Send + Sync + 'static {
+    const SIZE: usize;
+
+    /// The value ClickHouse stores under a `NULL`.
+    const ZERO: Self;
+
+    fn decode_into(src: &[u8], dst: &mut [Self]);
+
+    fn encode_into(src: &[Self], dst: &mut [u8]);
+
+    /// Borrows the values of a column that stores `Self`.
+    fn as_slice(column: &Column) -> Option<&[Self]>;
+
+    /// Mutably borrows the values of a column that accepts any `Self`.
+    ///
+    /// Columns whose values are restricted, such as enum codes, are only
+    /// available through [`Primitive::as_slice`].
+    fn as_vec_mut(column: &mut Column) -> Option<&mut Vec<Self>>;
+
+    /// Views the bytes of a `String` value as `Self`, which only `u8` can.
+    #[doc(hidden)]
+    fn from_bytes(_bytes: &[u8]) -> Option<&[Self]> {
+        None
+    }
+}
+
+macro_rules! impl_primitive {
+    (
+        $ty:ty,
+        $zero:expr,
+        $decode:expr,
+        $encode:expr,
+        $data:ident => $($pattern:pat_param)|+
+        $(, read_only => $($read_only:pat_param)|+)?
+        $(, from_bytes => $from_bytes:expr)?
+    ) => {
+        impl Primitive for $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+
+            const ZERO: Self = $zero;
+
+            fn decode_into(src: &[u8], dst: &mut [Self]) {
+                $decode(src, dst)
+            }
+
+            fn encode_into(src: &[Self], dst: &mut [u8]) {
+                $encode(src, dst)
+            }
+
+            fn as_slice(column: &Column) -> Option<&[Self]> {
+                match column {
+                    $($pattern => Some($data),)+
+                    $($($read_only => Some($data),)+)?
+                    _ => None,
+                }
+            }
+
+            fn as_vec_mut(column: &mut Column) -> Option<&mut Vec<Self>> {
+                match column {
+                    $($pattern => Some($data),)+
+                    _ => None,
+                }
+            }
+
+            $(
+                fn from_bytes(bytes: &[u8]) -> Option<&[Self]> {
+                    $from_bytes(bytes)
+                }
+            )?
+        }
+    };
+}
+
+impl_primitive!(
+    u8,
+    0,
+    |src: &[u8], dst: &mut [u8]| dst.copy_from_slice(src),
+    |src: &[u8], dst: &mut [u8]| dst.copy_from_slice(src),
+    data => Column::UInt8(data),
+    from_bytes => Some
+);
+impl_primitive!(
+    i8,
+    0,
+    |src: &[u8], dst: &mut [i8]| {
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d = *s as i8;
+        }
+    },
+    |src: &[i8], dst: &mut [u8]| {
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d = *s as u8;
+        }
+    },
+    data => Column::Int8(data),
+    read_only => Column::Enum8(EnumColumn { data, .. })
+);
+impl_primitive!(
+    u16,
+    0,
+    LittleEndian::read_u16_into,
+    LittleEndian::write_u16_into,
+    data => Column::UInt16(data) | Column::Date(data)
+);
+impl_primitive!(
+    u32,
+    0,
+    LittleEndian::read_u32_into,
+    LittleEndian::write_u32_into,
+    data => Column::UInt32(data) | Column::DateTime { data, .. }
+);
+impl_primitive!(
+    u64,
+    0,
+    LittleEndian::read_u64_into,
+    LittleEndian::write_u64_into,
+    data => Column::UInt64(data)
+);
+impl_primitive!(
+    u128,
+    0,
+    LittleEndian::read_u128_into,
+    LittleEndian::write_u128_into,
+    data => Column::UInt128(data)
+);
+impl_primitive!(
+    i16,
+    0,
+    LittleEndian::read_i16_into,
+    LittleEndian::write_i16_into,
+    data => Column::Int16(data),
+    read_only => Column::Enum16(EnumColumn { data, .. })
+);
+impl_primitive!(
+    i32,
+    0,
+    LittleEndian::read_i32_into,
+    LittleEndian::write_i32_into,
+    data => Column::Int32(data) | Column::Date32(data)
+);
+impl_primitive!(
+    i64,
+    0,
+    LittleEndian::read_i64_into,
+    LittleEndian::write_i64_into,
+    data => Column::Int64(data) | Column::DateTime64 { data, .. }
+);
+impl_primitive!(
+    i128,
+    0,
+    LittleEndian::read_i128_into,
+    LittleEndian::write_i128_into,
+    data => Column::Int128(data)
+);
+impl_primitive!(
+    f32,
+    0.0,
+    LittleEndian::read_f32_into,
+    LittleEndian::write_f32_into,
+    data => Column::Float32(data)
+);
+impl_primitive!(
+    f64,
+    0.0,
+    LittleEndian::read_f64_into,
+    LittleEndian::write_f64_into,
+    data => Column::Float64(data)
+);
+
+// UUIDs are two little-endian u64 halves, the high half first.
+impl_primitive!(
+    Uuid,
+    Uuid::nil(),
+    |src: &[u8], dst: &mut [Uuid]| {
+        for (d, s) in dst.iter_mut().zip(src.chunks_exact(16)) {
+            let high = LittleEndian::read_u64(&s[..8]);
+            let low = LittleEndian::read_u64(&s[8..]);
+            *d = Uuid::from_u64_pair(high, low);
+        }
+    },
+    |src: &[Uuid], dst: &mut [u8]| {
+        for (s, d) in src.iter().zip(dst.chunks_exact_mut(16)) {
+            let (high, low) = s.as_u64_pair();
+            LittleEndian::write_u64(&mut d[..8], high);
+            LittleEndian::write_u64(&mut d[8..], low);
+        }
+    },
+    data => Column::Uuid(data)
+);
+// IPv4 addresses are their numeric value as a little-endian u32.
+impl_primitive!(
+    Ipv4Addr,
+    Ipv4Addr::UNSPECIFIED,
+    |src: &[u8], dst: &mut [Ipv4Addr]| {
+        for (d, s) in dst.iter_mut().zip(src.chunks_exact(4)) {
+            *d = Ipv4Addr::from(LittleEndian::read_u32(s));
+        }
+    },
+    |src: &[Ipv4Addr], dst: &mut [u8]| {
+        for (s, d) in src.iter().zip(dst.chunks_exact_mut(4)) {
+            LittleEndian::write_u32(d, u32::from(*s));
+        }
+    },
+    data => Column::IPv4(data)
+);
+// IPv6 addresses are their 16 bytes in network order.
+impl_primitive!(
+    Ipv6Addr,
+    Ipv6Addr::UNSPECIFIED,
+    |src: &[u8], dst: &mut [Ipv6Addr]| {
+        for (d, s) in dst.iter_mut().zip(src.chunks_exact(16)) {
+            let octets: [u8; 16] = s.try_into().expect("chunk of 16 bytes");
+            *d = Ipv6Addr::from(octets);
+        }
+    },
+    |src: &[Ipv6Addr], dst: &mut [u8]| {
+        for (s, d) in src.iter().zip(dst.chunks_exact_mut(16)) {
+            d.copy_from_slice(&s.octets());
+        }
+    },
+    data => Column::IPv6(data)
+);
+
+pub(crate) async fn read_primitive<T, R>(
+    reader: &mut R,
+    rows: usize,
+) -> Result<Vec<T>>
+where
+    T: Primitive,
+    R: AsyncRead + Unpin + Send,
+{
+    let bytes = read_bytes(reader, rows * T::SIZE).await?;
+    let mut data = vec![T::ZERO; rows];
+    T::decode_into(&bytes, &mut data);
+    Ok(data)
+}
+
+pub(crate) async fn write_primitive<T, W>(
+    writer: &mut W,
+    data: &[T],
+) -> Result<usize>
+where
+    T: Primitive,
+    W: AsyncWrite + Unpin + Send,
+{
+    let mut bytes = vec![0_u8; data.len() * T::SIZE];
+    T::encode_into(data, &mut bytes);
+    writer.write_all(&bytes).await?;
+    Ok(bytes.len())
+}
@@ -0,0 +1,180 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{Column, Prefix};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// A `Tuple(T, ...)` column.
+///
+/// On the wire the elements are written one full column after another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TupleColumn {
+    pub(crate) elements: Vec<(Option<String>, Column)>,
+}
+
+impl TupleColumn {
+    pub fn new(elements: Vec<(Option<String>, Column)>) -> Result<TupleColumn> {
+        let Some((_, first)) = elements.first() else {
+            return Err(DataTypeError::EncodeError(
+                "tuple must have at least one element".into(),
+            ));
+        };
+        if elements
+            .iter()
+            .any(|(_, column)| column.len() != first.len())
+        {
+            return Err(DataTypeError::EncodeError(
+                "tuple elements must have the same number of rows".into(),
+            ));
+        }
+        Ok(TupleColumn { elements })
+    }
+
+    pub(crate) fn empty(elements: &[(Option<String>, DataType)]) -> Self {
+        TupleColumn {
+            elements: elements
+                .iter()
+                .map(|(name, data_type)| (name.clone(), Column::new(data_type)))
+                .collect(),
+        }
+    }
+
+    pub fn elements(&self) -> &[(Option<String>, Column)] {
+        &self.elements
+    }
+
+    pub fn element(&self, index: usize) -> Option<&Column> {
+        self.elements.get(index).map(|(_, column)| column)
+    }
+
+    /// Looks up an element of a named tuple.
+    pub fn field(&self, name: &str) -> Option<&Column> {
+        self.elements
+            .iter()
+            .find(|(field, _)| field.as_deref() == Some(name))
+            .map(|(_, column)| column)
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.first().map_or(0, |(_, column)| column.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::Tuple(
+            self.elements
+                .iter()
+                .map(|(name, column)| (name.clone(), column.data_type()))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn push_default(&mut self) {
+        for (_, column) in &mut self.elements {
+            column.push_default();
+        }
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        for (_, column) in &mut self.elements {
+            column.truncate(len);
+        }
+    }
+
+    /// Appends one row whose elements are pushed by `push`.
+    ///
+    /// If `push` fails, the column is left unchanged.
+    pub(crate) fn push_with(
+        &mut self,
+        push: impl FnOnce(&mut [(Option<String>, Column)]) -> Result<()>,
+    ) -> Result<()> {
+        let len = self.len();
+        if let Err(e) = push(&mut self.elements) {
+            self.truncate(len);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn read<R>(
+        elements: &[(Option<String>, DataType)],
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<TupleColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut columns = Vec::with_capacity(elements.len());
+        for (i, (name, data_type)) in elements.iter().enumerate() {
+            let prefix = prefix.inner(i);
+            let column =
+                Box::pin(Column::read_data(data_type, prefix, rows, reader))
+                    .await?;
+            columns.push((name.clone(), column));
+        }
+        Ok(TupleColumn { elements: columns })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut len = 0;
+        for (_, column) in &self.elements {
+            len += Box::pin(column.write_data(writer)).await?;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, DataType};
+
+    #[tokio::test]
+    async fn test_tuple_round_trip() -> Result<()> {
+        let data_type: DataType = "Tuple(UInt8, Nullable(String))".parse()?;
+        let bytes = [
+            1, 2, // UInt8 element
+            0, 1, // null map
+            1, b'a', 0, // String element
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 2, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+        assert_eq!(column.get::<(u8, Option<&str>)>(0)?, (1, Some("a")));
+        assert_eq!(column.get::<(u8, Option<String>)>(1)?, (2, None));
+        assert!(column.get::<(u8,)>(0).is_err());
+
+        let mut built = Column::new(&data_type);
+        built.push((1_u8, Some("a")))?;
+        built.push((2_u8, None::<&str>))?;
+        assert_eq!(built, column);
+        assert!(built.push((3_u8, "not nullable")).is_err());
+        assert_eq!(built.len(), 2);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_tuple_fields() -> Result<()> {
+        let mut column = Column::new(&"Tuple(id UInt64, name String)".parse()?);
+        column.push((7_u64, "seven"))?;
+        let Column::Tuple(tuple) = &column else {
+            panic!("expected tuple column");
+        };
+        assert_eq!(tuple.field("name").unwrap().get::<&str>(0)?, "seven");
+        assert!(tuple.field("missing").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,671 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::primitive::{read_primitive, write_primitive};
+use super::{Column, FromColumn, IntoColumn, Prefix};
+use crate::binary::{read_string, read_var_uint, write_string, write_var_uint};
+use crate::error::{DataTypeError, Result};
+use crate::types::{DataType, MAX_VARIANT_TYPES};
+
+/// The discriminator of a `NULL` row.
+const NULL_DISCRIMINATOR: u8 = 255;
+/// Discriminators written as one byte per row. The compact mode is only used
+/// in MergeTree parts.
+const BASIC_DISCRIMINATORS: u64 = 0;
+/// `Dynamic` structure with `max_types` in the prefix.
+const DYNAMIC_V1: u64 = 1;
+/// `Dynamic` structure without `max_types`.
+const DYNAMIC_V2: u64 = 2;
+const DEFAULT_MAX_DYNAMIC_TYPES: usize = 32;
+/// Name of the variant holding `Dynamic` values beyond `max_types`, which
+/// decides where it sorts among the other variants.
+const SHARED_VARIANT: &str = "SharedVariant";
+
+/// A `Variant(T, ...)` column.
+///
+/// Every row is `NULL` or a value of one of the types. On the wire a
+/// discriminator per row, the index of its type or 255 for `NULL`, is
+/// followed by one column per type holding only the rows of that type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantColumn {
+    discriminators: Vec<u8>,
+    /// The row of each value within its variant column.
+    offsets: Vec<usize>,
+    variants: Vec<Column>,
+}
+
+impl VariantColumn {
+    pub fn new(
+        discriminators: Vec<u8>,
+        variants: Vec<Column>,
+    ) -> Result<VariantColumn> {
+        VariantColumn::from_parts(discriminators, variants)
+            .map_err(DataTypeError::EncodeError)
+    }
+
+    fn from_parts(
+        discriminators: Vec<u8>,
+        variants: Vec<Column>,
+    ) -> std::result::Result<VariantColumn, String> {
+        if variants.len() > MAX_VARIANT_TYPES {
+            return Err(format!(
+                "Variant cannot have more than {MAX_VARIANT_TYPES} types"
+            ));
+        }
+        let mut counts = vec![0; variants.len()];
+        let mut offsets = Vec::with_capacity(discriminators.len());
+        for discriminator in &discriminators {
+            if *discriminator == NULL_DISCRIMINATOR {
+                offsets.push(0);
+                continue;
+            }
+            let Some(count) = counts.get_mut(*discriminator as usize) else {
+                return Err(format!(
+                    "unknown Variant discriminator {discriminator}"
+                ));
+            };
+            offsets.push(*count);
+            *count += 1;
+        }
+        for (i, (count, variant)) in counts.iter().zip(&variants).enumerate() {
+            if *count != variant.len() {
+                return Err(format!(
+                    "Variant type {i} has {} rows, expected {count}",
+                    variant.len()
+                ));
+            }
+        }
+        Ok(VariantColumn {
+            discriminators,
+            offsets,
+            variants,
+        })
+    }
+
+    pub(crate) fn empty(types: &[DataType]) -> Self {
+        VariantColumn {
+            discriminators: Vec::new(),
+            offsets: Vec::new(),
+            variants: types.iter().map(Column::new).collect(),
+        }
+    }
+
+    /// One column per type, in discriminator order.
+    pub fn variants(&self) -> &[Column] {
+        &self.variants
+    }
+
+    pub fn discriminators(&self) -> &[u8] {
+        &self.discriminators
+    }
+
+    /// The index of the type held by `row`, `None` if it is `NULL`.
+    pub fn discriminator(&self, row: usize) -> Option<usize> {
+        match self.discriminators.get(row) {
+            Some(&NULL_DISCRIMINATOR) | None => None,
+            Some(discriminator) => Some(*discriminator as usize),
+        }
+    }
+
+    pub fn is_null(&self, row: usize) -> bool {
+        self.discriminators.get(row) == Some(&NULL_DISCRIMINATOR)
+    }
+
+    /// The variant column and the row within it holding the value of `row`.
+    pub fn value(&self, row: usize) -> Option<(&Column, usize)> {
+        let discriminator = self.discriminator(row)?;
+        Some((&self.variants[discriminator], self.offsets[row]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.discriminators.len()
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        self.discriminators.len()
+            + self.variants.iter().map(Column::byte_size).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.discriminators.is_empty()
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::Variant(self.variants.iter().map(Column::data_type).collect())
+    }
+
+    pub fn push_null(&mut self) {
+        self.discriminators.push(NULL_DISCRIMINATOR);
+        self.offsets.push(0);
+    }
+
+    /// Appends `value` as a value of the type at `index`.
+    ///
+    /// If the value does not fit that type, the column is left unchanged.
+    pub fn push<T: IntoColumn>(
+        &mut self,
+        index: usize,
+        value: T,
+    ) -> Result<()> {
+        let Some(variant) = self.variants.get_mut(index) else {
+            return Err(DataTypeError::EncodeError(format!(
+                "Variant has no type {index}"
+            )));
+        };
+        let offset = variant.len();
+        variant.push(value)?;
+        self.discriminators.push(index as u8);
+        self.offsets.push(offset);
+        Ok(())
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.discriminators.truncate(len);
+        self.offsets.truncate(len);
+        let mut counts = vec![0; self.variants.len()];
+        for discriminator in self.discriminators.iter() {
+            if let Some(count) = counts.get_mut(*discriminator as usize) {
+                *count += 1;
+            }
+        }
+        for (variant, count) in self.variants.iter_mut().zip(counts) {
+            variant.truncate(count);
+        }
+    }
+
+    /// Makes room for a new type at `index`, shifting the discriminators of
+    /// the types after it.
+    fn insert_variant(&mut self, index: usize, data_type: &DataType) {
+        self.variants.insert(index, Column::new(data_type));
+        for discriminator in &mut self.discriminators {
+            if *discriminator != NULL_DISCRIMINATOR
+                && *discriminator as usize >= index
+            {
+                *discriminator += 1;
+            }
+        }
+    }
+
+    pub(crate) async fn read_prefix<R>(
+        types: &[DataType],
+        reader: &mut R,
+    ) -> Result<Prefix>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mode = reader.read_u64_le().await?;
+        if mode != BASIC_DISCRIMINATORS {
+            return Err(DataTypeError::DecodeError(format!(
+                "unsupported Variant discriminators serialization mode {mode}"
+            )));
+        }
+        let mut prefixes = Vec::with_capacity(types.len());
+        for data_type in types {
+            prefixes
+                .push(Box::pin(Column::read_prefix(data_type, reader)).await?);
+        }
+        Ok(Prefix::Inner(prefixes))
+    }
+
+    pub(crate) async fn write_prefix<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_u64_le(BASIC_DISCRIMINATORS).await?;
+        let mut len = 8;
+        for variant in &self.variants {
+            len += Box::pin(variant.write_prefix(writer)).await?;
+        }
+        Ok(len)
+    }
+
+    pub(crate) async fn read<R>(
+        types: &[DataType],
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<VariantColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let discriminators: Vec<u8> = read_primitive(reader, rows).await?;
+        let mut counts = vec![0; types.len()];
+        for discriminator in &discriminators {
+            if let Some(count) = counts.get_mut(*discriminator as usize) {
+                *count += 1;
+            }
+        }
+        let mut variants = Vec::with_capacity(types.len());
+        for (i, (data_type, count)) in types.iter().zip(counts).enumerate() {
+            let prefix = prefix.inner(i);
+            variants.push(
+                Box::pin(Column::read_data(data_type, prefix, count, reader))
+                    .await?,
+            );
+        }
+        VariantColumn::from_parts(discriminators, variants)
+            .map_err(DataTypeError::DecodeError)
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut len = write_primitive(writer, &self.discriminators).await?;
+        for variant in &self.variants {
+            len += Box::pin(variant.write_data(writer)).await?;
+        }
+        Ok(len)
+    }
+}
+
+/// The types held by a `Dynamic` column, from its prefix.
+#[derive(Debug)]
+pub(crate) struct DynamicStructure {
+    types: Vec<Option<DataType>>,
+    variant: Box<Prefix>,
+}
+
+/// A `Dynamic` column.
+///
+/// Stored as a `Variant` of the types present in the column, which the
+/// server lists in the column prefix, and a shared variant for values of
+/// types beyond `max_types`. Shared values are kept as they are sent: the
+/// binary encoded type followed by the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicColumn {
+    max_types: Option<usize>,
+    /// The type of each variant, `None` for the shared variant.
+    types: Vec<Option<DataType>>,
+    variant: VariantColumn,
+}
+
+impl DynamicColumn {
+    pub(crate) fn empty(max_types: Option<usize>) -> Self {
+        DynamicColumn {
+            max_types,
+            types: vec![None],
+            variant: VariantColumn::empty(&[DataType::String]),
+        }
+    }
+
+    pub fn max_types(&self) -> usize {
+        self.max_types.unwrap_or(DEFAULT_MAX_DYNAMIC_TYPES)
+    }
+
+    /// The types held by the column, in discriminator order.
+    pub fn data_types(&self) -> impl Iterator<Item = &DataType> {
+        self.types.iter().flatten()
+    }
+
+    /// The underlying variant, whose types are [`DynamicColumn::data_types`]
+    /// plus the shared variant.
+    pub fn variant(&self) -> &VariantColumn {
+        &self.variant
+    }
+
+    /// The type of the variant at `index`, `None` for the shared variant.
+    pub fn variant_type(&self, index: usize) -> Option<&DataType> {
+        self.types.get(index)?.as_ref()
+    }
+
+    /// The encoded type and value of `row` if it is in the shared variant.
+    pub fn shared(&self, row: usize) -> Option<&[u8]> {
+        let index = self.variant.discriminator(row)?;
+        if self.types[index].is_some() {
+            return None;
+        }
+        let (column, row) = self.variant.value(row)?;
+        column.get(row).ok()
+    }
+
+    /// The variant column and the row within it holding the value of `row`,
+    /// unless it is `NULL` or in the shared variant.
+    pub fn value(&self, row: usize) -> Option<(&Column, usize)> {
+        let index = self.variant.discriminator(row)?;
+        self.types[index].as_ref()?;
+        self.variant.value(row)
+    }
+
+    pub fn len(&self) -> usize {
+        self.variant.len()
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        self.variant.byte_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variant.is_empty()
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::Dynamic(self.max_types)
+    }
+
+    pub fn push_null(&mut self) {
+        self.variant.push_null();
+    }
+
+    /// Appends `value` as a value of `data_type`, which is added to the
+    /// column unless it already holds `max_types` types.
+    ///
+    /// If the value does not fit that type, the column is left unchanged.
+    pub fn push<T: IntoColumn>(
+        &mut self,
+        data_type: &DataType,
+        value: T,
+    ) -> Result<()> {
+        let index = match self
+            .types
+            .iter()
+            .position(|x| x.as_ref() == Some(data_type))
+        {
+            Some(index) => index,
+            None => self.add_type(data_type)?,
+        };
+        self.variant.push(index, value)
+    }
+
+    fn add_type(&mut self, data_type: &DataType) -> Result<usize> {
+        if !data_type.can_be_inside_variant() {
+            return Err(DataTypeError::EncodeError(format!(
+                "{data_type} cannot be inside Dynamic"
+            )));
+        }
+        if self.data_types().count() >= self.max_types() {
+            return Err(DataTypeError::EncodeError(format!(
+                "{} already holds {} types",
+                self.data_type(),
+                self.max_types()
+            )));
+        }
+        let name = data_type.to_string();
+        let index = self
+            .types
+            .iter()
+            .position(|x| variant_name(x) > name)
+            .unwrap_or(self.types.len());
+        self.types.insert(index, Some(data_type.clone()));
+        self.variant.insert_variant(index, data_type);
+        Ok(index)
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.variant.truncate(len);
+    }
+
+    pub(crate) async fn read_prefix<R>(
+        reader: &mut R,
+    ) -> Result<DynamicStructure>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let version = reader.read_u64_le().await?;
+        match version {
+            DYNAMIC_V1 => {
+                read_var_uint(reader).await?;
+            }
+            DYNAMIC_V2 => {}
+            _ => {
+                return Err(DataTypeError::DecodeError(format!(
+                    "unsupported Dynamic serialization version {version}"
+                )))
+            }
+        }
+        let count = read_var_uint(reader).await? as usize;
+        if count >= MAX_VARIANT_TYPES {
+            return Err(DataTypeError::DecodeError(format!(
+                "Dynamic column with {count} types"
+            )));
+        }
+        let mut types = Vec::with_capacity(count + 1);
+        for _ in 0..count {
+            let name = read_string(reader).await?;
+            types.push(Some(std::str::from_utf8(&name)?.parse()?));
+        }
+        types.push(None);
+        types.sort_by_cached_key(variant_name);
+        let variant =
+            VariantColumn::read_prefix(&variant_types(&types), reader).await?;
+        Ok(DynamicStructure {
+            types,
+            variant: Box::new(variant),
+        })
+    }
+
+    pub(crate) async fn write_prefix<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        writer.write_u64_le(DYNAMIC_V1).await?;
+        let mut len = 8;
+        len += write_var_uint(writer, self.max_types() as u64).await?;
+        len += write_var_uint(writer, self.data_types().count() as u64).await?;
+        for data_type in self.data_types() {
+            len +=
+                write_string(writer, data_type.to_string().as_bytes()).await?;
+        }
+        Ok(len + self.variant.write_prefix(writer).await?)
+    }
+
+    pub(crate) async fn read<R>(
+        max_types: Option<usize>,
+        prefix: &Prefix,
+        rows: usize,
+        reader: &mut R,
+    ) -> Result<DynamicColumn>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let Prefix::Dynamic(structure) = prefix else {
+            return Err(DataTypeError::DecodeError(
+                "Dynamic column without its structure prefix".into(),
+            ));
+        };
+        let types = variant_types(&structure.types);
+        let variant =
+            VariantColumn::read(&types, &structure.variant, rows, reader)
+                .await?;
+        Ok(DynamicColumn {
+            max_types,
+            types: structure.types.clone(),
+            variant,
+        })
+    }
+
+    pub(crate) async fn write<W>(&self, writer: &mut W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.variant.write(writer).await
+    }
+}
+
+fn variant_name(data_type: &Option<DataType>) -> String {
+    match data_type {
+        Some(data_type) => data_type.to_string(),
+        None => SHARED_VARIANT.to_owned(),
+    }
+}
+
+/// The shared variant is a `String` column of encoded values.
+fn variant_types(types: &[Option<DataType>]) -> Vec<DataType> {
+    types
+        .iter()
+        .map(|x| x.clone().unwrap_or(DataType::String))
+        .collect()
+}
+
+/// A single row of a `Variant` or `Dynamic` column.
+///
+/// [`Column::get`] already reads rows as the Rust type of the value they
+/// hold. Read them as `VariantValue` to find out which type that is first.
+/// Any other column yields its own row as [`VariantValue::Value`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariantValue<'a> {
+    Null,
+    /// The value at `row` of the variant column holding it.
+    Value {
+        column: &'a Column,
+        row: usize,
+    },
+    /// A `Dynamic` value in the shared variant: its binary encoded type
+    /// followed by the value.
+    Shared(&'a [u8]),
+}
+
+impl<'a> VariantValue<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, VariantValue::Null)
+    }
+
+    /// The type of the value, `None` for `NULL` and shared values.
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            VariantValue::Value { column, .. } => Some(column.data_type()),
+            _ => None,
+        }
+    }
+
+    /// Converts the value into a Rust value.
+    pub fn get<T: FromColumn<'a>>(&self) -> Result<T> {
+        match self {
+            VariantValue::Value { column, row } => column.get(*row),
+            VariantValue::Null => Err(DataTypeError::TypeMismatch {
+                data_type: "NULL".into(),
+                rust_type: std::any::type_name::<T>(),
+            }),
+            VariantValue::Shared(_) => Err(DataTypeError::TypeMismatch {
+                data_type: SHARED_VARIANT.into(),
+                rust_type: std::any::type_name::<T>(),
+            }),
+        }
+    }
+}
+
+impl<'a> FromColumn<'a> for VariantValue<'a> {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        if row >= column.len() {
+            return Err(DataTypeError::RowOutOfBounds {
+                row,
+                len: column.len(),
+            });
+        }
+        let value = match column {
+            Column::Variant(variant) => match variant.value(row) {
+                Some((column, row)) => VariantValue::Value { column, row },
+                None => VariantValue::Null,
+            },
+            Column::Dynamic(dynamic) => {
+                match (dynamic.value(row), dynamic.shared(row)) {
+                    (Some((column, row)), _) => {
+                        VariantValue::Value { column, row }
+                    }
+                    (None, Some(shared)) => VariantValue::Shared(shared),
+                    (None, None) => VariantValue::Null,
+                }
+            }
+            column => VariantValue::Value { column, row },
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{Column, DataType, VariantValue};
+
+    #[tokio::test]
+    async fn test_variant_round_trip() -> Result<()> {
+        let data_type: DataType = "Variant(UInt64, String)".parse()?;
+        let bytes = [
+            0, 0, 0, 0, 0, 0, 0, 0, // basic discriminators
+            1, 255, 0, // discriminators
+            1, b'a', // String rows
+            42, 0, 0, 0, 0, 0, 0, 0, // UInt64 rows
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.data_type(), data_type);
+        assert_eq!(column.get::<u64>(0)?, 42);
+        assert_eq!(column.get::<Option<u64>>(1)?, None);
+        assert_eq!(column.get::<&str>(2)?, "a");
+        assert!(column.get::<&str>(0).is_err());
+
+        let value: VariantValue = column.get(0)?;
+        assert_eq!(value.data_type(), Some(DataType::UInt64));
+        assert_eq!(value.get::<u64>()?, 42);
+        assert!(column.get::<VariantValue>(1)?.is_null());
+
+        let mut built = Column::new(&data_type);
+        let Column::Variant(variant) = &mut built else {
+            panic!("expected variant column");
+        };
+        variant.push(1, 42_u64)?;
+        assert!(variant.push(1, "a").is_err());
+        built.push(None::<u64>)?;
+        let Column::Variant(variant) = &mut built else {
+            panic!("expected variant column");
+        };
+        variant.push(0, "a")?;
+        assert_eq!(built, column);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_round_trip() -> Result<()> {
+        let data_type = DataType::Dynamic(None);
+        let bytes = [
+            1, 0, 0, 0, 0, 0, 0, 0, // structure version
+            32, 1, 5, b'I', b'n', b't', b'6', b'4', // max types and types
+            0, 0, 0, 0, 0, 0, 0, 0, // basic discriminators
+            0, 255, 1, // discriminators, SharedVariant sorts after Int64
+            7, 0, 0, 0, 0, 0, 0, 0, // Int64 rows
+            3, 0x15, 1, b'x', // shared String value
+        ];
+        let mut reader = &bytes[..];
+        let column = Column::read(&data_type, 3, &mut reader).await?;
+        assert!(reader.is_empty());
+        assert_eq!(column.get::<i64>(0)?, 7);
+        assert_eq!(column.get::<Option<i64>>(1)?, None);
+        assert_eq!(
+            column.get::<VariantValue>(2)?,
+            VariantValue::Shared(&[0x15, 1, b'x'])
+        );
+
+        let mut buf = Vec::new();
+        column.write(&mut buf).await?;
+        assert_eq!(buf, bytes);
+
+        let mut built = Column::new(&"Dynamic(max_types=2)".parse()?);
+        let Column::Dynamic(dynamic) = &mut built else {
+            panic!("expected dynamic column");
+        };
+        dynamic.push(&DataType::String, "a")?;
+        dynamic.push_null();
+        dynamic.push(&DataType::Int64, 7_i64)?;
+        assert!(dynamic.push(&DataType::UInt8, 1_u8).is_err());
+        assert!(dynamic.push(&DataType::Int64, "b").is_err());
+        assert_eq!(
+            dynamic.data_types().cloned().collect::<Vec<_>>(),
+            [DataType::Int64, DataType::String]
+        );
+        assert_eq!(dynamic.variant().discriminators(), [2, 255, 0]);
+        assert_eq!(built.get::<&str>(0)?, "a");
+        assert_eq!(built.get::<i64>(2)?, 7);
+
+        let mut buf = Vec::new();
+        built.write(&mut buf).await?;
+        let mut reader = &buf[..];
+        let read = Column::read(&built.data_type(), 3, &mut reader).await?;
+        assert_eq!(read, built);
+        Ok(())
+    }
+}
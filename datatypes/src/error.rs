@@ -0,0 +1,41 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DataTypeError {
+    #[error("invalid data type `{input}`: {reason}")]
+    ParseError { input: String, reason: String },
+
+    #[error("decode error: {0}")]
+    DecodeError(String),
+
+    #[error("encode error: {0}")]
+    EncodeError(String),
+
+    #[error("cannot convert column of type {data_type} to {rust_type}")]
+    TypeMismatch {
+        data_type: String,
+        rust_type: &'static str,
+    },
+
+    #[error("unknown value {value} for {data_type}")]
+    UnknownEnumValue { value: String, data_type: String },
+
+    #[error("row {row} is out of bounds for column of {len} rows")]
+    RowOutOfBounds { row: usize, len: usize },
+
+    #[error("{0}")]
+    Utf8Error(#[from] std::str::Utf8Error),
+
+    #[error("{0}")]
+    IoError(#[from] std::io::Error),
+
+    #[cfg(feature = "arrow")]
+    #[error("{0}")]
+    ArrowError(#[from] arrow::error::ArrowError),
+
+    #[cfg(feature = "polars")]
+    #[error("{0}")]
+    PolarsError(#[from] polars::error::PolarsError),
+}
+
+pub type Result<T, E = DataTypeError> = std::result::Result<T, E>;
@@ -1,14 +1,15 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
-}
+mod binary;
+mod column;
+mod error;
+mod row;
+mod types;
+mod value;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub use column::*;
+pub use error::*;
+pub use row::*;
+pub use types::*;
+pub use value::Value;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+#[cfg(feature = "derive")]
+pub use clickhouse_derive::{ClickHouseEnum, Row};
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::column::{
+    Column, IntoColumn, LineString, MultiLineString, MultiPolygon, Point,
+    Polygon, Ring,
+};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// A Rust type with the ClickHouse types its values can be written to.
+pub trait ColumnType {
+    /// The type a column for these values is naturally created with.
+    fn data_type() -> DataType;
+
+    /// Whether values can be appended to a column of `data_type`, which is
+    /// never `LowCardinality`, see [`can_write`].
+    ///
+    /// Types stored the same way are accepted too, such as `DateTime` for
+    /// `u32`.
+    fn accepts(data_type: &DataType) -> bool {
+        *data_type == Self::data_type()
+    }
+}
+
+/// Whether values of `T` can be appended to a column of `data_type`.
+///
+/// `LowCardinality` and `SimpleAggregateFunction` columns take the values
+/// of the type they wrap.
+pub fn can_write<T: ColumnType + ?Sized>(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::LowCardinality(inner)
+        | DataType::SimpleAggregateFunction(_, inner) => can_write::<T>(inner),
+        data_type => T::accepts(data_type),
+    }
+}
+
+/// A struct whose fields are the columns of a block, usually derived with
+/// `#[derive(Row)]`.
+///
+/// Rows are appended field by field to columns of the types a table
+/// actually has, so no intermediate values are built.
+pub trait Row: Sized {
+    /// The names of the columns, in field order.
+    const COLUMN_NAMES: &'static [&'static str];
+
+    /// The types of the fields, in field order.
+    fn data_types() -> Vec<DataType>;
+
+    /// Whether the field at `index` can be written to a column of
+    /// `data_type`.
+    fn accepts(index: usize, data_type: &DataType) -> bool;
+
+    /// Appends each field to the column at its index, which
+    /// [`Row::append_to`] has checked to exist.
+    #[doc(hidden)]
+    fn push_fields(self, columns: &mut [Column]) -> Result<()>;
+
+    /// Appends the row to `columns`, one per field in order. If a field does
+    /// not fit its column, all columns are left unchanged.
+    fn append_to(self, columns: &mut [Column]) -> Result<()> {
+        if columns.len() != Self::COLUMN_NAMES.len() {
+            return Err(DataTypeError::EncodeError(format!(
+                "expected {} columns, got {}",
+                Self::COLUMN_NAMES.len(),
+                columns.len()
+            )));
+        }
+        let len = columns.first().map_or(0, Column::len);
+        self.push_fields(columns).inspect_err(|_| {
+            for column in columns.iter_mut() {
+                column.truncate(len);
+            }
+        })
+    }
+}
+
+/// Appends a field, naming its column in the error.
+#[doc(hidden)]
+pub fn push_field<T: IntoColumn>(
+    column: &mut Column,
+    name: &str,
+    value: T,
+) -> Result<()> {
+    column.push(value).map_err(|e| {
+        DataTypeError::EncodeError(format!("cannot write column `{name}`: {e}"))
+    })
+}
+
+macro_rules! impl_column_type {
+    ($($ty:ty => $data_type:ident $(| $pattern:pat_param)*),+ $(,)?) => {
+        $(
+            impl ColumnType for $ty {
+                fn data_type() -> DataType {
+                    DataType::$data_type
+                }
+
+                fn accepts(data_type: &DataType) -> bool {
+                    matches!(data_type, DataType::$data_type $(| $pattern)*)
+                }
+            }
+        )+
+    };
+}
+
+impl_column_type!(
+    u8 => UInt8,
+    u16 => UInt16 | DataType::Date,
+    u32 => UInt32 | DataType::DateTime(_),
+    u64 => UInt64,
+    u128 => UInt128,
+    i8 => Int8 | DataType::Enum8(_),
+    i16 => Int16 | DataType::Enum16(_),
+    i32 => Int32 | DataType::Date32,
+    i64 => Int64 | DataType::DateTime64(_, _),
+    i128 => Int128,
+    f32 => Float32,
+    f64 => Float64,
+    bool => Bool,
+    String => String
+        | DataType::FixedString(_)
+        | DataType::Enum8(_)
+        | DataType::Enum16(_)
+        | DataType::Json(_),
+    Bytes => String
+        | DataType::FixedString(_)
+        | DataType::AggregateFunction(_, _),
+    Uuid => Uuid,
+    Ipv4Addr => IPv4,
+    Ipv6Addr => IPv6,
+    Point => Point,
+    Ring => Ring,
+    LineString => LineString,
+    MultiLineString => MultiLineString,
+    Polygon => Polygon,
+    MultiPolygon => MultiPolygon,
+);
+
+impl ColumnType for &str {
+    fn data_type() -> DataType {
+        String::data_type()
+    }
+
+    fn accepts(data_type: &DataType) -> bool {
+        String::accepts(data_type)
+    }
+}
+
+#[cfg(feature = "json")]
+impl ColumnType for serde_json::Value {
+    fn data_type() -> DataType {
+        DataType::Json(Vec::new())
+    }
+
+    fn accepts(data_type: &DataType) -> bool {
+        matches!(data_type, DataType::Json(_))
+    }
+}
+
+impl<T: ColumnType> ColumnType for Option<T> {
+    fn data_type() -> DataType {
+        DataType::Nullable(Box::new(T::data_type()))
+    }
+
+    fn accepts(data_type: &DataType) -> bool {
+        match data_type {
+            DataType::Nullable(inner) => can_write::<T>(inner),
+            _ => false,
+        }
+    }
+}
+
+/// Also writes the geo types stored as arrays, and the entries of a `Map`
+/// from a `Vec` of key and value tuples.
+impl<T: ColumnType> ColumnType for Vec<T> {
+    fn data_type() -> DataType {
+        DataType::Array(Box::new(T::data_type()))
+    }
+
+    fn accepts(data_type: &DataType) -> bool {
+        match data_type {
+            DataType::Array(inner) => can_write::<T>(inner),
+            DataType::Map(key, value) => can_write::<T>(&DataType::Tuple(
+                vec![(None, *key.clone()), (None, *value.clone())],
+            )),
+            DataType::Ring | DataType::LineString => {
+                can_write::<T>(&DataType::Point)
+            }
+            DataType::MultiLineString => can_write::<T>(&DataType::LineString),
+            DataType::Polygon => can_write::<T>(&DataType::Ring),
+            DataType::MultiPolygon => can_write::<T>(&DataType::Polygon),
+            _ => false,
+        }
+    }
+}
+
+macro_rules! impl_column_type_map {
+    ($($map:ident),+) => {
+        $(
+            impl<K: ColumnType, V: ColumnType> ColumnType for $map<K, V> {
+                fn data_type() -> DataType {
+                    DataType::Map(
+                        Box::new(K::data_type()),
+                        Box::new(V::data_type()),
+                    )
+                }
+
+                fn accepts(data_type: &DataType) -> bool {
+                    match data_type {
+                        DataType::Map(key, value) => {
+                            can_write::<K>(key) && can_write::<V>(value)
+                        }
+                        _ => false,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_column_type_map!(HashMap, BTreeMap);
+
+/// Tuples accept `Tuple` columns of as many elements, named or not.
+macro_rules! impl_column_type_tuple {
+    ($(($len:expr; $($ty:ident $idx:tt),+))+) => {
+        $(
+            impl<$($ty: ColumnType),+> ColumnType for ($($ty,)+) {
+                fn data_type() -> DataType {
+                    DataType::Tuple(vec![$((None, $ty::data_type()),)+])
+                }
+
+                fn accepts(data_type: &DataType) -> bool {
+                    match data_type {
+                        DataType::Tuple(elements) if elements.len() == $len => {
+                            $(can_write::<$ty>(&elements[$idx].1))&&+
+                        }
+                        _ => false,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_column_type_tuple! {
+    (1; A 0)
+    (2; A 0, B 1)
+    (3; A 0, B 1, C 2)
+    (4; A 0, B 1, C 2, D 3)
+    (5; A 0, B 1, C 2, D 3, E 4)
+    (6; A 0, B 1, C 2, D 3, E 4, F 5)
+    (7; A 0, B 1, C 2, D 3, E 4, F 5, G 6)
+    (8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+
+    use super::{can_write, ColumnType, Row};
+    use crate::{Column, DataType, Point};
+
+    #[test]
+    fn test_column_types() -> Result<()> {
+        assert_eq!(
+            <Vec<Option<u32>>>::data_type().to_string(),
+            "Array(Nullable(UInt32))"
+        );
+        assert_eq!(
+            <HashMap<String, (u8, f64)>>::data_type().to_string(),
+            "Map(String, Tuple(UInt8, Float64))"
+        );
+
+        for (data_type, expected) in [
+            ("DateTime('UTC')", true),
+            ("LowCardinality(Nullable(UInt32))", false),
+            ("SimpleAggregateFunction(max, UInt32)", true),
+            ("UInt64", false),
+        ] {
+            assert_eq!(can_write::<u32>(&data_type.parse()?), expected);
+        }
+        assert!(can_write::<Option<&str>>(
+            &"LowCardinality(Nullable(String))".parse()?
+        ));
+        assert!(can_write::<Vec<(String, u8)>>(&"Map(String, UInt8)".parse()?));
+        assert!(can_write::<(u8, String)>(&"Tuple(a UInt8, b String)".parse()?));
+        assert!(!can_write::<(u8,)>(&"Tuple(UInt8, String)".parse()?));
+        assert!(can_write::<Vec<Vec<Point>>>(&DataType::Polygon));
+        assert!(!can_write::<Vec<Point>>(&DataType::Polygon));
+        Ok(())
+    }
+
+    struct Event {
+        id: u64,
+        name: &'static str,
+    }
+
+    impl Row for Event {
+        const COLUMN_NAMES: &'static [&'static str] = &["id", "name"];
+
+        fn data_types() -> Vec<DataType> {
+            vec![u64::data_type(), <&str>::data_type()]
+        }
+
+        fn accepts(index: usize, data_type: &DataType) -> bool {
+            match index {
+                0 => can_write::<u64>(data_type),
+                1 => can_write::<&str>(data_type),
+                _ => false,
+            }
+        }
+
+        fn push_fields(self, columns: &mut [Column]) -> crate::Result<()> {
+            super::push_field(&mut columns[0], "id", self.id)?;
+            super::push_field(&mut columns[1], "name", self.name)
+        }
+    }
+
+    #[test]
+    fn test_append_row() -> Result<()> {
+        let mut columns = vec![
+            Column::new(&DataType::UInt64),
+            Column::new(&DataType::FixedString(2)),
+        ];
+        Event { id: 1, name: "ok" }.append_to(&mut columns)?;
+        let error = Event {
+            id: 2,
+            name: "too long",
+        }
+        .append_to(&mut columns)
+        .unwrap_err();
+        assert!(error.to_string().contains("column `name`"), "{error}");
+        assert_eq!(columns[0].len(), 1);
+        assert_eq!(columns[1].len(), 1);
+        assert!(Event { id: 3, name: "" }
+            .append_to(&mut columns[..1])
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1087 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{DataTypeError, Result};
+
+/// A ClickHouse column type, as found in the `type` field of a native block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataType {
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    UInt128,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Int128,
+    Float32,
+    Float64,
+    Bool,
+    String,
+    FixedString(usize),
+    Date,
+    Date32,
+    DateTime(Option<String>),
+    DateTime64(u8, Option<String>),
+    Uuid,
+    IPv4,
+    IPv6,
+    /// `Enum8('name' = code, ...)`, sorted by code.
+    Enum8(Vec<(String, i8)>),
+    /// `Enum16('name' = code, ...)`, sorted by code.
+    Enum16(Vec<(String, i16)>),
+    Nullable(Box<DataType>),
+    Array(Box<DataType>),
+    /// `Nested(name T, ...)`, a group of arrays of equal length per row.
+    Nested(Vec<(String, DataType)>),
+    LowCardinality(Box<DataType>),
+    /// `Tuple(T, ...)` or `Tuple(name T, ...)`.
+    Tuple(Vec<(Option<String>, DataType)>),
+    Map(Box<DataType>, Box<DataType>),
+    /// `JSON` or `JSON(params, ...)`, with its parameters kept verbatim.
+    ///
+    /// Values are read and written in the string serialization, see
+    /// [`DataType::with_json_as_string`].
+    Json(Vec<String>),
+    /// `Variant(T, ...)`, with the types sorted by name like the server
+    /// does. The position of a type is its discriminator on the wire.
+    Variant(Vec<DataType>),
+    /// `Dynamic` or `Dynamic(max_types=N)`.
+    Dynamic(Option<usize>),
+    /// `Tuple(Float64, Float64)` on the wire.
+    Point,
+    /// `Array(Point)` on the wire.
+    Ring,
+    /// `Array(Point)` on the wire.
+    LineString,
+    /// `Array(LineString)` on the wire.
+    MultiLineString,
+    /// `Array(Ring)` on the wire, the outer ring followed by the holes.
+    Polygon,
+    /// `Array(Polygon)` on the wire.
+    MultiPolygon,
+    /// `AggregateFunction(f, T, ...)`, with the function and its parameters
+    /// kept verbatim, e.g. `quantiles(0.5, 0.9)`.
+    AggregateFunction(String, Vec<DataType>),
+    /// `SimpleAggregateFunction(f, T)`, whose values are plain values of `T`.
+    SimpleAggregateFunction(String, Box<DataType>),
+}
+
+impl DataType {
+    /// Whether values of this type can be `NULL`, which is the case for
+    /// `Nullable(T)`, `LowCardinality(Nullable(T))`, `Variant` and
+    /// `Dynamic`.
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            DataType::Nullable(_)
+            | DataType::Variant(_)
+            | DataType::Dynamic(_) => true,
+            DataType::LowCardinality(inner) => inner.is_nullable(),
+            _ => false,
+        }
+    }
+
+    /// Whether values of this type may be wrapped into `Nullable`.
+    ///
+    /// ClickHouse rejects `Nullable` around composite types, so we do too.
+    pub fn can_be_inside_nullable(&self) -> bool {
+        !matches!(
+            self,
+            DataType::Nullable(_)
+                | DataType::Array(_)
+                | DataType::Nested(_)
+                | DataType::LowCardinality(_)
+                | DataType::Tuple(_)
+                | DataType::Map(_, _)
+                | DataType::Json(_)
+                | DataType::Variant(_)
+                | DataType::Dynamic(_)
+                | DataType::Point
+                | DataType::Ring
+                | DataType::LineString
+                | DataType::MultiLineString
+                | DataType::Polygon
+                | DataType::MultiPolygon
+                | DataType::AggregateFunction(_, _)
+                | DataType::SimpleAggregateFunction(_, _)
+        )
+    }
+
+    /// Whether values of this type may be held by a `Variant` or `Dynamic`.
+    ///
+    /// Both have their own `NULL`, and do not nest.
+    pub fn can_be_inside_variant(&self) -> bool {
+        match self {
+            DataType::LowCardinality(inner) => inner.can_be_inside_variant(),
+            DataType::Nullable(_)
+            | DataType::Variant(_)
+            | DataType::Dynamic(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Whether values of this type may be dictionary encoded with
+    /// `LowCardinality`.
+    pub fn can_be_inside_low_cardinality(&self) -> bool {
+        match self {
+            DataType::Nullable(inner) => inner.can_be_inside_low_cardinality(),
+            DataType::String => true,
+            DataType::Enum8(_) | DataType::Enum16(_) => false,
+            data_type => data_type.fixed_size().is_some(),
+        }
+    }
+
+    /// Size in bytes of a single value, for fixed-width types.
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            DataType::UInt8
+            | DataType::Int8
+            | DataType::Bool
+            | DataType::Enum8(_) => Some(1),
+            DataType::UInt16
+            | DataType::Int16
+            | DataType::Date
+            | DataType::Enum16(_) => Some(2),
+            DataType::FixedString(size) => Some(*size),
+            DataType::UInt32
+            | DataType::Int32
+            | DataType::Float32
+            | DataType::Date32
+            | DataType::DateTime(_)
+            | DataType::IPv4 => Some(4),
+            DataType::UInt64
+            | DataType::Int64
+            | DataType::Float64
+            | DataType::DateTime64(_, _) => Some(8),
+            DataType::UInt128
+            | DataType::Int128
+            | DataType::Uuid
+            | DataType::IPv6 => Some(16),
+            DataType::String
+            | DataType::Nullable(_)
+            | DataType::Array(_)
+            | DataType::Nested(_)
+            | DataType::LowCardinality(_)
+            | DataType::Tuple(_)
+            | DataType::Map(_, _)
+            | DataType::Json(_)
+            | DataType::Variant(_)
+            | DataType::Dynamic(_)
+            | DataType::Point
+            | DataType::Ring
+            | DataType::LineString
+            | DataType::MultiLineString
+            | DataType::Polygon
+            | DataType::MultiPolygon
+            | DataType::AggregateFunction(_, _)
+            | DataType::SimpleAggregateFunction(_, _) => None,
+        }
+    }
+
+    /// This type with every `JSON` replaced by `String`.
+    ///
+    /// The column layer only handles JSON as text. The server sends it that
+    /// way with `output_format_native_write_json_as_string`, and converts
+    /// `String` columns into `JSON` ones on insert.
+    pub fn with_json_as_string(&self) -> DataType {
+        match self {
+            DataType::Json(_) => DataType::String,
+            DataType::Nullable(inner) => {
+                DataType::Nullable(Box::new(inner.with_json_as_string()))
+            }
+            DataType::Array(inner) => {
+                DataType::Array(Box::new(inner.with_json_as_string()))
+            }
+            DataType::Nested(fields) => DataType::Nested(
+                fields
+                    .iter()
+                    .map(|(name, data_type)| {
+                        (name.clone(), data_type.with_json_as_string())
+                    })
+                    .collect(),
+            ),
+            DataType::Tuple(elements) => DataType::Tuple(
+                elements
+                    .iter()
+                    .map(|(name, data_type)| {
+                        (name.clone(), data_type.with_json_as_string())
+                    })
+                    .collect(),
+            ),
+            DataType::Map(key, value) => DataType::Map(
+                key.clone(),
+                Box::new(value.with_json_as_string()),
+            ),
+            DataType::SimpleAggregateFunction(function, inner) => {
+                DataType::SimpleAggregateFunction(
+                    function.clone(),
+                    Box::new(inner.with_json_as_string()),
+                )
+            }
+            DataType::Variant(types) => DataType::Variant(sort_variant_types(
+                types.iter().map(DataType::with_json_as_string).collect(),
+            )),
+            data_type => data_type.clone(),
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::UInt8 => write!(f, "UInt8"),
+            DataType::UInt16 => write!(f, "UInt16"),
+            DataType::UInt32 => write!(f, "UInt32"),
+            DataType::UInt64 => write!(f, "UInt64"),
+            DataType::UInt128 => write!(f, "UInt128"),
+            DataType::Int8 => write!(f, "Int8"),
+            DataType::Int16 => write!(f, "Int16"),
+            DataType::Int32 => write!(f, "Int32"),
+            DataType::Int64 => write!(f, "Int64"),
+            DataType::Int128 => write!(f, "Int128"),
+            DataType::Float32 => write!(f, "Float32"),
+            DataType::Float64 => write!(f, "Float64"),
+            DataType::Bool => write!(f, "Bool"),
+            DataType::String => write!(f, "String"),
+            DataType::FixedString(size) => write!(f, "FixedString({size})"),
+            DataType::Date => write!(f, "Date"),
+            DataType::Date32 => write!(f, "Date32"),
+            DataType::DateTime(None) => write!(f, "DateTime"),
+            DataType::DateTime(Some(tz)) => {
+                write!(f, "DateTime({})", quote(tz))
+            }
+            DataType::DateTime64(precision, None) => {
+                write!(f, "DateTime64({precision})")
+            }
+            DataType::DateTime64(precision, Some(tz)) => {
+                write!(f, "DateTime64({precision}, {})", quote(tz))
+            }
+            DataType::Uuid => write!(f, "UUID"),
+            DataType::IPv4 => write!(f, "IPv4"),
+            DataType::IPv6 => write!(f, "IPv6"),
+            DataType::Enum8(variants) => {
+                write!(f, "Enum8(")?;
+                write_enum_variants(f, variants)?;
+                write!(f, ")")
+            }
+            DataType::Enum16(variants) => {
+                write!(f, "Enum16(")?;
+                write_enum_variants(f, variants)?;
+                write!(f, ")")
+            }
+            DataType::Nullable(inner) => write!(f, "Nullable({inner})"),
+            DataType::Array(inner) => write!(f, "Array({inner})"),
+            DataType::Nested(fields) => {
+                write!(f, "Nested(")?;
+                for (i, (name, data_type)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} {data_type}", quote_identifier(name))?;
+                }
+                write!(f, ")")
+            }
+            DataType::LowCardinality(inner) => {
+                write!(f, "LowCardinality({inner})")
+            }
+            DataType::Tuple(elements) => {
+                write!(f, "Tuple(")?;
+                for (i, (name, data_type)) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match name {
+                        Some(name) => {
+                            write!(f, "{} {data_type}", quote_identifier(name))?
+                        }
+                        None => write!(f, "{data_type}")?,
+                    }
+                }
+                write!(f, ")")
+            }
+            DataType::Map(key, value) => write!(f, "Map({key}, {value})"),
+            DataType::Json(params) if params.is_empty() => write!(f, "JSON"),
+            DataType::Json(params) => write!(f, "JSON({})", params.join(", ")),
+            DataType::Variant(types) => {
+                write!(f, "Variant(")?;
+                for (i, data_type) in types.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{data_type}")?;
+                }
+                write!(f, ")")
+            }
+            DataType::Dynamic(None) => write!(f, "Dynamic"),
+            DataType::Dynamic(Some(max_types)) => {
+                write!(f, "Dynamic(max_types={max_types})")
+            }
+            DataType::Point => write!(f, "Point"),
+            DataType::Ring => write!(f, "Ring"),
+            DataType::LineString => write!(f, "LineString"),
+            DataType::MultiLineString => write!(f, "MultiLineString"),
+            DataType::Polygon => write!(f, "Polygon"),
+            DataType::MultiPolygon => write!(f, "MultiPolygon"),
+            DataType::AggregateFunction(function, types) => {
+                write!(f, "AggregateFunction({function}")?;
+                for data_type in types {
+                    write!(f, ", {data_type}")?;
+                }
+                write!(f, ")")
+            }
+            DataType::SimpleAggregateFunction(function, inner) => {
+                write!(f, "SimpleAggregateFunction({function}, {inner})")
+            }
+        }
+    }
+}
+
+fn write_enum_variants<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    variants: &[(String, T)],
+) -> fmt::Result {
+    for (i, (name, code)) in variants.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{} = {code}", quote(name))?;
+    }
+    Ok(())
+}
+
+impl FromStr for DataType {
+    type Err = DataTypeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse(s)
+    }
+}
+
+fn parse(input: &str) -> Result<DataType> {
+    let (name, args) = split_type(input)?;
+    let error = |reason: &str| DataTypeError::ParseError {
+        input: input.to_owned(),
+        reason: reason.to_owned(),
+    };
+
+    let data_type = match (name, args.as_slice()) {
+        ("UInt8", []) => DataType::UInt8,
+        ("UInt16", []) => DataType::UInt16,
+        ("UInt32", []) => DataType::UInt32,
+        ("UInt64", []) => DataType::UInt64,
+        ("UInt128", []) => DataType::UInt128,
+        ("Int8", []) => DataType::Int8,
+        ("Int16", []) => DataType::Int16,
+        ("Int32", []) => DataType::Int32,
+        ("Int64", []) => DataType::Int64,
+        ("Int128", []) => DataType::Int128,
+        ("Float32", []) => DataType::Float32,
+        ("Float64", []) => DataType::Float64,
+        ("Bool", []) => DataType::Bool,
+        ("String", []) => DataType::String,
+        ("FixedString", [size]) => match size.parse::<usize>() {
+            Ok(size) if size > 0 => DataType::FixedString(size),
+            _ => return Err(error("FixedString size must be positive")),
+        },
+        ("Date", []) => DataType::Date,
+        ("Date32", []) => DataType::Date32,
+        ("DateTime", []) => DataType::DateTime(None),
+        ("DateTime", [tz]) => DataType::DateTime(Some(
+            unquote(tz).ok_or_else(|| error("expected quoted timezone"))?,
+        )),
+        ("DateTime64", [precision]) => {
+            DataType::DateTime64(parse_precision(precision, &error)?, None)
+        }
+        ("DateTime64", [precision, tz]) => DataType::DateTime64(
+            parse_precision(precision, &error)?,
+            Some(unquote(tz).ok_or_else(|| error("expected quoted timezone"))?),
+        ),
+        ("UUID", []) => DataType::Uuid,
+        ("IPv4", []) => DataType::IPv4,
+        ("IPv6", []) => DataType::IPv6,
+        ("Enum8", variants) if !variants.is_empty() => {
+            DataType::Enum8(parse_enum_variants(variants, &error)?)
+        }
+        ("Enum16", variants) if !variants.is_empty() => {
+            DataType::Enum16(parse_enum_variants(variants, &error)?)
+        }
+        // like the server, pick the narrowest type that fits all codes
+        ("Enum", variants) if !variants.is_empty() => {
+            match parse_enum_variants::<i8>(variants, &error) {
+                Ok(variants) => DataType::Enum8(variants),
+                Err(_) => {
+                    DataType::Enum16(parse_enum_variants(variants, &error)?)
+                }
+            }
+        }
+        ("Nullable", [inner]) => {
+            let inner = parse(inner)?;
+            if !inner.can_be_inside_nullable() {
+                return Err(error(&format!(
+                    "{inner} cannot be inside Nullable"
+                )));
+            }
+            DataType::Nullable(Box::new(inner))
+        }
+        ("Array", [inner]) => DataType::Array(Box::new(parse(inner)?)),
+        ("Nested", fields) if !fields.is_empty() => {
+            let mut result = Vec::with_capacity(fields.len());
+            for field in fields {
+                let (name, data_type) = split_field(field)
+                    .ok_or_else(|| error("expected `name Type` field"))?;
+                result.push((name, parse(data_type)?));
+            }
+            DataType::Nested(result)
+        }
+        ("LowCardinality", [inner]) => {
+            let inner = parse(inner)?;
+            if !inner.can_be_inside_low_cardinality() {
+                return Err(error(&format!(
+                    "{inner} cannot be inside LowCardinality"
+                )));
+            }
+            DataType::LowCardinality(Box::new(inner))
+        }
+        ("Tuple", elements) if !elements.is_empty() => {
+            let mut result = Vec::with_capacity(elements.len());
+            for element in elements {
+                // unnamed elements are plain types, named ones `name Type`
+                match parse(element) {
+                    Ok(data_type) => result.push((None, data_type)),
+                    Err(e) => match split_field(element) {
+                        Some((name, data_type)) => {
+                            result.push((Some(name), parse(data_type)?))
+                        }
+                        None => return Err(e),
+                    },
+                }
+            }
+            DataType::Tuple(result)
+        }
+        ("Map", [key, value]) => {
+            let key = parse(key)?;
+            let valid_key = match &key {
+                DataType::LowCardinality(inner) => {
+                    !matches!(**inner, DataType::Nullable(_))
+                }
+                DataType::Nullable(_) => false,
+                DataType::Enum8(_) | DataType::Enum16(_) => true,
+                key => key.can_be_inside_low_cardinality(),
+            };
+            if !valid_key {
+                return Err(error(&format!("{key} cannot be a Map key")));
+            }
+            DataType::Map(Box::new(key), Box::new(parse(value)?))
+        }
+        ("JSON", params) => {
+            DataType::Json(params.iter().map(|x| x.to_string()).collect())
+        }
+        ("Variant", types) if !types.is_empty() => {
+            let mut result = Vec::with_capacity(types.len());
+            for data_type in types {
+                let data_type = parse(data_type)?;
+                if !data_type.can_be_inside_variant() {
+                    return Err(error(&format!(
+                        "{data_type} cannot be inside Variant"
+                    )));
+                }
+                if result.contains(&data_type) {
+                    return Err(error(&format!("duplicate type {data_type}")));
+                }
+                result.push(data_type);
+            }
+            if result.len() > MAX_VARIANT_TYPES {
+                return Err(error(&format!(
+                    "Variant cannot have more than {MAX_VARIANT_TYPES} types"
+                )));
+            }
+            DataType::Variant(sort_variant_types(result))
+        }
+        ("Dynamic", []) => DataType::Dynamic(None),
+        ("Dynamic", [param]) => {
+            let max_types = param
+                .split_once('=')
+                .filter(|(name, _)| name.trim() == "max_types")
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                .filter(|max_types| *max_types < MAX_VARIANT_TYPES)
+                .ok_or_else(|| {
+                    error("expected `max_types=N` with N below 255")
+                })?;
+            DataType::Dynamic(Some(max_types))
+        }
+        ("Point", []) => DataType::Point,
+        ("Ring", []) => DataType::Ring,
+        ("LineString", []) => DataType::LineString,
+        ("MultiLineString", []) => DataType::MultiLineString,
+        ("Polygon", []) => DataType::Polygon,
+        ("MultiPolygon", []) => DataType::MultiPolygon,
+        ("AggregateFunction", [function, types @ ..]) => {
+            // the server only names a version when it is not the latest
+            if function.parse::<u64>().is_ok() {
+                return Err(error(
+                    "versioned AggregateFunction states are not supported",
+                ));
+            }
+            let types = types
+                .iter()
+                .map(|data_type| parse(data_type))
+                .collect::<Result<_>>()?;
+            DataType::AggregateFunction(function.to_string(), types)
+        }
+        ("SimpleAggregateFunction", [function, inner]) => {
+            DataType::SimpleAggregateFunction(
+                function.to_string(),
+                Box::new(parse(inner)?),
+            )
+        }
+        ("Object", _) => {
+            return Err(error("Object is not supported, use JSON instead"))
+        }
+        (name, _) if is_known(name) => {
+            return Err(error("unexpected number of type arguments"))
+        }
+        _ => return Err(error("unknown data type")),
+    };
+    Ok(data_type)
+}
+
+fn is_known(name: &str) -> bool {
+    matches!(
+        name,
+        "UInt8"
+            | "UInt16"
+            | "UInt32"
+            | "UInt64"
+            | "UInt128"
+            | "Int8"
+            | "Int16"
+            | "Int32"
+            | "Int64"
+            | "Int128"
+            | "Float32"
+            | "Float64"
+            | "Bool"
+            | "String"
+            | "FixedString"
+            | "Date"
+            | "Date32"
+            | "DateTime"
+            | "DateTime64"
+            | "UUID"
+            | "IPv4"
+            | "IPv6"
+            | "Enum"
+            | "Enum8"
+            | "Enum16"
+            | "Nullable"
+            | "Array"
+            | "Nested"
+            | "LowCardinality"
+            | "Tuple"
+            | "Map"
+            | "Variant"
+            | "Dynamic"
+            | "Point"
+            | "Ring"
+            | "LineString"
+            | "MultiLineString"
+            | "Polygon"
+            | "MultiPolygon"
+            | "AggregateFunction"
+            | "SimpleAggregateFunction"
+    )
+}
+
+/// Discriminator 255 marks `NULL`, which leaves room for 255 types.
+pub(crate) const MAX_VARIANT_TYPES: usize = 255;
+
+/// Orders `Variant` types by name, which is how the server assigns their
+/// discriminators.
+pub(crate) fn sort_variant_types(mut types: Vec<DataType>) -> Vec<DataType> {
+    types.sort_by_cached_key(DataType::to_string);
+    types
+}
+
+fn parse_precision(
+    input: &str,
+    error: &impl Fn(&str) -> DataTypeError,
+) -> Result<u8> {
+    match input.parse::<u8>() {
+        Ok(precision) if precision <= 9 => Ok(precision),
+        _ => Err(error("DateTime64 precision must be between 0 and 9")),
+    }
+}
+
+/// Parses the `'name' = code` arguments of an enum type.
+///
+/// Codes may be omitted from all variants, in which case they are numbered
+/// from 1 in order of declaration.
+fn parse_enum_variants<T>(
+    args: &[&str],
+    error: &impl Fn(&str) -> DataTypeError,
+) -> Result<Vec<(String, T)>>
+where
+    T: TryFrom<i64> + Ord + Copy,
+{
+    let mut variants = Vec::with_capacity(args.len());
+    let mut implicit = 0;
+    for (i, arg) in args.iter().enumerate() {
+        let (name, code) = split_enum_variant(arg)
+            .ok_or_else(|| error("expected `'name' = code` enum variant"))?;
+        implicit += code.is_none() as usize;
+        let code = match code {
+            Some(code) => code
+                .parse::<i64>()
+                .map_err(|_| error("enum code must be an integer"))?,
+            None => i as i64 + 1,
+        };
+        let code = T::try_from(code)
+            .map_err(|_| error("enum code is out of range"))?;
+        variants.push((name, code));
+    }
+    if implicit != 0 && implicit != variants.len() {
+        return Err(error("enum codes must be given for all variants or none"));
+    }
+    variants.sort_by_key(|(_, code)| *code);
+    if variants.windows(2).any(|w| w[0].1 == w[1].1) {
+        return Err(error("duplicate enum code"));
+    }
+    let mut names: Vec<_> = variants.iter().map(|(name, _)| name).collect();
+    names.sort();
+    if names.windows(2).any(|w| w[0] == w[1]) {
+        return Err(error("duplicate enum name"));
+    }
+    Ok(variants)
+}
+
+/// Splits `'name' = code` into the unquoted name and the code, if any.
+fn split_enum_variant(input: &str) -> Option<(String, Option<&str>)> {
+    let input = input.trim();
+    let rest = input.strip_prefix('\'')?;
+    let mut escaped = false;
+    let end = rest.char_indices().find_map(|(i, c)| match c {
+        _ if escaped => {
+            escaped = false;
+            None
+        }
+        '\\' => {
+            escaped = true;
+            None
+        }
+        '\'' => Some(i + 2),
+        _ => None,
+    })?;
+    let name = unquote(&input[..end])?;
+    let code = input[end..].trim();
+    if code.is_empty() {
+        return Some((name, None));
+    }
+    Some((name, Some(code.strip_prefix('=')?.trim())))
+}
+
+/// Splits `Name(arg1, arg2, ...)` into its name and top-level arguments.
+///
+/// Commas nested in parentheses or inside single-quoted literals do not
+/// separate arguments.
+pub(crate) fn split_type(input: &str) -> Result<(&str, Vec<&str>)> {
+    let input = input.trim();
+    let error = |reason: &str| DataTypeError::ParseError {
+        input: input.to_owned(),
+        reason: reason.to_owned(),
+    };
+
+    let Some(open) = input.find('(') else {
+        if input.is_empty() {
+            return Err(error("empty type name"));
+        }
+        return Ok((input, Vec::new()));
+    };
+    if !input.ends_with(')') {
+        return Err(error("unbalanced parentheses"));
+    }
+
+    let name = input[..open].trim();
+    let body = &input[open + 1..input.len() - 1];
+    let mut args = Vec::new();
+    let mut depth = 0_usize;
+    let mut in_quote = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        if in_quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '\'' => in_quote = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '\'' => in_quote = true,
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| error("unbalanced parentheses"))?;
+            }
+            ',' if depth == 0 => {
+                args.push(body[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_quote || depth != 0 {
+        return Err(error("unbalanced parentheses or quotes"));
+    }
+    let last = body[start..].trim();
+    if !last.is_empty() || !args.is_empty() {
+        args.push(last);
+    }
+    if args.iter().any(|arg| arg.is_empty()) {
+        return Err(error("empty type argument"));
+    }
+    Ok((name, args))
+}
+
+/// Splits a `name Type` element of `Nested` into its name and type.
+///
+/// Names may be quoted with backticks.
+pub(crate) fn split_field(input: &str) -> Option<(String, &str)> {
+    let input = input.trim();
+    if let Some(rest) = input.strip_prefix('`') {
+        let end = rest.find('`')?;
+        let data_type = rest[end + 1..].trim();
+        (!data_type.is_empty()).then(|| (rest[..end].to_owned(), data_type))
+    } else {
+        let (name, data_type) = input.split_once(char::is_whitespace)?;
+        Some((name.to_owned(), data_type.trim()))
+    }
+}
+
+/// Renders a name, wrapping it in backticks unless it is a plain
+/// identifier.
+pub fn quote_identifier(input: &str) -> String {
+    let plain = input
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        input.to_owned()
+    } else {
+        format!("`{}`", input.replace('\\', "\\\\").replace('`', "\\`"))
+    }
+}
+
+/// Strips single quotes from a ClickHouse string literal and resolves its
+/// backslash escapes.
+pub(crate) fn unquote(input: &str) -> Option<String> {
+    let inner = input.trim().strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            result.push(chars.next()?);
+        } else {
+            result.push(c);
+        }
+    }
+    Some(result)
+}
+
+/// Renders a string as a single-quoted ClickHouse literal.
+pub(crate) fn quote(input: &str) -> String {
+    let mut result = String::with_capacity(input.len() + 2);
+    result.push('\'');
+    for c in input.chars() {
+        if c == '\'' || c == '\\' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('\'');
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::DataType;
+
+    #[test]
+    fn test_parse_simple_types() -> Result<()> {
+        for name in [
+            "UInt8",
+            "UInt16",
+            "UInt32",
+            "UInt64",
+            "UInt128",
+            "Int8",
+            "Int16",
+            "Int32",
+            "Int64",
+            "Int128",
+            "Float32",
+            "Float64",
+            "Bool",
+            "String",
+            "Date",
+            "Date32",
+            "DateTime",
+            "UUID",
+            "IPv4",
+            "IPv6",
+            "FixedString(16)",
+        ] {
+            let data_type: DataType = name.parse()?;
+            assert_eq!(data_type.to_string(), name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_datetime() -> Result<()> {
+        assert_eq!(
+            "DateTime('Asia/Shanghai')".parse::<DataType>()?,
+            DataType::DateTime(Some("Asia/Shanghai".into()))
+        );
+        assert_eq!(
+            " DateTime64( 3 , 'UTC' ) ".parse::<DataType>()?,
+            DataType::DateTime64(3, Some("UTC".into()))
+        );
+        assert_eq!(
+            DataType::DateTime64(6, Some("UTC".into())).to_string(),
+            "DateTime64(6, 'UTC')"
+        );
+        assert!("DateTime64(10)".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_enum() -> Result<()> {
+        assert_eq!(
+            "Enum8('b' = 2, 'a\\'s' = -1)".parse::<DataType>()?,
+            DataType::Enum8(vec![("a's".into(), -1), ("b".into(), 2)])
+        );
+        assert_eq!(
+            "Enum16('a' = 1000)".parse::<DataType>()?.to_string(),
+            "Enum16('a' = 1000)"
+        );
+        assert_eq!(
+            "Enum('a', 'b')".parse::<DataType>()?,
+            DataType::Enum8(vec![("a".into(), 1), ("b".into(), 2)])
+        );
+        assert_eq!(
+            "Enum('a' = 1, 'b' = 300)".parse::<DataType>()?,
+            DataType::Enum16(vec![("a".into(), 1), ("b".into(), 300)])
+        );
+        assert!("Enum8('a' = 128)".parse::<DataType>().is_err());
+        assert!("Enum8('a' = 1, 'b' = 1)".parse::<DataType>().is_err());
+        assert!("Enum8('a' = 1, 'a' = 2)".parse::<DataType>().is_err());
+        assert!("Enum8(a = 1)".parse::<DataType>().is_err());
+        assert!("Enum8('a', 'b' = 1)".parse::<DataType>().is_err());
+        assert!("LowCardinality(Enum8('a' = 1))"
+            .parse::<DataType>()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nullable() -> Result<()> {
+        assert_eq!(
+            "Nullable(String)".parse::<DataType>()?,
+            DataType::Nullable(Box::new(DataType::String))
+        );
+        assert!("Nullable(Nullable(String))".parse::<DataType>().is_err());
+        assert!("Nullable(String".parse::<DataType>().is_err());
+        assert!("Nullable()".parse::<DataType>().is_err());
+        assert!("Nullable".parse::<DataType>().is_err());
+        assert!("Strin".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_array() -> Result<()> {
+        assert_eq!(
+            "Array(Array(UInt32))".parse::<DataType>()?,
+            DataType::Array(Box::new(DataType::Array(Box::new(
+                DataType::UInt32
+            ))))
+        );
+        assert_eq!(
+            "Array(Nullable(String))".parse::<DataType>()?.to_string(),
+            "Array(Nullable(String))"
+        );
+        assert!("Nullable(Array(String))".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tuple() -> Result<()> {
+        assert_eq!(
+            "Tuple(UInt8, Nullable(String))".parse::<DataType>()?,
+            DataType::Tuple(vec![
+                (None, DataType::UInt8),
+                (None, DataType::Nullable(Box::new(DataType::String))),
+            ])
+        );
+        let named: DataType = "Tuple(a UInt8, `b c` Array(String))".parse()?;
+        assert_eq!(named.to_string(), "Tuple(a UInt8, `b c` Array(String))");
+        assert!("Tuple(a Strin)".parse::<DataType>().is_err());
+        assert!("Nullable(Tuple(UInt8))".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_map() -> Result<()> {
+        assert_eq!(
+            "Map(LowCardinality(String), String)".parse::<DataType>()?,
+            DataType::Map(
+                Box::new(DataType::LowCardinality(Box::new(DataType::String))),
+                Box::new(DataType::String)
+            )
+        );
+        assert!("Map(Nullable(String), String)".parse::<DataType>().is_err());
+        assert!("Map(Array(UInt8), String)".parse::<DataType>().is_err());
+        assert!("Map(String)".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json() -> Result<()> {
+        assert_eq!("JSON".parse::<DataType>()?, DataType::Json(Vec::new()));
+        let data_type: DataType =
+            "JSON(max_dynamic_paths = 10, a.b UInt32, SKIP c)".parse()?;
+        assert_eq!(
+            data_type.to_string(),
+            "JSON(max_dynamic_paths = 10, a.b UInt32, SKIP c)"
+        );
+        assert_eq!(
+            "Map(String, Array(JSON))"
+                .parse::<DataType>()?
+                .with_json_as_string()
+                .to_string(),
+            "Map(String, Array(String))"
+        );
+        assert!("Nullable(JSON)".parse::<DataType>().is_err());
+        assert!("Object('json')".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_variant() -> Result<()> {
+        let data_type: DataType =
+            "Variant(UInt64, String, Array(UInt8))".parse()?;
+        assert_eq!(
+            data_type,
+            DataType::Variant(vec![
+                DataType::Array(Box::new(DataType::UInt8)),
+                DataType::String,
+                DataType::UInt64,
+            ])
+        );
+        assert_eq!(
+            data_type.to_string(),
+            "Variant(Array(UInt8), String, UInt64)"
+        );
+        assert!("Variant(String, String)".parse::<DataType>().is_err());
+        assert!("Variant(Nullable(String))".parse::<DataType>().is_err());
+        assert!("Nullable(Variant(String))".parse::<DataType>().is_err());
+
+        assert_eq!("Dynamic".parse::<DataType>()?, DataType::Dynamic(None));
+        let data_type: DataType = "Dynamic(max_types = 8)".parse()?;
+        assert_eq!(data_type, DataType::Dynamic(Some(8)));
+        assert_eq!(data_type.to_string(), "Dynamic(max_types=8)");
+        assert!("Dynamic(8)".parse::<DataType>().is_err());
+        assert!("Variant(Dynamic)".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_geo() -> Result<()> {
+        for name in [
+            "Point",
+            "Ring",
+            "LineString",
+            "MultiLineString",
+            "Polygon",
+            "MultiPolygon",
+        ] {
+            assert_eq!(name.parse::<DataType>()?.to_string(), name);
+        }
+        assert_eq!(
+            "Array(Polygon)".parse::<DataType>()?,
+            DataType::Array(Box::new(DataType::Polygon))
+        );
+        assert!("Nullable(Point)".parse::<DataType>().is_err());
+        assert!("Point(1)".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_aggregate_function() -> Result<()> {
+        let data_type: DataType =
+            "AggregateFunction(quantiles(0.5, 0.9), UInt64)".parse()?;
+        assert_eq!(
+            data_type,
+            DataType::AggregateFunction(
+                "quantiles(0.5, 0.9)".into(),
+                vec![DataType::UInt64]
+            )
+        );
+        assert_eq!(
+            data_type.to_string(),
+            "AggregateFunction(quantiles(0.5, 0.9), UInt64)"
+        );
+        assert_eq!(
+            "AggregateFunction(count)".parse::<DataType>()?,
+            DataType::AggregateFunction("count".into(), Vec::new())
+        );
+        assert!("AggregateFunction(1, sumMap, Array(UInt8))"
+            .parse::<DataType>()
+            .is_err());
+
+        let data_type: DataType =
+            "SimpleAggregateFunction(anyLast, Nullable(String))".parse()?;
+        assert_eq!(
+            data_type,
+            DataType::SimpleAggregateFunction(
+                "anyLast".into(),
+                Box::new(DataType::Nullable(Box::new(DataType::String)))
+            )
+        );
+        assert_eq!(
+            data_type.to_string(),
+            "SimpleAggregateFunction(anyLast, Nullable(String))"
+        );
+        assert!("SimpleAggregateFunction(sum)".parse::<DataType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nested() -> Result<()> {
+        let data_type: DataType =
+            "Nested(id UInt64, `tag name` Nullable(String))".parse()?;
+        assert_eq!(
+            data_type,
+            DataType::Nested(vec![
+                ("id".into(), DataType::UInt64),
+                (
+                    "tag name".into(),
+                    DataType::Nullable(Box::new(DataType::String))
+                ),
+            ])
+        );
+        assert_eq!(
+            data_type.to_string(),
+            "Nested(id UInt64, `tag name` Nullable(String))"
+        );
+        assert!("Nested(UInt64)".parse::<DataType>().is_err());
+        Ok(())
+    }
+}
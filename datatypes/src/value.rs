@@ -0,0 +1,926 @@
+use std::fmt::{self, Display, Formatter, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::column::convert::{get, mismatch};
+use crate::column::{
+    Column, DynamicColumn, FromColumn, IntoColumn, NestedColumn, Point,
+    VariantColumn,
+};
+use crate::error::{DataTypeError, Result};
+use crate::types::DataType;
+
+/// A single value of any type, for results whose schema is only known at
+/// runtime.
+///
+/// `LowCardinality`, `Variant` and `Dynamic` values are the value they hold,
+/// and so are `Nullable` ones unless they are `NULL`. The geo types are
+/// arrays of `(x, y)` tuples, and `Nested` values arrays of tuples.
+///
+/// Values display in the literal syntax of the `Values` format, so `'a'`,
+/// `[1,2]` or `{'key':NULL}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    UInt128(u128),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Int128(i128),
+    Float32(f32),
+    Float64(f64),
+    Bool(bool),
+    /// `String` values, `FixedString` values with their zero padding and
+    /// `AggregateFunction` states, none of which have to be UTF-8.
+    String(Bytes),
+    /// Days since 1970-01-01.
+    Date(u16),
+    /// Days since 1970-01-01, may be negative.
+    Date32(i32),
+    /// Seconds since the unix epoch.
+    DateTime {
+        value: u32,
+        tz: Option<String>,
+    },
+    /// Ticks of `10^-precision` seconds since the unix epoch.
+    DateTime64 {
+        value: i64,
+        precision: u8,
+        tz: Option<String>,
+    },
+    Uuid(Uuid),
+    IPv4(Ipv4Addr),
+    IPv6(Ipv6Addr),
+    Enum8 {
+        name: String,
+        code: i8,
+    },
+    Enum16 {
+        name: String,
+        code: i16,
+    },
+    /// A JSON document as text.
+    Json(String),
+    Array(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// The entries of a map, in order.
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The type a value is naturally stored as, which is what it is written
+    /// to `Dynamic` columns as.
+    ///
+    /// `None` where the type cannot be told, such as for `NULL` or an empty
+    /// array.
+    pub fn data_type(&self) -> Option<DataType> {
+        let data_type = match self {
+            Value::Null => return None,
+            Value::UInt8(_) => DataType::UInt8,
+            Value::UInt16(_) => DataType::UInt16,
+            Value::UInt32(_) => DataType::UInt32,
+            Value::UInt64(_) => DataType::UInt64,
+            Value::UInt128(_) => DataType::UInt128,
+            Value::Int8(_) => DataType::Int8,
+            Value::Int16(_) => DataType::Int16,
+            Value::Int32(_) => DataType::Int32,
+            Value::Int64(_) => DataType::Int64,
+            Value::Int128(_) => DataType::Int128,
+            Value::Float32(_) => DataType::Float32,
+            Value::Float64(_) => DataType::Float64,
+            Value::Bool(_) => DataType::Bool,
+            Value::String(_) => DataType::String,
+            Value::Date(_) => DataType::Date,
+            Value::Date32(_) => DataType::Date32,
+            Value::DateTime { tz, .. } => DataType::DateTime(tz.clone()),
+            Value::DateTime64 { precision, tz, .. } => {
+                DataType::DateTime64(*precision, tz.clone())
+            }
+            Value::Uuid(_) => DataType::Uuid,
+            Value::IPv4(_) => DataType::IPv4,
+            Value::IPv6(_) => DataType::IPv6,
+            Value::Enum8 { name, code } => {
+                DataType::Enum8(vec![(name.clone(), *code)])
+            }
+            Value::Enum16 { name, code } => {
+                DataType::Enum16(vec![(name.clone(), *code)])
+            }
+            Value::Json(_) => DataType::Json(Vec::new()),
+            Value::Array(values) => {
+                DataType::Array(Box::new(common_type(values.iter())?))
+            }
+            Value::Tuple(values) => DataType::Tuple(
+                values
+                    .iter()
+                    .map(|value| Some((None, value.data_type()?)))
+                    .collect::<Option<_>>()?,
+            ),
+            Value::Map(entries) => DataType::Map(
+                Box::new(common_type(entries.iter().map(|(key, _)| key))?),
+                Box::new(common_type(entries.iter().map(|(_, value)| value))?),
+            ),
+        };
+        Some(data_type)
+    }
+
+    /// The value as the server parses a query parameter from, in the
+    /// escaped text format: strings without quotes, `NULL` as `\N`, and
+    /// points in time as unix timestamps so that they mean the same instant
+    /// in the time zone of any type.
+    pub fn to_parameter(&self) -> String {
+        match self {
+            Value::Null => "\\N".to_owned(),
+            Value::String(value) => Escaped(value).to_string(),
+            Value::Enum8 { name, .. } | Value::Enum16 { name, .. } => {
+                Escaped(name.as_bytes()).to_string()
+            }
+            Value::Json(value) => Escaped(value.as_bytes()).to_string(),
+            // quoted literals that have nothing to escape
+            Value::Date(_)
+            | Value::Date32(_)
+            | Value::Uuid(_)
+            | Value::IPv4(_)
+            | Value::IPv6(_) => self.to_string().trim_matches('\'').to_owned(),
+            value => Parameter(value).to_string(),
+        }
+    }
+}
+
+/// A value inside a query parameter, written like a literal but with
+/// points in time as unix timestamps.
+struct Parameter<'a>(&'a Value);
+
+impl Display for Parameter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let separated = |f: &mut Formatter<'_>, values: &[Value]| {
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                Parameter(value).fmt(f)?;
+            }
+            Ok(())
+        };
+        match self.0 {
+            Value::DateTime { value, .. } => value.fmt(f),
+            Value::DateTime64 {
+                value, precision, ..
+            } => {
+                let factor = 10_u64.pow((*precision).into());
+                let sign = if *value < 0 { "-" } else { "" };
+                let (seconds, ticks) = (
+                    value.unsigned_abs() / factor,
+                    value.unsigned_abs() % factor,
+                );
+                write!(f, "{sign}{seconds}")?;
+                if *precision > 0 {
+                    write!(f, ".{ticks:0width$}", width = *precision as usize)?;
+                }
+                Ok(())
+            }
+            Value::Array(values) => {
+                f.write_char('[')?;
+                separated(f, values)?;
+                f.write_char(']')
+            }
+            Value::Tuple(values) => {
+                f.write_char('(')?;
+                separated(f, values)?;
+                f.write_char(')')
+            }
+            Value::Map(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}:{}", Parameter(key), Parameter(value))?;
+                }
+                f.write_char('}')
+            }
+            value => value.fmt(f),
+        }
+    }
+}
+
+/// The type of the first value that is not `NULL`, made `Nullable` if any
+/// value is.
+fn common_type<'a>(
+    mut values: impl Iterator<Item = &'a Value> + Clone,
+) -> Option<DataType> {
+    let data_type = values.clone().find_map(Value::data_type)?;
+    if values.any(Value::is_null) && data_type.can_be_inside_nullable() {
+        return Some(DataType::Nullable(Box::new(data_type)));
+    }
+    Some(data_type)
+}
+
+macro_rules! impl_from {
+    ($($ty:ty => $variant:ident),+) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+    u64 => UInt64,
+    u128 => UInt128,
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    i64 => Int64,
+    i128 => Int128,
+    f32 => Float32,
+    f64 => Float64,
+    bool => Bool,
+    Bytes => String,
+    String => String,
+    Uuid => Uuid,
+    Ipv4Addr => IPv4,
+    Ipv6Addr => IPv6
+);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<Point> for Value {
+    fn from(point: Point) -> Self {
+        Value::Tuple(vec![Value::Float64(point.x), Value::Float64(point.y)])
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'a> FromColumn<'a> for Value {
+    fn from_column(column: &'a Column, row: usize) -> Result<Self> {
+        if row >= column.len() {
+            return Err(DataTypeError::RowOutOfBounds {
+                row,
+                len: column.len(),
+            });
+        }
+        let value = match column {
+            Column::UInt8(data) => Value::UInt8(data[row]),
+            Column::UInt16(data) => Value::UInt16(data[row]),
+            Column::UInt32(data) => Value::UInt32(data[row]),
+            Column::UInt64(data) => Value::UInt64(data[row]),
+            Column::UInt128(data) => Value::UInt128(data[row]),
+            Column::Int8(data) => Value::Int8(data[row]),
+            Column::Int16(data) => Value::Int16(data[row]),
+            Column::Int32(data) => Value::Int32(data[row]),
+            Column::Int64(data) => Value::Int64(data[row]),
+            Column::Int128(data) => Value::Int128(data[row]),
+            Column::Float32(data) => Value::Float32(data[row]),
+            Column::Float64(data) => Value::Float64(data[row]),
+            Column::Bool(data) => Value::Bool(data[row]),
+            Column::String(data) => Value::String(data[row].clone()),
+            Column::FixedString(values) => {
+                Value::String(Bytes::copy_from_slice(values.value(row)?))
+            }
+            Column::Date(data) => Value::Date(data[row]),
+            Column::Date32(data) => Value::Date32(data[row]),
+            Column::DateTime { tz, data } => Value::DateTime {
+                value: data[row],
+                tz: tz.clone(),
+            },
+            Column::DateTime64 {
+                precision,
+                tz,
+                data,
+            } => Value::DateTime64 {
+                value: data[row],
+                precision: *precision,
+                tz: tz.clone(),
+            },
+            Column::Uuid(data) => Value::Uuid(data[row]),
+            Column::IPv4(data) => Value::IPv4(data[row]),
+            Column::IPv6(data) => Value::IPv6(data[row]),
+            Column::Enum8(values) => {
+                let code = values.data[row];
+                let name = values.name(code).expect("enum codes are validated");
+                Value::Enum8 {
+                    name: name.to_owned(),
+                    code,
+                }
+            }
+            Column::Enum16(values) => {
+                let code = values.data[row];
+                let name = values.name(code).expect("enum codes are validated");
+                Value::Enum16 {
+                    name: name.to_owned(),
+                    code,
+                }
+            }
+            Column::Nullable(nullable) if nullable.is_null(row) => Value::Null,
+            Column::Nullable(nullable) => nullable.inner().get(row)?,
+            Column::Array(array)
+            | Column::Ring(array)
+            | Column::LineString(array)
+            | Column::MultiLineString(array)
+            | Column::Polygon(array)
+            | Column::MultiPolygon(array) => Value::Array(
+                array
+                    .range(row)?
+                    .map(|i| array.inner().get(i))
+                    .collect::<Result<_>>()?,
+            ),
+            Column::Nested(nested) => Value::Array(
+                nested
+                    .range(row)?
+                    .map(|i| {
+                        let fields = nested.fields().iter();
+                        fields
+                            .map(|(_, column)| column.get(i))
+                            .collect::<Result<_>>()
+                            .map(Value::Tuple)
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Column::LowCardinality(column) => {
+                column.dictionary().get(column.key(row)?)?
+            }
+            Column::Tuple(tuple) => Value::Tuple(
+                tuple
+                    .elements()
+                    .iter()
+                    .map(|(_, column)| column.get(row))
+                    .collect::<Result<_>>()?,
+            ),
+            Column::Map(map) => Value::Map(
+                map.entries()
+                    .range(row)?
+                    .map(|i| Ok((map.keys().get(i)?, map.values().get(i)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Column::Json { data, .. } => Value::Json(data[row].clone()),
+            Column::Variant(variant) => match variant.value(row) {
+                Some((column, row)) => column.get(row)?,
+                None => Value::Null,
+            },
+            // values of the shared variant are their encoded type and value
+            Column::Dynamic(dynamic) => {
+                match (dynamic.value(row), dynamic.shared(row)) {
+                    (Some((column, row)), _) => column.get(row)?,
+                    (None, Some(shared)) => {
+                        Value::String(Bytes::copy_from_slice(shared))
+                    }
+                    (None, None) => Value::Null,
+                }
+            }
+            Column::Point(data) => data[row].into(),
+            Column::AggregateFunction(states) => {
+                Value::String(get(states.states(), row)?.clone())
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Writes values to columns of their own type or of one stored the same
+/// way, so an `Int8` to an `Enum8` column or a `String` to a `JSON` one.
+///
+/// If the value does not fit the column, the column is left unchanged.
+impl IntoColumn for Value {
+    fn append_to(self, column: &mut Column) -> Result<()> {
+        match column {
+            Column::LowCardinality(_) => return column.push(self),
+            Column::Nullable(nullable) if self.is_null() => {
+                nullable.push_null();
+                return Ok(());
+            }
+            Column::Nullable(_) => return Some(self).append_to(column),
+            Column::Variant(variant) => return push_variant(variant, self),
+            Column::Dynamic(dynamic) => return push_dynamic(dynamic, self),
+            _ => {}
+        }
+        match self {
+            Value::Null => Err(mismatch::<Option<Value>>(column)),
+            Value::UInt8(value) => value.append_to(column),
+            Value::UInt16(value) | Value::Date(value) => {
+                value.append_to(column)
+            }
+            Value::UInt32(value) | Value::DateTime { value, .. } => {
+                value.append_to(column)
+            }
+            Value::UInt64(value) => value.append_to(column),
+            Value::UInt128(value) => value.append_to(column),
+            Value::Int8(value) => value.append_to(column),
+            Value::Int16(value) => value.append_to(column),
+            Value::Int32(value) | Value::Date32(value) => {
+                value.append_to(column)
+            }
+            Value::Int64(value) => value.append_to(column),
+            Value::Int128(value) => value.append_to(column),
+            Value::Float32(value) => value.append_to(column),
+            Value::Float64(value) => value.append_to(column),
+            Value::Bool(value) => value.append_to(column),
+            Value::String(value) => match column {
+                Column::Enum8(_) | Column::Enum16(_) | Column::Json { .. } => {
+                    std::str::from_utf8(&value)?.append_to(column)
+                }
+                _ => value.append_to(column),
+            },
+            Value::DateTime64 {
+                value, precision, ..
+            } => match column {
+                Column::DateTime64 { precision: to, .. } => {
+                    rescale(value, precision, *to)?.append_to(column)
+                }
+                _ => value.append_to(column),
+            },
+            Value::Uuid(value) => value.append_to(column),
+            Value::IPv4(value) => value.append_to(column),
+            Value::IPv6(value) => value.append_to(column),
+            Value::Enum8 { name, code } => match column {
+                Column::Int8(_) => code.append_to(column),
+                _ => name.append_to(column),
+            },
+            Value::Enum16 { name, code } => match column {
+                Column::Int16(_) => code.append_to(column),
+                _ => name.append_to(column),
+            },
+            Value::Json(value) => value.append_to(column),
+            Value::Array(values) => match column {
+                Column::Nested(nested) => push_nested(nested, values),
+                Column::Map(_) => Err(mismatch::<Self>(column)),
+                _ => values.append_to(column),
+            },
+            Value::Tuple(values) => match column {
+                Column::Tuple(tuple)
+                    if tuple.elements.len() == values.len() =>
+                {
+                    tuple.push_with(|elements| {
+                        elements.iter_mut().zip(values).try_for_each(
+                            |((_, column), value)| column.push(value),
+                        )
+                    })
+                }
+                Column::Point(data) => match values[..] {
+                    [Value::Float64(x), Value::Float64(y)] => {
+                        data.push(Point { x, y });
+                        Ok(())
+                    }
+                    _ => Err(mismatch::<Self>(column)),
+                },
+                _ => Err(mismatch::<Self>(column)),
+            },
+            Value::Map(entries) => match column {
+                Column::Map(_) => entries.append_to(column),
+                _ => Err(mismatch::<Self>(column)),
+            },
+        }
+    }
+}
+
+/// Converts ticks of `10^-from` seconds to ticks of `10^-to` seconds,
+/// failing rather than losing precision.
+fn rescale(value: i64, from: u8, to: u8) -> Result<i64> {
+    let scale = |digits: u8| 10_i64.checked_pow(digits.into());
+    let rescaled = if to >= from {
+        scale(to - from).and_then(|factor| value.checked_mul(factor))
+    } else {
+        scale(from - to)
+            .filter(|factor| value % factor == 0)
+            .map(|factor| value / factor)
+    };
+    rescaled.ok_or_else(|| {
+        DataTypeError::EncodeError(format!(
+            "cannot convert DateTime64({from}) value {value} to \
+             DateTime64({to})"
+        ))
+    })
+}
+
+/// Appends a value as the first type of the variant that it fits.
+fn push_variant(variant: &mut VariantColumn, value: Value) -> Result<()> {
+    if value.is_null() {
+        variant.push_null();
+        return Ok(());
+    }
+    for index in 0..variant.variants().len() {
+        if variant.push(index, value.clone()).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(DataTypeError::EncodeError(format!(
+        "{value} does not fit any type of {}",
+        variant.data_type()
+    )))
+}
+
+fn push_dynamic(dynamic: &mut DynamicColumn, value: Value) -> Result<()> {
+    if value.is_null() {
+        dynamic.push_null();
+        return Ok(());
+    }
+    let Some(data_type) = value.data_type() else {
+        return Err(DataTypeError::EncodeError(format!(
+            "cannot tell the type of {value} to write it to Dynamic"
+        )));
+    };
+    dynamic.push(&data_type, value)
+}
+
+/// Appends a row of tuples, one element per field.
+fn push_nested(nested: &mut NestedColumn, rows: Vec<Value>) -> Result<()> {
+    let len = nested.len();
+    let result = rows.into_iter().try_for_each(|row| match row {
+        Value::Tuple(values) if values.len() == nested.fields.len() => nested
+            .fields
+            .iter_mut()
+            .zip(values)
+            .try_for_each(|((_, column), value)| column.push(value)),
+        row => Err(DataTypeError::EncodeError(format!(
+            "{row} is not a row of {}",
+            nested.data_type()
+        ))),
+    });
+    if let Err(e) = result {
+        nested.truncate(len);
+        return Err(e);
+    }
+    let end = nested.fields.first().map_or(0, |(_, column)| column.len());
+    nested.offsets.push(end as u64);
+    Ok(())
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::UInt8(value) => value.fmt(f),
+            Value::UInt16(value) => value.fmt(f),
+            Value::UInt32(value) => value.fmt(f),
+            Value::UInt64(value) => value.fmt(f),
+            Value::UInt128(value) => value.fmt(f),
+            Value::Int8(value) => value.fmt(f),
+            Value::Int16(value) => value.fmt(f),
+            Value::Int32(value) => value.fmt(f),
+            Value::Int64(value) => value.fmt(f),
+            Value::Int128(value) => value.fmt(f),
+            Value::Float32(value) if value.is_nan() => f.write_str("nan"),
+            Value::Float32(value) => value.fmt(f),
+            Value::Float64(value) if value.is_nan() => f.write_str("nan"),
+            Value::Float64(value) => value.fmt(f),
+            Value::Bool(value) => value.fmt(f),
+            Value::String(value) => write_quoted(f, value),
+            Value::Date(days) => write!(f, "'{}'", Date(*days as i64)),
+            Value::Date32(days) => write!(f, "'{}'", Date(*days as i64)),
+            Value::DateTime { value, tz } => {
+                write_date_time(f, *value as i64, 0, 0, tz.as_deref())
+            }
+            Value::DateTime64 {
+                value,
+                precision,
+                tz,
+            } => {
+                let factor = 10_i64.pow((*precision).into());
+                let (seconds, ticks) =
+                    (value.div_euclid(factor), value.rem_euclid(factor));
+                write_date_time(f, seconds, ticks, *precision, tz.as_deref())
+            }
+            Value::Uuid(value) => write!(f, "'{value}'"),
+            Value::IPv4(value) => write!(f, "'{value}'"),
+            Value::IPv6(value) => write!(f, "'{value}'"),
+            Value::Enum8 { name, .. } | Value::Enum16 { name, .. } => {
+                write_quoted(f, name.as_bytes())
+            }
+            Value::Json(value) => write_quoted(f, value.as_bytes()),
+            Value::Array(values) => {
+                f.write_char('[')?;
+                write_separated(f, values)?;
+                f.write_char(']')
+            }
+            Value::Tuple(values) => {
+                f.write_char('(')?;
+                write_separated(f, values)?;
+                f.write_char(')')
+            }
+            Value::Map(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{key}:{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_separated(f: &mut Formatter<'_>, values: &[Value]) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        value.fmt(f)?;
+    }
+    Ok(())
+}
+
+/// Writes a string literal, escaping quotes, backslashes, control
+/// characters and bytes that are not UTF-8.
+fn write_quoted(f: &mut Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "'{}'", Escaped(bytes))
+}
+
+/// A string with the escapes of a literal, without its quotes.
+struct Escaped<'a>(&'a [u8]);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\'' => f.write_str("\\'")?,
+                    '\\' => f.write_str("\\\\")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    '\0' => f.write_str("\\0")?,
+                    c if c.is_ascii_control() => {
+                        write!(f, "\\x{:02X}", c as u8)?
+                    }
+                    c => f.write_char(c)?,
+                }
+            }
+            for byte in chunk.invalid() {
+                write!(f, "\\x{byte:02X}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a point in time as a literal that means the same instant in any
+/// time zone.
+///
+/// Without a time zone database the time can only be written in UTC, which
+/// a plain literal would be read in the time zone of the column instead.
+fn write_date_time(
+    f: &mut Formatter<'_>,
+    seconds: i64,
+    ticks: i64,
+    precision: u8,
+    tz: Option<&str>,
+) -> fmt::Result {
+    let utc = matches!(tz, Some("UTC" | "Etc/UTC" | "GMT" | "Etc/GMT"));
+    let time = seconds.rem_euclid(86400);
+    let mut literal = format!(
+        "'{} {:02}:{:02}:{:02}",
+        Date(seconds.div_euclid(86400)),
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+    if precision > 0 {
+        write!(literal, ".{ticks:0width$}", width = precision as usize)?;
+    }
+    literal.push('\'');
+    match (utc, precision) {
+        (true, _) => f.write_str(&literal),
+        (false, 0) => write!(f, "toDateTime({literal}, 'UTC')"),
+        (false, precision) => {
+            write!(f, "toDateTime64({literal}, {precision}, 'UTC')")
+        }
+    }
+}
+
+/// Days since 1970-01-01, displayed as `YYYY-MM-DD`.
+struct Date(i64);
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // the civil from days algorithm of Howard Hinnant
+        let z = self.0 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    use super::Value;
+    use crate::{Column, DataType};
+
+    #[test]
+    fn test_display() {
+        for (value, expected) in [
+            (Value::Null, "NULL"),
+            (Value::Float64(f64::NAN), "nan"),
+            (Value::Float32(-f32::INFINITY), "-inf"),
+            ("it's \\ \n".into(), r"'it\'s \\ \n'"),
+            (
+                Value::String(Bytes::from_static(b"\xff\x01a")),
+                r"'\xFF\x01a'",
+            ),
+            (Value::Date(19723), "'2024-01-01'"),
+            (Value::Date32(-1), "'1969-12-31'"),
+            (
+                Value::DateTime {
+                    value: 1704067199,
+                    tz: Some("UTC".into()),
+                },
+                "'2023-12-31 23:59:59'",
+            ),
+            (
+                Value::DateTime { value: 0, tz: None },
+                "toDateTime('1970-01-01 00:00:00', 'UTC')",
+            ),
+            (
+                Value::DateTime64 {
+                    value: -1,
+                    precision: 3,
+                    tz: Some("Europe/Berlin".into()),
+                },
+                "toDateTime64('1969-12-31 23:59:59.999', 3, 'UTC')",
+            ),
+            (
+                Value::Enum8 {
+                    name: "a'b".into(),
+                    code: 1,
+                },
+                r"'a\'b'",
+            ),
+            (Value::Array(vec![Value::Null, 1u8.into()]), "[NULL,1]"),
+            (
+                Value::Map(vec![(
+                    "k".into(),
+                    Value::Tuple(vec![1.5f64.into(), true.into()]),
+                )]),
+                "{'k':(1.5,true)}",
+            ),
+        ] {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_to_parameter() {
+        for (value, expected) in [
+            (Value::Null, r"\N"),
+            (42u64.into(), "42"),
+            ("it's \\ \t".into(), r"it\'s \\ \t"),
+            (Value::Date(19723), "2024-01-01"),
+            (
+                Value::DateTime {
+                    value: 1704067199,
+                    tz: Some("Asia/Tokyo".into()),
+                },
+                "1704067199",
+            ),
+            (
+                Value::DateTime64 {
+                    value: -1,
+                    precision: 3,
+                    tz: None,
+                },
+                "-0.001",
+            ),
+            (
+                Value::Array(vec![
+                    "a'b".into(),
+                    Value::Null,
+                    Value::DateTime { value: 1, tz: None },
+                ]),
+                r"['a\'b',NULL,1]",
+            ),
+            (
+                Value::Map(vec![("k".into(), 1.5f64.into())]),
+                "{'k':1.5}",
+            ),
+        ] {
+            assert_eq!(value.to_parameter(), expected);
+        }
+    }
+
+    /// Every column reads back the values it was written.
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for (data_type, values) in
+            [
+                (
+                    "LowCardinality(Nullable(String))",
+                    vec![Value::Null, "a".into(), "a".into()],
+                ),
+                (
+                    "Enum8('a' = 1, 'b' = 2)",
+                    vec![Value::Enum8 {
+                        name: "b".into(),
+                        code: 2,
+                    }],
+                ),
+                (
+                    "DateTime64(3, 'UTC')",
+                    vec![Value::DateTime64 {
+                        value: 1500,
+                        precision: 3,
+                        tz: Some("UTC".into()),
+                    }],
+                ),
+                (
+                    "Map(String, Array(UInt8))",
+                    vec![Value::Map(vec![("a".into(), vec![1u8, 2].into())])],
+                ),
+                (
+                    "Tuple(a Int32, b Nullable(Float64))",
+                    vec![Value::Tuple(vec![1i32.into(), Value::Null])],
+                ),
+                (
+                    "Nested(a UInt8, b String)",
+                    vec![Value::Array(vec![Value::Tuple(vec![
+                        1u8.into(),
+                        "x".into(),
+                    ])])],
+                ),
+                (
+                    "Variant(String, UInt64)",
+                    vec![42u64.into(), Value::Null, "x".into()],
+                ),
+                ("Dynamic", vec![vec![Some(1i64), None].into(), Value::Null]),
+                (
+                    "Polygon",
+                    vec![Value::Array(vec![Value::Array(vec![
+                        crate::Point { x: 1.0, y: 2.0 }.into(),
+                    ])])],
+                ),
+            ]
+        {
+            let data_type: DataType = data_type.parse()?;
+            let mut column = Column::new(&data_type);
+            for value in &values {
+                column.push(value.clone())?;
+            }
+            let read = (0..column.len())
+                .map(|row| column.get::<Value>(row))
+                .collect::<crate::Result<Vec<_>>>()?;
+            assert_eq!(read, values, "{data_type}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_mismatch() -> Result<()> {
+        let mut column = Column::new(&"Tuple(UInt8, String)".parse()?);
+        assert!(column
+            .push(Value::Tuple(vec![1u8.into(), 2u8.into()]))
+            .is_err());
+        assert!(column.push(Value::Null).is_err());
+        assert_eq!(column.len(), 0);
+
+        let mut column = Column::new(&"DateTime64(1)".parse()?);
+        let value = |value| Value::DateTime64 {
+            value,
+            precision: 3,
+            tz: None,
+        };
+        column.push(value(1200))?;
+        assert!(column.push(value(1234)).is_err());
+        assert_eq!(column.get::<i64>(0)?, 12);
+        Ok(())
+    }
+}
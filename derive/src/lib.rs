@@ -0,0 +1,253 @@
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Ident,
+    LitStr,
+};
+
+/// Maps a Rust enum of unit variants onto an `Enum8` or `Enum16` column.
+///
+/// Variants are matched by name, so the Rust discriminants do not have to
+/// agree with the codes of the ClickHouse type. Use
+/// `#[clickhouse(rename = "name")]` on a variant whose ClickHouse name
+/// differs from the Rust one.
+#[proc_macro_derive(ClickHouseEnum, attributes(clickhouse))]
+pub fn derive_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_enum(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_enum(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            ident,
+            "ClickHouseEnum can only be derived for enums",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "ClickHouseEnum cannot be derived for generic enums",
+        ));
+    }
+
+    let mut variants = Vec::with_capacity(data.variants.len());
+    let mut names = Vec::with_capacity(data.variants.len());
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "ClickHouseEnum variants cannot have fields",
+            ));
+        }
+        variants.push(&variant.ident);
+        names.push(clickhouse_name(&variant.ident, &variant.attrs)?);
+    }
+
+    // codes are only used for the type of new columns, existing enums are
+    // matched by name
+    let (enum_type, codes) = if names.len() <= i8::MAX as usize {
+        let codes = (1..=names.len() as i8).map(Literal::i8_suffixed);
+        (quote!(Enum8), codes.collect::<Vec<_>>())
+    } else if names.len() <= i16::MAX as usize {
+        let codes = (1..=names.len() as i16).map(Literal::i16_suffixed);
+        (quote!(Enum16), codes.collect())
+    } else {
+        return Err(Error::new_spanned(
+            ident,
+            format!("ClickHouseEnum supports at most {} variants", i16::MAX),
+        ));
+    };
+
+    Ok(quote! {
+        impl<'a> ::clickhouse_datatypes::FromColumn<'a> for #ident {
+            fn from_column(
+                column: &'a ::clickhouse_datatypes::Column,
+                row: usize,
+            ) -> ::clickhouse_datatypes::Result<Self> {
+                let name: &str =
+                    ::clickhouse_datatypes::FromColumn::from_column(column, row)?;
+                match name {
+                    #(#names => Ok(#ident::#variants),)*
+                    _ => Err(::clickhouse_datatypes::DataTypeError::DecodeError(
+                        format!(
+                            "{name:?} is not a variant of {}",
+                            stringify!(#ident),
+                        ),
+                    )),
+                }
+            }
+        }
+
+        impl ::clickhouse_datatypes::IntoColumn for #ident {
+            fn append_to(
+                self,
+                column: &mut ::clickhouse_datatypes::Column,
+            ) -> ::clickhouse_datatypes::Result<()> {
+                let name: &str = match self {
+                    #(#ident::#variants => #names,)*
+                };
+                ::clickhouse_datatypes::IntoColumn::append_to(name, column)
+            }
+        }
+
+        impl ::clickhouse_datatypes::ColumnType for #ident {
+            fn data_type() -> ::clickhouse_datatypes::DataType {
+                ::clickhouse_datatypes::DataType::#enum_type(vec![
+                    #((#names.to_owned(), #codes),)*
+                ])
+            }
+
+            /// Enums that have all of the variants, or plain strings.
+            fn accepts(data_type: &::clickhouse_datatypes::DataType) -> bool {
+                let names: &[&str] = &[#(#names),*];
+                match data_type {
+                    ::clickhouse_datatypes::DataType::Enum8(values) => {
+                        names.iter().all(|name| {
+                            values.iter().any(|(value, _)| value == name)
+                        })
+                    }
+                    ::clickhouse_datatypes::DataType::Enum16(values) => {
+                        names.iter().all(|name| {
+                            values.iter().any(|(value, _)| value == name)
+                        })
+                    }
+                    ::clickhouse_datatypes::DataType::String => true,
+                    _ => false,
+                }
+            }
+        }
+    })
+}
+
+/// Writes a struct as the columns of a block, one column per field.
+///
+/// Columns are named after the fields, use `#[clickhouse(rename = "name")]`
+/// on a field whose column is named differently. Every field type has to
+/// implement `IntoColumn` and `ColumnType`, which decides what column types
+/// it can be written to.
+#[proc_macro_derive(Row, attributes(clickhouse))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_row(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "Row can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "Row can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut idents = Vec::with_capacity(fields.len());
+    let mut names = Vec::with_capacity(fields.len());
+    let mut types = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("fields are named");
+        names.push(clickhouse_name(field_ident, &field.attrs)?);
+        idents.push(field_ident);
+        types.push(&field.ty);
+    }
+    let indices = 0..fields.len();
+    let push_indices = 0..fields.len();
+
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::clickhouse_datatypes::Row
+            for #ident #ty_generics #where_clause
+        {
+            const COLUMN_NAMES: &'static [&'static str] = &[#(#names),*];
+
+            fn data_types() -> Vec<::clickhouse_datatypes::DataType> {
+                vec![#(
+                    <#types as ::clickhouse_datatypes::ColumnType>::data_type()
+                ),*]
+            }
+
+            fn accepts(
+                index: usize,
+                data_type: &::clickhouse_datatypes::DataType,
+            ) -> bool {
+                match index {
+                    #(#indices => ::clickhouse_datatypes::can_write::<#types>(
+                        data_type,
+                    ),)*
+                    _ => false,
+                }
+            }
+
+            fn push_fields(
+                self,
+                columns: &mut [::clickhouse_datatypes::Column],
+            ) -> ::clickhouse_datatypes::Result<()> {
+                #(::clickhouse_datatypes::push_field(
+                    &mut columns[#push_indices],
+                    #names,
+                    self.#idents,
+                )?;)*
+                Ok(())
+            }
+        }
+    })
+}
+
+/// The name of a variant or field, unless `#[clickhouse(rename = "name")]`
+/// gives another one.
+fn clickhouse_name(ident: &Ident, attrs: &[Attribute]) -> syn::Result<String> {
+    let mut name = ident.unraw().to_string();
+    for attr in attrs {
+        if !attr.path().is_ident("clickhouse") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unknown clickhouse attribute"))
+            }
+        })?;
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use super::expand_enum;
+
+    #[test]
+    fn test_too_many_variants() -> Result<()> {
+        for (variants, ok) in [(i16::MAX as usize, true), (1 << 15, false)] {
+            let body: Vec<String> =
+                (0..variants).map(|i| format!("V{i}")).collect();
+            let input = format!("enum E {{ {} }}", body.join(", "));
+            let expanded = expand_enum(syn::parse_str(&input)?);
+            assert_eq!(expanded.is_ok(), ok, "{variants} variants");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use clickhouse_datatypes::{ClickHouseEnum, Column, DataType, DataTypeError};
+
+#[derive(Debug, Clone, Copy, PartialEq, ClickHouseEnum)]
+enum Level {
+    Debug,
+    Info,
+    #[clickhouse(rename = "warning")]
+    Warn,
+}
+
+#[test]
+fn test_enum_column() -> Result<()> {
+    let data_type: DataType =
+        "Enum8('Debug' = 1, 'Info' = 2, 'warning' = 3)".parse()?;
+    let mut column = Column::new(&data_type);
+    column.push(Level::Warn)?;
+    column.push(Level::Debug)?;
+    assert_eq!(column.as_slice::<i8>(), Some(&[3, 1][..]));
+    assert_eq!(column.get::<Level>(0)?, Level::Warn);
+    assert_eq!(column.get::<Option<Level>>(1)?, Some(Level::Debug));
+    Ok(())
+}
+
+#[test]
+fn test_enum_missing_variant() -> Result<()> {
+    let mut column = Column::new(&"Enum8('Debug' = 1, 'Error' = 4)".parse()?);
+    assert!(matches!(
+        column.push(Level::Info),
+        Err(DataTypeError::UnknownEnumValue { .. })
+    ));
+    column.push("Error")?;
+    assert!(column.get::<Level>(0).is_err());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use clickhouse_datatypes::{
+    can_write, ClickHouseEnum, Column, ColumnType, DataType, Row,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ClickHouseEnum)]
+enum Kind {
+    Click,
+    View,
+}
+
+#[derive(Row)]
+struct Event<'a> {
+    id: u64,
+    #[clickhouse(rename = "event_name")]
+    name: &'a str,
+    kind: Kind,
+    r#type: Option<String>,
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_row_columns() -> Result<()> {
+    assert_eq!(
+        Event::COLUMN_NAMES,
+        ["id", "event_name", "kind", "type", "tags"]
+    );
+    let data_types: Vec<String> = Event::data_types()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        data_types,
+        [
+            "UInt64",
+            "String",
+            "Enum8('Click' = 1, 'View' = 2)",
+            "Nullable(String)",
+            "Array(String)"
+        ]
+    );
+
+    assert!(Event::accepts(
+        0,
+        &"SimpleAggregateFunction(any, UInt64)".parse()?
+    ));
+    assert!(Event::accepts(1, &"LowCardinality(String)".parse()?));
+    assert!(Event::accepts(
+        2,
+        &"Enum16('View' = 5, 'Click' = 300)".parse()?
+    ));
+    assert!(!Event::accepts(2, &"Enum8('Click' = 1)".parse()?));
+    assert!(!Event::accepts(3, &DataType::String));
+    assert!(!Event::accepts(5, &DataType::String));
+    assert!(can_write::<Kind>(&DataType::String));
+    assert_eq!(Kind::data_type().to_string(), data_types[2]);
+    Ok(())
+}
+
+#[test]
+fn test_row_append() -> Result<()> {
+    let types = [
+        "UInt64",
+        "LowCardinality(String)",
+        "Enum8('View' = 1, 'Click' = 2)",
+        "Nullable(String)",
+        "Array(String)",
+    ];
+    let mut columns = types
+        .iter()
+        .map(|data_type| Ok(Column::new(&data_type.parse()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let event = Event {
+        id: 7,
+        name: "home",
+        kind: Kind::Click,
+        r#type: None,
+        tags: vec!["a".into()],
+    };
+    event.append_to(&mut columns)?;
+    assert_eq!(columns[0].get::<u64>(0)?, 7);
+    assert_eq!(columns[1].get::<&str>(0)?, "home");
+    assert_eq!(columns[2].get::<i8>(0)?, 2);
+    assert_eq!(columns[3].get::<Option<String>>(0)?, None);
+    assert_eq!(columns[4].get::<Vec<String>>(0)?, ["a"]);
+    Ok(())
+}